@@ -7,15 +7,12 @@ pub mod semantic;
 use py_ir as ir;
 
 #[cfg(test)]
-fn parse_test(
-    chars: &str,
-    tester: impl FnOnce(&mut terl::Parser<py_lex::Token>) -> terl::Result<(), terl::ParseError>,
-) {
-    use terl::{Buffer, ResultMapperExt, Source};
+fn tokenize_test(chars: &str) -> (terl::Buffer<char>, terl::Parser<py_lex::Token>) {
+    use terl::{Buffer, ResultMapperExt};
 
     let source = Buffer::new("test.py1".to_string(), chars.chars().collect());
     let parser = terl::Parser::<char>::new(source);
-    let (char_buffer, mut parser) = parser
+    parser
         .process(|p| {
             let mut tokens = vec![];
 
@@ -24,7 +21,17 @@ fn parse_test(
             }
             Ok(tokens)
         })
-        .unwrap_or_else(|_| unreachable!());
+        .unwrap_or_else(|_| unreachable!())
+}
+
+#[cfg(test)]
+fn parse_test(
+    chars: &str,
+    tester: impl FnOnce(&mut terl::Parser<py_lex::Token>) -> terl::Result<(), terl::ParseError>,
+) {
+    use terl::Source;
+
+    let (char_buffer, mut parser) = tokenize_test(chars);
 
     if let Err(error) = tester(&mut parser) {
         let calling_tree = parser.calling_tree();
@@ -1,6 +1,7 @@
 use py_lex::{
     ops::{OperatorAssociativity, OperatorTypes, Operators},
     syntax::*,
+    types::TypePlaceholder,
 };
 
 use super::*;
@@ -17,7 +18,7 @@ fn escape(src: &Token, c: char) -> Result<char> {
         't' => '\t',
         'n' => '\n',
         's' => ' ',
-        _ => return src.throw(format!("Invalid or unsupported escape character: {}", c)),
+        _ => return src.throw(py_lex::msg!(InvalidEscape, char = c)),
     })
 }
 
@@ -28,7 +29,7 @@ impl ParseUnit<Token> for CharLiteral {
         p.r#match(Symbol::Char)?;
         let unparsed = p.parse::<Token>()?;
         if !(unparsed.len() == 1 || unparsed.len() == 2 && unparsed.starts_with('_')) {
-            return unparsed.throw(format!("Invalid CharLiteral {}", unparsed));
+            return unparsed.throw(py_lex::msg!(InvalidChar, literal = unparsed));
         }
         let parsed = if unparsed.len() == 1 {
             unparsed.as_bytes()[0] as char
@@ -65,7 +66,7 @@ impl ParseUnit<Token> for StringLiteral {
             }
         }
         if next_escape {
-            return unparsed.throw("Invalid escape! maybe you losted a character");
+            return unparsed.throw(py_lex::msg!(UnfinishedEscape));
         }
 
         Ok(StringLiteral { parsed })
@@ -75,7 +76,7 @@ impl ParseUnit<Token> for StringLiteral {
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum NumberLiteral {
     Float(f64),
-    Digit(usize),
+    Digit(u128),
 }
 
 impl ParseUnit<Token> for NumberLiteral {
@@ -88,8 +89,12 @@ impl ParseUnit<Token> for NumberLiteral {
         let Some(int) = int_dec.next() else {
             unreachable!()
         };
-        let int = match int.parse::<usize>() {
+        let int = match int.parse::<u128>() {
             Ok(int) => int,
+            Err(e) if *e.kind() == std::num::IntErrorKind::PosOverflow => {
+                let reason = py_lex::msg!(LiteralTooLarge, literal = int, max = u128::MAX);
+                return p.throw(reason);
+            }
             Err(e) => return p.unmatch(e),
         };
 
@@ -104,7 +109,7 @@ impl ParseUnit<Token> for NumberLiteral {
         };
         // check that if anything left
         if let Some(next) = int_dec.next() {
-            return p.unmatch(format!("unexpect {}", next));
+            return p.unmatch(py_lex::msg!(UnexpectedToken, token = next));
         }
 
         Ok(Self::Float(int as f64 + dec))
@@ -149,7 +154,10 @@ impl ParseUnit<Token> for FnCallArgs {
 #[derive(Debug, Clone)]
 pub struct FnCall {
     span: Span,
-    pub fn_name: Ident,
+    /// the instance which a method is called on, like `p de1 a` in `ya1 ru4 p de1 a de1 f`
+    pub receiver: Option<Member>,
+    /// the name of the function, or the method if there is a receiver
+    pub fn_name: PU<Ident>,
     pub args: FnCallArgs,
 }
 
@@ -164,9 +172,22 @@ impl ParseUnit<Token> for FnCall {
 
     fn parse(p: &mut Parser<Token>) -> ParseResult<Self, Token> {
         let args = p.parse::<FnCallArgs>()?;
-        let fn_name = p.parse::<Ident>()?;
+        let mut path = vec![p.parse::<PU<Ident>>()?];
+        while p.r#match(Symbol::GetElement).is_ok() {
+            path.push(p.parse::<PU<Ident>>().apply(mapper::MustMatch)?);
+        }
+        let fn_name = path.pop().unwrap();
+        let receiver = match path.is_empty() {
+            true => None,
+            false => {
+                let object = path.remove(0);
+                let fields = path;
+                Some(Member { object, fields })
+            }
+        };
 
         Ok(FnCall {
+            receiver,
             fn_name,
             args,
             span: p.get_span(),
@@ -174,8 +195,52 @@ impl ParseUnit<Token> for FnCall {
     }
 }
 
+impl FnCall {
+    /// the call itself, and calls in its arguments
+    pub fn fn_calls(&self) -> Vec<&FnCall> {
+        std::iter::once(self)
+            .chain(self.args.iter().flat_map(Expr::fn_calls))
+            .collect()
+    }
+}
+
 pub type Variable = Ident;
 
+/// a field of a value, like `p de1 a de1 x`
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub object: PU<Ident>,
+    /// names of fields, from the outermost one. it's not empty unless it's the receiver
+    /// of a method call
+    pub fields: Vec<PU<Ident>>,
+}
+
+impl WithSpan for Member {
+    fn get_span(&self) -> Span {
+        let object = self.object.get_span();
+        match self.fields.last() {
+            Some(last) => object.merge(last.get_span()),
+            None => object,
+        }
+    }
+}
+
+impl ParseUnit<Token> for Member {
+    type Target = Member;
+
+    fn parse(p: &mut Parser<Token>) -> ParseResult<Self, Token> {
+        let object = p.parse::<PU<Ident>>()?;
+        let mut fields = vec![];
+        while p.r#match(Symbol::GetElement).is_ok() {
+            fields.push(p.parse::<PU<Ident>>().apply(mapper::MustMatch)?);
+        }
+        if fields.is_empty() {
+            return p.unmatch(py_lex::msg!(ExpectThis, expect = Symbol::GetElement));
+        }
+        Ok(Member { object, fields })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Array {
     elements: Vec<Expr>,
@@ -203,18 +268,65 @@ impl ParseUnit<Token> for Array {
     }
 }
 
+/// an anonymous function which could use variables around it, like
+/// `bi4bao1 zheng3 can1 zheng3 x jie2 han2 fan3 x jia1 n fen1 jie2`
+///
+/// there is no syntax to choose how a variable is captured, it's decided by the body:
+/// - a variable which is assigned anywhere in the body (or in closures in it) is captured
+///   by reference, assignments change the variable itself and the closure sees values
+///   assigned to it after the closure is defined
+/// - other variables are captured by value, the closure keeps values they have when the
+///   closure is defined, and values assigned to them after that are not seen
+///
+/// closures can only be called, so references never outlive variables they refer to
+#[derive(Debug, Clone)]
+pub struct Closure {
+    /// [`None`] if the return type is omitted or written as `zi4dong4`, then it will be
+    /// inferred from values returned by `fan3`
+    pub ty: Option<types::TypeDefine>,
+    pub params: Parameters,
+    pub codes: CodeBlock,
+    /// from `bi4bao1` to the end of parameters
+    pub sign_span: Span,
+}
+
+impl ParseUnit<Token> for Closure {
+    type Target = Closure;
+
+    fn parse(p: &mut Parser<Token>) -> ParseResult<Self, Token> {
+        p.r#match(Symbol::Closure)?;
+        let ty = match p.r#match(RPU(TypePlaceholder::Auto)).apply(mapper::Try)? {
+            Some(..) => None,
+            None => p.parse::<types::TypeDefine>().apply(mapper::Try)?,
+        };
+        let params = p.parse::<PU<Parameters>>().apply(mapper::MustMatch)?;
+        let sign_span = p.get_span();
+        let codes = p.parse::<CodeBlock>().apply(mapper::MustMatch)?;
+        Ok(Closure {
+            ty,
+            params: params.take(),
+            codes,
+            sign_span,
+        })
+    }
+}
+
 complex_pu! {
+    #[allow(clippy::large_enum_variant)]
     cpu AtomicExpr {
         CharLiteral,
         StringLiteral,
         NumberLiteral,
         FnCall,
         Array,
+        Member,
+        Closure,
         Variable
     }
 }
 
 #[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
 pub enum ExprItem {
     AtomicExpr(PU<AtomicExpr>),
     Operators(PU<Operators>),
@@ -270,7 +382,7 @@ impl ParseUnit<Token> for ExprItems {
                     _ => None,
                 })
                 .nth(nth)
-                .map(|span| span.make_message("left bracket here"))
+                .map(|span| span.make_message(py_lex::msg!(LeftBracketHere)))
         };
 
         enum Expect {
@@ -309,11 +421,11 @@ impl ParseUnit<Token> for ExprItems {
                     } else if bracket_depth != 0 {
                         let left_bracket = left_bracket(&items, bracket_depth);
                         let current_span = p.get_span();
-                        let expect_next = format!("expect this to be `{}`", Operators::BracketR);
+                        let expect_next = py_lex::msg!(ExpectThis, expect = Operators::BracketR);
                         let expect_next = p
                             .parse::<PU<Token>>()
                             .map(|tk| tk.make_message(expect_next));
-                        break current_span.throw("unclosed bracket").map_err(|mut e| {
+                        break current_span.throw(py_lex::msg!(UnclosedBracket)).map_err(|mut e| {
                             e.extend(left_bracket);
                             e.extend(expect_next.ok());
                             e
@@ -347,6 +459,24 @@ impl std::ops::Deref for Expr {
     }
 }
 
+impl Expr {
+    /// function calls in the expression, including ones in arguments of calls
+    pub fn fn_calls(&self) -> Vec<&FnCall> {
+        self.items
+            .iter()
+            .flat_map(|item| match item {
+                ExprItem::AtomicExpr(atomic) => match &**atomic {
+                    AtomicExpr::FnCall(fn_call) => fn_call.fn_calls(),
+                    AtomicExpr::Array(array) => array.iter().flat_map(Expr::fn_calls).collect(),
+                    AtomicExpr::Closure(closure) => closure.codes.fn_calls(),
+                    _ => vec![],
+                },
+                ExprItem::Operators(..) => vec![],
+            })
+            .collect()
+    }
+}
+
 impl ParseUnit<Token> for Expr {
     type Target = Expr;
 
@@ -443,6 +573,16 @@ mod tests {
         })
     }
 
+    #[test]
+    #[should_panic]
+    fn number_too_large() {
+        // larger than u128::MAX
+        parse_test("340282366920938463463374607431768211456", |p| {
+            p.parse::<NumberLiteral>()?;
+            Ok(())
+        })
+    }
+
     #[test]
     fn function_call() {
         parse_test("ya1 1919810 fen1 chuan4 acminoac ru4 han2shu4", |p| {
@@ -451,6 +591,56 @@ mod tests {
         })
     }
 
+    #[test]
+    fn method_call() {
+        parse_test("ya1 1 ru4 p de1 a de1 mo2chang2", |p| {
+            let fn_call = p.parse::<FnCall>()?;
+            let receiver = fn_call.receiver.unwrap();
+            assert_eq!(&**receiver.object, "p");
+            assert_eq!(receiver.fields.len(), 1);
+            assert_eq!(&**fn_call.fn_name, "mo2chang2");
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn member() {
+        parse_test("p de1 a de1 x jia1 1", |p| {
+            let expr = p.parse::<Expr>()?;
+            let ExprItem::AtomicExpr(atomic) = &expr[0] else {
+                unreachable!()
+            };
+            assert!(matches!(&**atomic, AtomicExpr::Member(member) if member.fields.len() == 2));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn closure() {
+        parse_test("bi4bao1 zheng3 can1 zheng3 x jie2 han2 fan3 x jia1 n fen1 jie2", |p| {
+            let expr = p.parse::<Expr>()?;
+            let ExprItem::AtomicExpr(atomic) = &expr[0] else {
+                unreachable!()
+            };
+            let AtomicExpr::Closure(closure) = &**atomic else {
+                unreachable!()
+            };
+            assert!(closure.ty.is_some());
+            assert_eq!(closure.params.len(), 1);
+            assert_eq!(closure.codes.stmts.len(), 1);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn closure_with_inferred_return_type() {
+        parse_test("bi4bao1 can1 jie2 han2 fan3 1 fen1 jie2", |p| {
+            let closure = p.parse::<Closure>()?;
+            assert!(closure.ty.is_none() && closure.params.is_empty());
+            Ok(())
+        })
+    }
+
     #[test]
     fn unary() {
         parse_test("fei1 191810", |p| {
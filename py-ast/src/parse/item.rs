@@ -1,34 +1,126 @@
 use super::*;
-use py_lex::syntax::Symbol;
+use crate::complex_pu;
+use py_lex::{
+    ops::Operators,
+    syntax::{StructsDefinition, Symbol},
+    types::TypePlaceholder,
+};
 
 #[derive(Debug, Clone)]
-pub struct Comment;
+pub struct Comment {
+    /// note: span here is span in [`Buffer<char>`], between `shi4` and `jie2`
+    ///
+    /// whitespace and symbols are dropped while tokenizing, so the text of comment
+    /// can only be taken from source code
+    pub text: Span,
+}
 
 impl ParseUnit<Token> for Comment {
     type Target = Comment;
 
     fn parse(p: &mut Parser<Token>) -> ParseResult<Self, Token> {
-        p.r#match(Symbol::Comment)?;
+        let start = p.r#match(RPU(Symbol::Comment))?.get_span();
+        let start = p.buffer()[start.start].get_span().end;
 
         loop {
             let str = p
                 .parse::<Token>()
-                .map_err(|e| e.append("comment without ending"))?;
+                .map_err(|e| e.append(py_lex::msg!(UnclosedComment)))?;
 
             if &*str == "jie2" {
-                return Ok(Comment {});
+                let text = Span::new(start, str.get_span().start);
+                return Ok(Comment { text });
+            }
+        }
+    }
+}
+
+/// a type parameter of a generic function, and interfaces which its type arguments must
+/// implement, like `T shi2xian4 Ke3jia1`
+#[derive(Debug, Clone)]
+pub struct TypeParam {
+    pub name: Ident,
+    pub bounds: Vec<PU<Ident>>,
+}
+
+impl ParseUnit<Token> for TypeParam {
+    type Target = TypeParam;
+
+    fn parse(p: &mut Parser<Token>) -> ParseResult<Self, Token> {
+        let name = p.parse::<Ident>()?;
+        let mut bounds = vec![];
+        while p.r#match(Symbol::Implements).is_ok() {
+            bounds.push(p.parse::<PU<Ident>>().apply(mapper::MustMatch)?);
+        }
+        Ok(TypeParam { name, bounds })
+    }
+}
+
+/// type parameters of a generic function, like `fan4xing2 can1 T fen1 U jie2`
+#[derive(Debug, Clone)]
+pub struct TypeParams {
+    pub params: Vec<TypeParam>,
+}
+
+impl ParseUnit<Token> for TypeParams {
+    type Target = TypeParams;
+
+    fn parse(p: &mut Parser<Token>) -> ParseResult<Self, Token> {
+        p.r#match(Symbol::Generic)?;
+        p.r#match(Symbol::Parameter).apply(mapper::MustMatch)?;
+        let mut params = vec![p.parse::<TypeParam>().apply(mapper::MustMatch)?];
+        while p.r#match(Symbol::Semicolon).is_ok() {
+            params.push(p.parse::<TypeParam>().apply(mapper::MustMatch)?);
+        }
+        p.r#match(Symbol::EndOfBlock).apply(mapper::MustMatch)?;
+        Ok(TypeParams { params })
+    }
+}
+
+/// name of a function, an [`Ident`], or an operator like `yun4suan4 jia1`
+///
+/// the name of a function defined for an operator is the operator itself, which is a
+/// keyword, so it never conflicts with names of other functions
+///
+/// names of primitive types cant be names of functions, or a function whose return type
+/// is omitted would be named by the type when its name is missing
+#[derive(Debug, Clone, Copy)]
+pub struct FnName;
+
+impl ParseUnit<Token> for FnName {
+    type Target = Ident;
+
+    fn parse(p: &mut Parser<Token>) -> ParseResult<Self, Token> {
+        if p.r#match(RPU(Symbol::Operator))
+            .apply(mapper::Try)?
+            .is_none()
+        {
+            let name = p.parse::<Ident>()?;
+            if types::PRIMITIVE_TYPES.contains(&&*name) {
+                return p.unmatch(py_lex::msg!(KeywordAsIdent));
             }
+            return Ok(name);
         }
+        let op = p.parse::<Operators>().apply(mapper::MustMatch)?;
+        Ok(Ident(op.to_string()))
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct FnDefine {
+    /// `gong1kai1`, the function could be used outside the module which it's in
+    pub public: Option<Span>,
+    /// `dao3chu1`, the function is declared in the c header
     pub export: Option<Span>,
-    pub ty: types::TypeDefine,
-    pub name: Ident,
+    /// type parameters, empty if the function is not generic
+    pub generics: Vec<TypeParam>,
+    /// [`None`] if the return type is omitted or written as `zi4dong4`, then it will be
+    /// inferred from values returned by `fan3`
+    pub ty: Option<types::TypeDefine>,
+    pub name: PU<Ident>,
     pub params: Parameters,
     pub codes: CodeBlock,
+    /// an empty span before the name if the return type is omitted
     pub retty_span: Span,
     pub sign_span: Span,
 }
@@ -37,20 +129,53 @@ impl ParseUnit<Token> for FnDefine {
     type Target = FnDefine;
 
     fn parse(p: &mut Parser<Token>) -> ParseResult<Self, Token> {
+        let public = p
+            .r#match(RPU(Symbol::Public))
+            .apply(mapper::Try)?
+            .map(|pu| pu.get_span());
         let export = p
             .r#match(RPU(Symbol::Export))
             .apply(mapper::Try)?
             .map(|pu| pu.get_span());
-        let ty = p.parse::<PU<types::TypeDefine>>()?;
-        let name = p.parse::<Ident>()?;
+        let generics = p
+            .parse::<TypeParams>()
+            .apply(mapper::Try)?
+            .map(|params| params.params)
+            .unwrap_or_default();
+        // the return type may be omitted, so it must be parsed with the name
+        let typed = p
+            .once(|p| {
+                Ok((
+                    p.parse::<PU<types::TypeDefine>>()?,
+                    p.parse::<PU<FnName>>()?,
+                ))
+            })
+            .apply(mapper::Try)?;
+        let (ty, retty_span, name) = match typed {
+            Some((ty, name)) => {
+                let retty_span = ty.get_span();
+                (Some(ty.take()), retty_span, name)
+            }
+            None => {
+                let auto = p.r#match(RPU(TypePlaceholder::Auto)).apply(mapper::Try)?;
+                let name = p.parse::<PU<FnName>>()?;
+                let retty_span = match auto {
+                    Some(auto) => auto.get_span(),
+                    None => Span::new(name.get_span().start, name.get_span().start),
+                };
+                (None, retty_span, name)
+            }
+        };
         let params = p.parse::<PU<Parameters>>()?;
         let codes = p.parse::<CodeBlock>().apply(mapper::MustMatch)?;
 
         Ok(Self {
+            public,
             export,
-            retty_span: ty.get_span(),
-            sign_span: ty.get_span().merge(params.get_span()),
-            ty: ty.take(),
+            generics,
+            retty_span,
+            sign_span: retty_span.merge(params.get_span()),
+            ty,
             name,
             params: params.take(),
             codes,
@@ -58,9 +183,228 @@ impl ParseUnit<Token> for FnDefine {
     }
 }
 
+impl FnDefine {
+    /// the operator which the function is defined for, like `yun4suan4 jia1`
+    pub fn operator(&self) -> Option<Operators> {
+        Operators::from_keyword(&self.name)
+    }
+}
+
+/// a field of a class, like `zheng3 x fen1`
+#[derive(Debug, Clone)]
+pub struct FieldDefine {
+    pub ty: PU<types::TypeDefine>,
+    pub name: Ident,
+}
+
+impl ParseUnit<Token> for FieldDefine {
+    type Target = FieldDefine;
+
+    fn parse(p: &mut Parser<Token>) -> ParseResult<Self, Token> {
+        let ty = p.parse::<PU<types::TypeDefine>>()?;
+        let name = p.parse::<Ident>()?;
+        p.r#match(Symbol::Semicolon).apply(mapper::MustMatch)?;
+        Ok(FieldDefine { ty, name })
+    }
+}
+
+complex_pu! {
+    #[allow(clippy::large_enum_variant)]
+    cpu ClassItem {
+        // $ty $name (...)
+        FnDefine,
+        // $ty $name
+        FieldDefine,
+        Comment
+    }
+}
+
+/// a class with fields and methods, like `lei4 Dian3 han2 zheng3 x fen1 ... jie2`
+///
+/// the first parameter of a method receives the instance which it's called on. it's passed
+/// by value like other arguments, and parameters are immutable, so a method cant change
+/// the instance
+#[derive(Debug, Clone)]
+pub struct Class {
+    pub name: PU<Ident>,
+    /// interfaces which the class claims to implement, like `shi2xian4 Ke3jia1`
+    pub implements: Vec<PU<Ident>>,
+    pub items: Vec<PU<ClassItem>>,
+}
+
+impl ParseUnit<Token> for Class {
+    type Target = Class;
+
+    fn parse(p: &mut Parser<Token>) -> ParseResult<Self, Token> {
+        p.r#match(StructsDefinition::Classs)?;
+        let name = p.parse::<PU<Ident>>().apply(mapper::MustMatch)?;
+        let mut implements = vec![];
+        while p.r#match(Symbol::Implements).is_ok() {
+            implements.push(p.parse::<PU<Ident>>().apply(mapper::MustMatch)?);
+        }
+        p.r#match(Symbol::Block).apply(mapper::MustMatch)?;
+        let mut items = vec![];
+        while p
+            .peek_token()
+            .is_some_and(|next| **next != *Symbol::EndOfBlock)
+        {
+            items.push(p.parse::<PU<ClassItem>>().apply(mapper::MustMatch)?);
+        }
+        p.r#match(Symbol::EndOfBlock).apply(mapper::MustMatch)?;
+        Ok(Class {
+            name,
+            implements,
+            items,
+        })
+    }
+}
+
+impl Class {
+    pub fn fields(&self) -> impl Iterator<Item = (Span, &FieldDefine)> {
+        self.items.iter().filter_map(|item| match &**item {
+            ClassItem::FieldDefine(field) => Some((item.get_span(), field)),
+            _ => None,
+        })
+    }
+
+    pub fn methods(&self) -> impl Iterator<Item = &FnDefine> {
+        self.items.iter().filter_map(|item| match &**item {
+            ClassItem::FnDefine(method) => Some(method),
+            _ => None,
+        })
+    }
+}
+
+/// a signature which types implementing an interface must have a function with, like
+/// `T jia can1 T a fen1 T b jie2 fen1`
+#[derive(Debug, Clone)]
+pub struct FnSignDefine {
+    pub ty: PU<types::TypeDefine>,
+    pub name: Ident,
+    pub params: Parameters,
+    pub sign_span: Span,
+}
+
+impl ParseUnit<Token> for FnSignDefine {
+    type Target = FnSignDefine;
+
+    fn parse(p: &mut Parser<Token>) -> ParseResult<Self, Token> {
+        let ty = p.parse::<PU<types::TypeDefine>>()?;
+        let name = p.parse::<FnName>()?;
+        let params = p.parse::<PU<Parameters>>()?;
+        p.r#match(Symbol::Semicolon).apply(mapper::MustMatch)?;
+        Ok(FnSignDefine {
+            sign_span: ty.get_span().merge(params.get_span()),
+            ty,
+            name,
+            params: params.take(),
+        })
+    }
+}
+
+complex_pu! {
+    #[allow(clippy::large_enum_variant)]
+    cpu InterfaceItem {
+        // $ty $name (...);
+        FnSignDefine,
+        Comment
+    }
+}
+
+/// functions which types implementing the interface must have, like
+/// `jie1kou3 Ke3jia1 can1 T jie2 han2 T jia can1 T a fen1 T b jie2 fen1 jie2`
+///
+/// `T` in signatures is the type which implements the interface
+#[derive(Debug, Clone)]
+pub struct Interface {
+    pub name: PU<Ident>,
+    pub this: PU<Ident>,
+    pub items: Vec<PU<InterfaceItem>>,
+}
+
+impl ParseUnit<Token> for Interface {
+    type Target = Interface;
+
+    fn parse(p: &mut Parser<Token>) -> ParseResult<Self, Token> {
+        p.r#match(StructsDefinition::Interface)?;
+        let name = p.parse::<PU<Ident>>().apply(mapper::MustMatch)?;
+        p.r#match(Symbol::Parameter).apply(mapper::MustMatch)?;
+        let this = p.parse::<PU<Ident>>().apply(mapper::MustMatch)?;
+        p.r#match(Symbol::EndOfBlock).apply(mapper::MustMatch)?;
+        p.r#match(Symbol::Block).apply(mapper::MustMatch)?;
+        let mut items = vec![];
+        while p
+            .peek_token()
+            .is_some_and(|next| **next != *Symbol::EndOfBlock)
+        {
+            items.push(p.parse::<PU<InterfaceItem>>().apply(mapper::MustMatch)?);
+        }
+        p.r#match(Symbol::EndOfBlock).apply(mapper::MustMatch)?;
+        Ok(Interface { name, this, items })
+    }
+}
+
+impl Interface {
+    pub fn fns(&self) -> impl Iterator<Item = &FnSignDefine> {
+        self.items.iter().filter_map(|item| match &**item {
+            InterfaceItem::FnSignDefine(sign) => Some(sign),
+            InterfaceItem::Comment(..) => None,
+        })
+    }
+}
+
+/// a module, like `mo2kuai4 shu4xue2 han2 ... jie2`
+///
+/// items in a module can only be used in it and modules in it, unless they are marked
+/// with `gong1kai1`. items in other modules are used by paths like `shu4xue2 de1 jia`
+///
+/// `gong1kai1` only decides where items could be used, functions are declared in the c
+/// header only if they are marked with `dao3chu1`
+///
+/// classes and interfaces cant be defined in modules, because types are not qualified by
+/// paths of modules
+#[derive(Debug, Clone)]
+pub struct Module {
+    /// `gong1kai1`, the module could be used outside the module which it's in
+    pub public: Option<Span>,
+    pub name: PU<Ident>,
+    pub items: Vec<PU<Item>>,
+    /// errors of items in the module, including ones which cant be parsed and are skipped
+    pub errors: Vec<Error>,
+}
+
+impl ParseUnit<Token> for Module {
+    type Target = Module;
+
+    fn parse(p: &mut Parser<Token>) -> ParseResult<Self, Token> {
+        let public = p
+            .r#match(RPU(Symbol::Public))
+            .apply(mapper::Try)?
+            .map(|pu| pu.get_span());
+        p.r#match(Symbol::Module)?;
+        let name = p.parse::<PU<Ident>>().apply(mapper::MustMatch)?;
+        p.r#match(Symbol::Block).apply(mapper::MustMatch)?;
+        let (items, errors) = parse_items_in(p, true);
+        p.r#match(Symbol::EndOfBlock).apply(mapper::MustMatch)?;
+        Ok(Module {
+            public,
+            name,
+            items,
+            errors,
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CodeBlock {
-    pub stmts: Vec<Statement>,
+    span: Span,
+    pub stmts: Vec<PU<Statement>>,
+}
+
+impl WithSpan for CodeBlock {
+    fn get_span(&self) -> Span {
+        self.span
+    }
 }
 
 impl ParseUnit<Token> for CodeBlock {
@@ -69,11 +413,60 @@ impl ParseUnit<Token> for CodeBlock {
     fn parse(p: &mut Parser<Token>) -> ParseResult<Self, Token> {
         p.r#match(Symbol::Block)?;
         let mut stmts = vec![];
-        while let Some(stmt) = p.parse::<Statement>().apply(mapper::Try)? {
-            stmts.push(stmt)
+        // no statement starts with `jie2`, so it must be the end of the block
+        while p
+            .peek_token()
+            .is_some_and(|next| **next != *Symbol::EndOfBlock)
+        {
+            let error = match p.parse::<PU<Statement>>() {
+                Ok(stmt) => {
+                    stmts.push(stmt);
+                    continue;
+                }
+                Err(error) => error,
+            };
+            let skipped = p.parse::<PU<SkipStatement>>()?;
+            let error = match error.kind() {
+                ParseErrorKind::Unmatch => {
+                    let start = skipped.get_span().start;
+                    Span::new(start, start + 1).make_error(py_lex::msg!(ExpectStatement))
+                }
+                ParseErrorKind::Semantic => error.error(),
+            };
+            stmts.push(skipped.map(|_| Invalid { error }.into()));
         }
         p.r#match(Symbol::EndOfBlock)?;
-        Ok(Self { stmts })
+        Ok(Self {
+            span: p.get_span(),
+            stmts,
+        })
+    }
+}
+
+impl CodeBlock {
+    /// errors of statements which cant be parsed, including ones in nested blocks
+    pub fn errors(&self) -> Vec<&Error> {
+        self.stmts.iter().flat_map(|stmt| stmt.errors()).collect()
+    }
+
+    /// function calls in the block, including ones in nested blocks
+    pub fn fn_calls(&self) -> Vec<&FnCall> {
+        self.stmts.iter().flat_map(|stmt| stmt.fn_calls()).collect()
+    }
+}
+
+impl Item {
+    /// errors of statements which cant be parsed in the item
+    ///
+    /// items with errors can be used for semantic analysis, functions are registered
+    /// by their signatures, but their bodies are not generated
+    pub fn errors(&self) -> Vec<&Error> {
+        match self {
+            Item::FnDefine(define) => define.codes.errors(),
+            Item::Class(class) => class.methods().flat_map(|m| m.codes.errors()).collect(),
+            Item::Module(module) => module.errors.iter().collect(),
+            Item::Interface(..) | Item::Comment(..) => vec![],
+        }
     }
 }
 
@@ -104,6 +497,59 @@ mod tests {
         );
     }
 
+    #[test]
+    fn inferred_return_type() {
+        for src in ["zi4dong4 f can1 jie2 han2 jie2", "f can1 jie2 han2 jie2"] {
+            parse_test(src, |p| {
+                let define = p.parse::<FnDefine>()?;
+                assert!(define.ty.is_none());
+                assert_eq!(&**define.name, "f");
+                Ok(())
+            });
+        }
+    }
+
+    #[test]
+    fn keywords_as_function_names() {
+        // `zheng3` is the return type, not the name of a function without return type
+        for src in ["zheng3 can1 jie2 han2 jie2", "fu2 can1 jie2 han2 jie2"] {
+            parse_test(src, |p| {
+                assert!(p.parse::<FnDefine>().is_err());
+                Ok(())
+            });
+        }
+        parse_test("zheng3 ruo4 can1 jie2 han2 jie2", |p| {
+            assert!(p.parse::<FnDefine>().is_err());
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn generic_function_define() {
+        let src = "fan4xing2 can1 T fen1 U jie2 T f can1 T x fen1 U y jie2 han2 fan3 x fen1 jie2";
+        parse_test(src, |p| {
+            let define = p.parse::<FnDefine>()?;
+            let generics = define
+                .generics
+                .iter()
+                .map(|param| &*param.name)
+                .collect::<Vec<_>>();
+            assert_eq!(generics, ["T", "U"]);
+            assert!(define.generics.iter().all(|param| param.bounds.is_empty()));
+            assert_eq!(&**define.name, "f");
+            Ok(())
+        });
+    }
+
+    #[test]
+    #[should_panic]
+    fn generic_function_without_type_params() {
+        parse_test("fan4xing2 can1 jie2 zheng3 f can1 jie2 han2 jie2", |p| {
+            p.parse::<FnDefine>()?;
+            Ok(())
+        });
+    }
+
     #[test]
     fn complex_funcion_define() {
         parse_test(
@@ -123,6 +569,88 @@ mod tests {
         )
     }
 
+    #[test]
+    fn class_define() {
+        let src = "lei4 Dian3 han2
+                       zheng3 x fen1
+                       shi4 a comment jie2
+                       zheng3 y fen1
+                       zheng3 he can1 Dian3 p jie2 han2 fan3 p de1 x jia1 p de1 y fen1 jie2
+                   jie2";
+        parse_test(src, |p| {
+            let class = p.parse::<Class>()?;
+            assert_eq!(&**class.name, "Dian3");
+            assert_eq!(class.fields().count(), 2);
+            assert_eq!(class.methods().count(), 1);
+            Ok(())
+        });
+    }
+
+    #[test]
+    #[should_panic]
+    fn field_without_semicolon() {
+        parse_test("lei4 Dian3 han2 zheng3 x jie2", |p| {
+            p.parse::<Class>()?;
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn interface_define() {
+        let src = "jie1kou3 Ke3jia1 can1 T jie2 han2
+                       T jia can1 T a fen1 T b jie2 fen1
+                       shi4 a comment jie2
+                   jie2";
+        parse_test(src, |p| {
+            let interface = p.parse::<Interface>()?;
+            assert_eq!(&**interface.name, "Ke3jia1");
+            assert_eq!(&**interface.this, "T");
+            let fns = interface.fns().collect::<Vec<_>>();
+            assert_eq!(fns.len(), 1);
+            assert_eq!(&*fns[0].name, "jia");
+            assert_eq!(fns[0].params.len(), 2);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn bounded_generics() {
+        let src = "lei4 Dian3 shi2xian4 Ke3jia1 shi2xian4 Ke3bi3 han2 jie2
+                   fan4xing2 can1 T shi2xian4 Ke3jia1 fen1 U jie2
+                   T f can1 T x fen1 U y jie2 han2 fan3 x fen1 jie2";
+        parse_test(src, |p| {
+            let class = p.parse::<Class>()?;
+            assert_eq!(class.implements.len(), 2);
+            let define = p.parse::<FnDefine>()?;
+            assert_eq!(&**define.generics[0].bounds[0], "Ke3jia1");
+            assert!(define.generics[1].bounds.is_empty());
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn operator_define() {
+        let src = "Dian3 yun4suan4 jia1 can1 Dian3 a fen1 Dian3 b jie2 han2 fan3 a fen1 jie2
+                   jie1kou3 Ke3bi3 can1 T jie2 han2 zheng3 yun4suan4 xiao3 can1 T a fen1 T b jie2 fen1 jie2";
+        parse_test(src, |p| {
+            let define = p.parse::<FnDefine>()?;
+            assert_eq!(&**define.name, "jia1");
+            assert_eq!(define.operator(), Some(Operators::Add));
+            let interface = p.parse::<Interface>()?;
+            assert_eq!(&*interface.fns().next().unwrap().name, "xiao3");
+            Ok(())
+        });
+    }
+
+    #[test]
+    #[should_panic]
+    fn operator_without_operator() {
+        parse_test("Dian3 yun4suan4 can1 Dian3 a jie2 han2 jie2", |p| {
+            p.parse::<FnDefine>()?;
+            Ok(())
+        });
+    }
+
     #[test]
     fn comment() {
         parse_test("shi4 ehhhaaaaaaaaaaaaaaaaaaaaaaaa jie2", |p| {
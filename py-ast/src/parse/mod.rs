@@ -14,6 +14,190 @@ pub use item::*;
 pub use stmt::*;
 pub use types::*;
 
+type KeywordsSet = std::thread::LocalKey<std::collections::HashSet<&'static str>>;
+
+static KEPPING_KEYWORDS: [&KeywordsSet; 5] = [
+    &py_lex::ops::KEPPING_KEYWORDS,
+    &py_lex::ops::sub_classes::KEPPING_KEYWORDS,
+    &py_lex::preprocess::KEPPING_KEYWORDS,
+    &py_lex::syntax::KEPPING_KEYWORDS,
+    &py_lex::types::KEPPING_KEYWORDS,
+];
+
+/// keeping keywords cant be used as identifiers
+pub fn is_keyword(word: &str) -> bool {
+    KEPPING_KEYWORDS
+        .iter()
+        .any(|keeps| keeps.with(|keeps| keeps.contains(word)))
+}
+
+/// all keeping keywords, sorted
+pub fn keywords() -> Vec<&'static str> {
+    let mut keywords = KEPPING_KEYWORDS
+        .iter()
+        .flat_map(|keeps| keeps.with(|keeps| keeps.iter().copied().collect::<Vec<_>>()))
+        .collect::<Vec<_>>();
+    keywords.sort_unstable();
+    keywords.dedup();
+    keywords
+}
+
+/// skip tokens which cant be parsed, until the end of the statement(`fen1`), or until
+/// the end of the block(`jie2`) which contains them
+///
+/// if `item` is set, tokens are skipped until the end of the function body instead. an
+/// item without a body is skipped until the next item, or until the `jie2` which ends the
+/// module containing it
+///
+/// `fen1` and `jie2` of blocks in skipped tokens are skipped too, the `jie2` which
+/// ends the block containing the statement is not skipped
+fn skip(p: &mut Parser<Token>, item: bool) {
+    use py_lex::syntax::{ControlFlow, Symbol};
+
+    // unclosed `han2`, `can1`, `ya1`, `zu3` and `jie2` (as bracket)
+    let mut openings: Vec<String> = vec![];
+    let mut skipped = false;
+    while let Some(next) = p.peek_token() {
+        let word = next.string.clone();
+        let is = |keyword: &str| word == keyword;
+        if openings.is_empty() && !item && is(&Symbol::EndOfBlock) {
+            return;
+        }
+        let top = openings.is_empty() && item && skipped;
+        if top && (is(&Symbol::EndOfBlock) || item_follows(p)) {
+            return;
+        }
+        p.next_token();
+        skipped = true;
+
+        if is(&Symbol::Comment) {
+            while p.next_token().is_some_and(|token| **token != *Symbol::EndOfBlock) {}
+        } else if is(&Symbol::Block)
+            || is(&Symbol::Parameter)
+            || is(&Symbol::FnCallL)
+            || is(&Symbol::ArrayL)
+        {
+            openings.push(word);
+        } else if is(&Symbol::EndOfBlock) {
+            let closing = openings.last().map(|opening| opening.as_str());
+            if closing == Some(&*Symbol::Parameter) {
+                openings.pop();
+            } else if closing == Some(&*Symbol::Block) {
+                openings.pop();
+                // the end of statements with blocks, like `ruo4` and `chong2`
+                let else_ = p.peek_token().is_some_and(|next| **next == *ControlFlow::Else);
+                if openings.is_empty() && !else_ {
+                    return;
+                }
+            } else if openings.is_empty() {
+                // a `jie2` which cant start an item
+                return;
+            } else {
+                // must be a bracket
+                openings.push(word);
+            }
+        } else if is(&Symbol::FnCallR) {
+            if openings.last().is_some_and(|last| *last == *Symbol::FnCallL) {
+                openings.pop();
+            }
+        } else if is(&Symbol::BracketR) {
+            // `he2` closes both arrays and brackets
+            if openings
+                .last()
+                .is_some_and(|last| *last == *Symbol::ArrayL || *last == *Symbol::BracketL)
+            {
+                openings.pop();
+            }
+        } else if is(&Symbol::Semicolon) && openings.is_empty() && !item {
+            return;
+        }
+    }
+}
+
+/// if an item can be parsed from the next token, the parser is not moved
+fn item_follows(p: &mut Parser<Token>) -> bool {
+    let mut follows = false;
+    let _ = p.once(|p| {
+        follows = p.parse::<Item>().is_ok();
+        // always fails, so that nothing is taken from the parser
+        p.unmatch::<()>(py_lex::msg!(ExpectFnDefine))
+    });
+    follows
+}
+
+/// skip a statement which cant be parsed, see [`skip`]
+#[derive(Debug)]
+struct SkipStatement;
+
+impl ParseUnit<Token> for SkipStatement {
+    type Target = ();
+
+    fn parse(p: &mut Parser<Token>) -> ParseResult<Self, Token> {
+        skip(p, false);
+        Ok(())
+    }
+}
+
+/// skip an item which cant be parsed, see [`skip`]
+#[derive(Debug)]
+struct SkipItem;
+
+impl ParseUnit<Token> for SkipItem {
+    type Target = ();
+
+    fn parse(p: &mut Parser<Token>) -> ParseResult<Self, Token> {
+        skip(p, true);
+        Ok(())
+    }
+}
+
+/// parse all items in the source code, and recover from syntax errors
+///
+/// items are parsed until the end of tokens, or until the [`Token::end_of_file`] which
+/// ends the source file
+///
+/// statements which cant be parsed are replaced with [`Invalid`], and items which cant
+/// be parsed are skipped, all of these errors are returned in order
+///
+/// use [`Item::errors`] to know if an item is parsed cleanly
+pub fn parse_items(p: &mut Parser<Token>) -> (Vec<Item>, Vec<Error>) {
+    let (items, errors) = parse_items_in(p, false);
+    (items.into_iter().map(PU::take).collect(), errors)
+}
+
+/// parse items until the end of the source code, or until the `jie2` which ends the
+/// module if `in_module` is set, see [`parse_items`]
+fn parse_items_in(p: &mut Parser<Token>, in_module: bool) -> (Vec<PU<Item>>, Vec<Error>) {
+    let mut items = vec![];
+    let mut errors = vec![];
+    let end = |next: &Token| in_module && **next == *py_lex::syntax::Symbol::EndOfBlock;
+    while p.peek_token().is_some_and(|next| !end(next)) {
+        match p.parse::<PU<Item>>() {
+            Ok(item) => {
+                errors.extend(item.errors().into_iter().cloned());
+                items.push(item);
+            }
+            Err(error) => {
+                let skipped = p.parse::<PU<SkipItem>>().unwrap_or_else(|_| unreachable!());
+                // outside modules, the `jie2` which the item is skipped until must end
+                // the item itself, like a function whose `han2` is missing
+                if !in_module {
+                    let _ = p.r#match(py_lex::syntax::Symbol::EndOfBlock);
+                }
+                let error = match error.kind() {
+                    ParseErrorKind::Unmatch => {
+                        let start = skipped.get_span().start;
+                        Span::new(start, start + 1).make_error(py_lex::msg!(ExpectFnDefine))
+                    }
+                    ParseErrorKind::Semantic => error.error(),
+                };
+                errors.push(error);
+            }
+        }
+    }
+    (items, errors)
+}
+
 #[derive(Debug, Clone)]
 pub struct Ident(String);
 
@@ -35,28 +219,16 @@ impl ParseUnit<Token> for Ident {
     type Target = Ident;
 
     fn parse(p: &mut Parser<Token>) -> ParseResult<Self, Token> {
-        let Some(token) = p.next() else {
-            return p.unmatch("expect a `Ident`, but no token left");
+        let Some(token) = p.next_token() else {
+            return p.unmatch(py_lex::msg!(IdentButEof));
         };
 
         if token.chars().next().is_some_and(|c| c.is_ascii_digit()) {
-            return p.unmatch("bad ident! ident should not start with a digit");
+            return p.unmatch(py_lex::msg!(IdentStartsWithDigit));
         }
 
-        use py_lex::*;
-
-        let keeps = &[
-            ops::KEPPING_KEYWORDS,
-            ops::sub_classes::KEPPING_KEYWORDS,
-            preprocess::KEPPING_KEYWORDS,
-            syntax::KEPPING_KEYWORDS,
-            types::KEPPING_KEYWORDS,
-        ];
-
-        for keeps in keeps {
-            if keeps.with(|keeps| keeps.contains(&**token)) {
-                return p.unmatch("keeping keywords could not be ident");
-            }
+        if is_keyword(token) {
+            return p.unmatch(py_lex::msg!(KeywordAsIdent));
         }
 
         // keeping keywords cant be used as identifiers
@@ -125,6 +297,73 @@ mod tests {
         })
     }
 
+    #[test]
+    fn recover_from_errors() {
+        let src = "
+        zheng3 zhu3 can1 jie2 han2
+            zheng3 a wei2 fen1
+            ruo4 can1 a jie2 han2 1 2 fen1 jie2
+            fan3 a fen1
+        jie2
+        zheng3 can1 jie2 han2 fan3 0 fen1 jie2
+        zheng3 f can1 jie2 han2 fan3 0 fen1 jie2";
+        parse_test(src, |p| {
+            let (items, errors) = parse_items(p);
+            assert_eq!(errors.len(), 3);
+            assert_eq!(items.len(), 2);
+            assert_eq!(items[0].errors().len(), 2);
+            assert!(items[1].errors().is_empty());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn recover_in_modules() {
+        let src = "
+        gong1kai1 mo2kuai4 shu4 han2
+            mo2kuai4 nei4 han2
+                zheng3 1 can1 jie2 han2 fan3 0 fen1 jie2
+                zheng3 g can1 jie2 han2 fan3 0 fen1 jie2
+            jie2
+            gong1kai1 zheng3 f can1 jie2 han2 fan3 ya1 ru4 nei4 de1 g fen1 jie2
+        jie2
+        zheng3 zhu3 can1 jie2 han2 fan3 ya1 ru4 shu4 de1 f fen1 jie2";
+        parse_test(src, |p| {
+            let (items, errors) = parse_items(p);
+            assert_eq!(errors.len(), 1);
+            assert_eq!(items.len(), 2);
+            let Item::Module(module) = &items[0] else {
+                unreachable!()
+            };
+            assert!(module.public.is_some());
+            assert_eq!(module.items.len(), 2);
+            assert_eq!(items[0].errors().len(), 1);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn recover_from_items_without_body() {
+        let src = "
+        zheng3 f can1 jie2 fan3 0 fen1 jie2
+        zheng3 g can1 jie2 han2 fan3 0 fen1 jie2
+        mo2kuai4 shu4 han2
+            zheng3 h can1 jie2 fan3 0 fen1
+        jie2
+        zheng3 zhu3 can1 jie2 han2 fan3 0 fen1 jie2";
+        parse_test(src, |p| {
+            let (items, errors) = parse_items(p);
+            assert_eq!(errors.len(), 2);
+            assert_eq!(items.len(), 3);
+            let Item::Module(module) = &items[1] else {
+                unreachable!()
+            };
+            assert!(module.items.is_empty());
+            assert_eq!(module.errors.len(), 1);
+            Ok(())
+        })
+    }
+
     #[test]
     #[should_panic]
     fn bad_ident() {
@@ -2,7 +2,7 @@ use crate::complex_pu;
 
 use super::*;
 
-use py_lex::syntax::Symbol;
+use py_lex::{syntax::Symbol, types::TypePlaceholder};
 
 #[derive(Debug, Clone)]
 pub struct VarAssign {
@@ -21,8 +21,11 @@ impl ParseUnit<Token> for VarAssign {
 
 #[derive(Debug, Clone)]
 pub struct VarDefine {
-    pub ty: PU<types::TypeDefine>,
-    pub name: Ident,
+    /// [`None`] if the type is written as `zi4dong4`, then it will be inferred from the
+    /// initial value and uses of the variable
+    pub ty: PU<Option<types::TypeDefine>>,
+    pub name: PU<Ident>,
+    /// must be [`Some`] if the type is inferred
     pub init: Option<VarAssign>,
 }
 
@@ -30,16 +33,39 @@ impl ParseUnit<Token> for VarDefine {
     type Target = VarDefine;
 
     fn parse(p: &mut Parser<Token>) -> ParseResult<Self, Token> {
-        let ty = p.parse::<PU<types::TypeDefine>>()?;
-        let name = p.parse::<Ident>()?;
-        let init = p.parse::<VarAssign>().apply(mapper::Try)?;
+        let auto = p.r#match(RPU(TypePlaceholder::Auto)).apply(mapper::Try)?;
+        let ty = match auto {
+            Some(auto) => PU::new(auto.get_span(), None),
+            None => p.parse::<PU<types::TypeDefine>>()?.map(Some),
+        };
+        let name = p.parse::<PU<Ident>>()?;
+        let init = match ty.is_some() {
+            true => p.parse::<VarAssign>().apply(mapper::Try)?,
+            false => Some(p.parse::<VarAssign>().apply(mapper::MustMatch)?),
+        };
         Ok(Self { ty, name, init })
     }
 }
 
+impl VarDefine {
+    /// the closure if the variable is initialized by it directly
+    pub fn closure(&self) -> Option<&Closure> {
+        match &self.init.as_ref()?.val[..] {
+            [ExprItem::AtomicExpr(atomic)] => match &**atomic {
+                AtomicExpr::Closure(closure) => Some(closure),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct VarStore {
-    pub name: Ident,
+    pub name: PU<Ident>,
+    /// the value is stored into the field of the variable if it's not empty, like
+    /// `p de1 a de1 x wei2 1`
+    pub fields: Vec<PU<Ident>>,
     pub assign: PU<VarAssign>,
 }
 
@@ -47,24 +73,24 @@ impl ParseUnit<Token> for VarStore {
     type Target = VarStore;
 
     fn parse(p: &mut Parser<Token>) -> ParseResult<Self, Token> {
-        let name = p.parse::<Ident>()?;
+        let name = p.parse::<PU<Ident>>()?;
+        let mut fields = vec![];
+        while p.r#match(Symbol::GetElement).is_ok() {
+            fields.push(p.parse::<PU<Ident>>().apply(mapper::MustMatch)?);
+        }
         let assign = p.parse::<PU<VarAssign>>()?;
-        Ok(VarStore { name, assign })
+        Ok(VarStore {
+            name,
+            fields,
+            assign,
+        })
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct Parameter {
-    /// so to make semantic cheaking easier
-    inner: VarDefine,
-}
-
-impl std::ops::Deref for Parameter {
-    type Target = VarDefine;
-
-    fn deref(&self) -> &Self::Target {
-        &self.inner
-    }
+    pub ty: PU<types::TypeDefine>,
+    pub name: PU<Ident>,
 }
 
 impl ParseUnit<Token> for Parameter {
@@ -72,13 +98,8 @@ impl ParseUnit<Token> for Parameter {
 
     fn parse(p: &mut Parser<Token>) -> ParseResult<Self, Token> {
         let ty = p.parse::<PU<types::TypeDefine>>()?;
-        let name = p.parse::<Ident>()?;
-        let inner = VarDefine {
-            ty,
-            name,
-            init: None,
-        };
-        Ok(Parameter { inner })
+        let name = p.parse::<PU<Ident>>()?;
+        Ok(Parameter { ty, name })
     }
 }
 
@@ -166,6 +187,8 @@ statement_wrapper! {
 }
 
 /// be different from [`crate::complex_pu`], this version using box to make [`Statement`] enum smaller
+///
+/// placeholders are variants which will never be parsed
 macro_rules! statements {
     (
         $(#[$metas:meta])*
@@ -174,7 +197,11 @@ macro_rules! statements {
             $(#[$v_metas:meta])*
             $variant:ident
         ),*
-    }) => {
+    }
+    $(
+        $(#[$p_metas:meta])*
+        placeholder $placeholder:ident
+    )*) => {
         #[derive(Debug, Clone)]
         $(#[$metas])*
         pub enum $enum_name {
@@ -182,6 +209,10 @@ macro_rules! statements {
                 $(#[$v_metas])*
                 $variant(Box<$variant>),
             )*
+            $(
+                $(#[$p_metas])*
+                $placeholder(Box<$placeholder>),
+            )*
         }
 
         $(
@@ -192,6 +223,14 @@ macro_rules! statements {
         }
         )*
 
+        $(
+        impl From<$placeholder> for $enum_name {
+             fn from(v: $placeholder) -> $enum_name {
+                <$enum_name>::$placeholder(Box::new(v))
+            }
+        }
+        )*
+
         impl terl::ParseUnit<py_lex::Token> for $enum_name {
             type Target = $enum_name;
 
@@ -226,12 +265,79 @@ statements! {
         Comment,
         CodeBlock
     }
+
+    /// a statement which cant be parsed
+    placeholder Invalid
+}
+
+/// placeholder of a statement which cant be parsed, so that following statements
+/// can still be parsed
+#[derive(Debug, Clone)]
+pub struct Invalid {
+    pub error: terl::Error,
+}
+
+impl Statement {
+    /// errors of statements which cant be parsed, including ones in nested blocks
+    pub fn errors(&self) -> Vec<&terl::Error> {
+        match self {
+            Statement::Invalid(invalid) => vec![&invalid.error],
+            Statement::CodeBlock(block) => block.errors(),
+            Statement::While(while_) => while_.block.errors(),
+            Statement::If(if_) => if_
+                .branches
+                .iter()
+                .map(|branch| &branch.body)
+                .chain(if_.else_.iter().map(|else_| &else_.block))
+                .flat_map(CodeBlock::errors)
+                .collect(),
+            Statement::VarDefineStmt(define) => define
+                .closure()
+                .map(|closure| closure.codes.errors())
+                .unwrap_or_default(),
+            _ => vec![],
+        }
+    }
+
+    /// function calls in the statement, including ones in nested blocks
+    pub fn fn_calls(&self) -> Vec<&FnCall> {
+        fn conds(conds: &Conditions) -> Vec<&FnCall> {
+            conds.iter().flat_map(Expr::fn_calls).collect()
+        }
+        match self {
+            Statement::FnCallStmt(fn_call) => fn_call.fn_calls(),
+            Statement::VarStoreStmt(store) => store.assign.val.fn_calls(),
+            Statement::VarDefineStmt(define) => define
+                .init
+                .iter()
+                .flat_map(|init| init.val.fn_calls())
+                .collect(),
+            Statement::If(if_) => if_
+                .branches
+                .iter()
+                .flat_map(|branch| [conds(&branch.conds), branch.body.fn_calls()])
+                .chain(if_.else_.iter().map(|else_| else_.block.fn_calls()))
+                .flatten()
+                .collect(),
+            Statement::While(while_) => [conds(&while_.conds), while_.block.fn_calls()].concat(),
+            Statement::Return(return_) => return_.val.iter().flat_map(Expr::fn_calls).collect(),
+            Statement::CodeBlock(block) => block.fn_calls(),
+            Statement::Comment(..) | Statement::Invalid(..) => vec![],
+        }
+    }
 }
 
 complex_pu! {
+    #[allow(clippy::large_enum_variant)]
     cpu Item {
         // $ty $name (...)
         FnDefine,
+        // lei4 $name han2 ... jie2
+        Class,
+        // jie1kou3 $name can1 $this jie2 han2 ... jie2
+        Interface,
+        // mo2kuai4 $name han2 ... jie2
+        Module,
         Comment
     }
 }
@@ -263,6 +369,33 @@ mod tests {
         });
     }
 
+    #[test]
+    fn variable_define_inferred() {
+        parse_test("zi4dong4 a wei2 114514 fen1", |p| {
+            p.parse::<Statement>()?;
+            Ok(())
+        });
+    }
+
+    #[test]
+    #[should_panic]
+    fn variable_define_inferred_without_init() {
+        parse_test("zi4dong4 a fen1", |p| {
+            p.parse::<VarDefine>()?;
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn field_store() {
+        parse_test("p de1 a de1 x wei2 1 fen1", |p| {
+            let store = p.parse::<VarStore>()?;
+            assert_eq!(&**store.name, "p");
+            assert_eq!(store.fields.len(), 2);
+            Ok(())
+        });
+    }
+
     #[test]
     fn variable_reassign() {
         parse_test("a wei2 114514 fen1", |p| {
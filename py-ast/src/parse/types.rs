@@ -1,10 +1,13 @@
 use std::ops::Deref;
 
-use py_lex::types::BasicExtenWord;
+use py_lex::{syntax::Symbol, types::BasicExtenWord};
 
 use super::*;
 use crate::complex_pu;
 
+/// names of primitive types, they are parsed as [`Ident`]s like names of classes
+pub(crate) const PRIMITIVE_TYPES: [&str; 2] = ["zheng3", "fu2"];
+
 /// Decorators
 #[derive(Debug, Clone, Copy)]
 pub struct TypeConstExtend;
@@ -46,7 +49,7 @@ impl ParseUnit<Token> for Size{
         let token =p.parse::<Token>()?;
         let size = match token.parse::<usize>(){
                 Ok(num) => PU::new(token.get_span(),num),
-                Err(pe) => return token.unmatch(format!("expect a number, but got `{}` while parsing",pe)),
+                Err(pe) => return token.unmatch(py_lex::msg!(ExpectNumber, error = pe)),
             };
         Ok(Self { size })
     }
@@ -137,7 +140,7 @@ impl ParseUnit<Token> for TypeSignExtend {
             BasicExtenWord::Signed => true,
             BasicExtenWord::Unsigned => false,
             _ => {
-                return keyword.throw("should be `you3fu2` or `wu2fu2`");
+                return keyword.throw(py_lex::msg!(ExpectSign));
             }
         };
 
@@ -145,13 +148,45 @@ impl ParseUnit<Token> for TypeSignExtend {
     }
 }
 
+/// type of functions, like `han2zhi3 zheng3 can1 zheng3 fen1 zheng3 jie2`
+#[derive(Debug, Clone)]
+pub struct FnTypeDefine {
+    /// the return type
+    pub ty: Box<PU<TypeDefine>>,
+    pub params: Vec<PU<TypeDefine>>,
+}
+
+impl ParseUnit<Token> for FnTypeDefine {
+    type Target = FnTypeDefine;
+
+    fn parse(p: &mut Parser<Token>) -> ParseResult<Self, Token> {
+        p.r#match(BasicExtenWord::Function)?;
+        let ty = Box::new(p.parse::<PU<TypeDefine>>().apply(mapper::MustMatch)?);
+        p.r#match(Symbol::Parameter).apply(mapper::MustMatch)?;
+        let Some(param) = p.parse::<PU<TypeDefine>>().apply(mapper::Try)? else {
+            p.r#match(Symbol::EndOfBlock).apply(mapper::MustMatch)?;
+            return Ok(FnTypeDefine { ty, params: vec![] });
+        };
+
+        let mut params = vec![param];
+        while p.r#match(Symbol::Semicolon).is_ok() {
+            params.push(p.parse::<PU<TypeDefine>>()?);
+        }
+
+        p.r#match(Symbol::EndOfBlock).apply(mapper::MustMatch)?;
+        Ok(FnTypeDefine { ty, params })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct TypeDefine {
     pub const_: Option<PU<TypeConstExtend>>,
     pub decorators: Vec<PU<TypeDecorators>>,
     pub width: Option<PU<TypeWidthExtend>>,
     pub sign: Option<PU<TypeSignExtend>>,
+    /// `han2zhi3` if it's a type of functions
     pub ty: Ident,
+    pub fn_type: Option<FnTypeDefine>,
 }
 
 impl TypeDefine {
@@ -164,13 +199,30 @@ impl TryFrom<&TypeDefine> for crate::ir::types::TypeDefine {
     type Error = terl::Error;
 
     fn try_from(def: &crate::parse::TypeDefine) -> std::result::Result<Self, Self::Error> {
-        use crate::ir::types::{ComplexType, PrimitiveType};
+        use crate::ir::types::{ComplexType, FnType, PrimitiveType};
+
+        if let Some(fn_type) = &def.fn_type {
+            let params = fn_type.params.iter().map(|param| param.to_mir_ty());
+            return Ok(FnType {
+                ty: Box::new(fn_type.ty.to_mir_ty()?),
+                params: params.collect::<terl::Result<_>>()?,
+            }
+            .into());
+        }
+        // values are not addressed yet, so there are no pointers, references or arrays
+        if let Some(const_) = def.const_ {
+            return Err(const_.make_error(py_lex::msg!(UnsupportedDecorator, ty = def.ty)));
+        }
+        if let Some(decorator) = def.decorators.first() {
+            return Err(decorator.make_error(py_lex::msg!(UnsupportedDecorator, ty = def.ty)));
+        }
+
         /*
            int: sign, width
            float: width
         */
 
-        if &*def.ty == "zheng3" {
+        if &*def.ty == PRIMITIVE_TYPES[0] {
             // default to be i64
             let sign = def.sign.map(|pu| pu.sign).unwrap_or(true);
 
@@ -179,9 +231,9 @@ impl TryFrom<&TypeDefine> for crate::ir::types::TypeDefine {
                     || *width_extend.width > 128
                     || *width_extend.width < 64
                 {
-                    return Err(width_extend.make_error(format!(
-                        "`zheng3` with width {} is not suppert now",
-                        *width_extend.width
+                    return Err(width_extend.make_error(py_lex::msg!(
+                        UnsupportedIntWidth,
+                        width = *width_extend.width
                     )));
                 }
                 *width_extend.width
@@ -200,20 +252,20 @@ impl TryFrom<&TypeDefine> for crate::ir::types::TypeDefine {
                 _ => unreachable!(),
             };
             return Ok(ty.into());
-        } else if &*def.ty == "fu2" {
+        } else if &*def.ty == PRIMITIVE_TYPES[1] {
             // default to be f32
             if let Some(sign) = def.sign {
                 return Err(
-                    sign.make_error("`fu2` type cant be decorated with `you3fu2` or `wu2fu2`")
+                    sign.make_error(py_lex::msg!(SignedFloat))
                 );
             }
             let width = if let Some(width) = def.width {
                 if *width.width == 32 || *width.width == 64 {
                     *width.width
                 } else {
-                    return Err(width.make_error(format!(
-                        "`fu2` with width {} is not supperted now",
-                        *width.width
+                    return Err(width.make_error(py_lex::msg!(
+                        UnsupportedFloatWidth,
+                        width = *width.width
                     )));
                 }
             } else {
@@ -229,47 +281,13 @@ impl TryFrom<&TypeDefine> for crate::ir::types::TypeDefine {
         }
 
         if let Some(sign) = def.sign {
-            return Err(sign.make_error(format!(
-                "type `{}` with `you3fu2` or `wu2fu2` is not supperted now",
-                def.ty
-            )));
+            return Err(sign.make_error(py_lex::msg!(UnsupportedSign, ty = def.ty)));
         }
         if let Some(width) = def.width {
-            return Err(width.make_error(format!(
-                "type `{}` with `you3fu2` or `wu2fu2` is not supperted now",
-                def.ty
-            )));
+            return Err(width.make_error(py_lex::msg!(UnsupportedWidth, ty = def.ty)));
         }
         let ty = def.ty.to_string();
-
-        if def.const_.is_none() && def.decorators.is_empty() {
-            return Ok(ComplexType::no_decorators(ty).into());
-        }
-
-        use crate::ir::types::TypeDecorators::*;
-
-        let mut decorators = vec![];
-        if def.const_.is_some() {
-            decorators.push(Const);
-        }
-
-        for decorator in &def.decorators {
-            let decorator = match &**decorator{
-                crate::parse::TypeDecorators::TypeArrayExtend(array) => match array.size {
-                    Some(size) => SizedArray(*size),
-                    None => Array,
-                },
-                crate::parse::TypeDecorators::TypeReferenceExtend(_) => {
-                    Reference
-                }
-                crate::parse::TypeDecorators::TypePointerExtend(_) => {
-                    Pointer
-                }
-            };
-            decorators.push(decorator);
-        }
-
-        Ok(ComplexType { decorators, ty }.into())
+        Ok(ComplexType::no_decorators(ty).into())
     }
 }
 
@@ -277,6 +295,16 @@ impl ParseUnit<Token> for TypeDefine {
     type Target = TypeDefine;
 
     fn parse(p: &mut Parser<Token>) -> ParseResult<Self, Token> {
+        if let Some(fn_type) = p.parse::<FnTypeDefine>().apply(mapper::Try)? {
+            return Ok(TypeDefine {
+                const_: None,
+                decorators: vec![],
+                width: None,
+                sign: None,
+                ty: Ident(BasicExtenWord::Function.to_string()),
+                fn_type: Some(fn_type),
+            });
+        }
         let const_ = p.parse::<PU<TypeConstExtend>>().apply(mapper::Try)?;
         let mut decorators = vec![];
         while let Some(decorator) = p.parse::<PU<TypeDecorators>>().apply(mapper::Try)? {
@@ -291,6 +319,7 @@ impl ParseUnit<Token> for TypeDefine {
             width,
             sign,
             ty,
+            fn_type: None,
         })
     }
 }
@@ -308,4 +337,23 @@ mod tests {
             Ok(())
         })
     }
+
+    #[test]
+    fn fn_type() {
+        parse_test("han2zhi3 zheng3 can1 zheng3 fen1 kuan1 32 zheng3 jie2", |p| {
+            let ty = p.parse::<TypeDefine>()?;
+            assert_eq!(ty.fn_type.unwrap().params.len(), 2);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn fn_type_without_params() {
+        parse_test("han2zhi3 han2zhi3 fu2 can1 jie2 can1 jie2", |p| {
+            let ty = p.parse::<TypeDefine>()?;
+            let fn_type = ty.fn_type.unwrap();
+            assert!(fn_type.params.is_empty() && fn_type.ty.fn_type.is_some());
+            Ok(())
+        })
+    }
 }
@@ -1,10 +1,11 @@
-use super::mangle::Mangle;
+use super::mangle::{Mangle, MangleInstance};
 use super::*;
 use crate::parse;
 use either::Either;
 use py_declare::mir::IntoIR;
 use py_declare::*;
 use py_lex::PU;
+use std::collections::{HashMap, HashSet};
 use terl::*;
 
 py_ir::custom_ir_variable!(pub IR<py_ir::value::Value>);
@@ -86,113 +87,879 @@ impl<T, E> FromIterator<Result<T, E>> for Results<T, E> {
     }
 }
 
+/// types which are neither primitive types nor classes are undefined
+fn defined_ty(
+    defs: &Defs,
+    at: Span,
+    ty: py_ir::types::TypeDefine,
+) -> Result<py_ir::types::TypeDefine> {
+    match &ty {
+        py_ir::types::TypeDefine::Complex(complex)
+            if complex.decorators.is_empty() && defs.get_class(&complex.ty).is_none() =>
+        {
+            Err(at.make_error(py_lex::msg!(UndefinedType, name = complex.ty)))
+        }
+        py_ir::types::TypeDefine::Fn(fn_ty) => {
+            let types = fn_ty.params.iter().chain(std::iter::once(&*fn_ty.ty));
+            for ty in types {
+                defined_ty(defs, at, ty.clone())?;
+            }
+            Ok(ty)
+        }
+        _ => Ok(ty),
+    }
+}
+
+/// the field of the class, whose name is at the span
+fn field_of<'c>(class: &'c defs::Class, field: &PU<parse::Ident>) -> Result<&'c py_ir::Field> {
+    class.field(field).ok_or_else(|| {
+        let defined = py_lex::msg!(ClassDefinedHere, name = class.name);
+        let reason = py_lex::msg!(NoField, class = class.name, field = **field);
+        field
+            .make_error(reason)
+            .append(class.span.make_message(defined))
+    })
+}
+
+/// the constructor of the class, which takes values of fields in order
+fn constructor(class: &defs::Class, name: String) -> FnDefine {
+    let instance = "_0".to_owned();
+    let mut body = Statements::new();
+    body.push(VarDefine {
+        ty: class.ty(),
+        name: instance.clone(),
+        init: None,
+        is_temp: false,
+    });
+    for field in &class.fields {
+        body.push(VarStore {
+            name: instance.clone(),
+            fields: vec![field.name.clone()],
+            val: py_ir::value::Value::Variable(field.name.clone()),
+        });
+    }
+    let val = Some(py_ir::value::Value::Variable(instance));
+    body.push(Return { val });
+
+    let params = class.fields.iter().map(|field| Parameter {
+        ty: field.ty.clone(),
+        name: field.name.clone(),
+    });
+    FnDefine {
+        export: false,
+        ty: class.ty(),
+        name,
+        params: params.collect(),
+        body,
+        template: None,
+    }
+}
+
+/// depth first search, `path` is classes which contain the class
+fn sort_classes<'d>(
+    defs: &'d Defs,
+    class: &'d defs::Class,
+    path: &mut Vec<&'d defs::Class>,
+    sorted: &mut Vec<&'d defs::Class>,
+) -> Result<()> {
+    if let Some(at) = path.iter().position(|outer| outer.name == class.name) {
+        let cycle = path[at..].iter().chain([&class]);
+        let cycle = cycle.map(|class| class.name.as_str()).collect::<Vec<_>>();
+        let reason = py_lex::msg!(
+            RecursiveClass,
+            name = class.name,
+            cycle = cycle.join(" -> ")
+        );
+        return Err(class.span.make_error(reason));
+    }
+    if sorted.iter().any(|sorted| sorted.name == class.name) {
+        return Ok(());
+    }
+    path.push(class);
+    for field in &class.fields {
+        if let Some(inner) = defs.class_of(&field.ty) {
+            sort_classes(defs, inner, path, sorted)?;
+        }
+    }
+    path.pop();
+    sorted.push(class);
+    Ok(())
+}
+
+/// register classes and their constructors, so that functions can use them
+///
+/// return structs of classes, where a struct comes after ones its fields are of, and
+/// constructors of classes
+fn regist_classes<M: Mangle>(
+    define: &mut Defines<M>,
+    items: &[parse::Item],
+) -> Result<Vec<Item>, Vec<Error>> {
+    let mut errors = vec![];
+    // names are registered first, so that fields can be of classes defined later
+    let mut classes = vec![];
+    for item in items {
+        let parse::Item::Class(class) = item else {
+            continue;
+        };
+        if let Some(previous) = define.defs.get_class(&class.name) {
+            let defined = py_lex::msg!(ClassDefinedHere, name = class.name);
+            let reason = py_lex::msg!(DoubleClass, name = class.name);
+            let error = class.name.make_error(reason);
+            errors.push(error.append(previous.span.make_message(defined)));
+            continue;
+        }
+        define.defs.new_class(defs::Class {
+            name: class.name.to_string(),
+            fields: vec![],
+            span: class.name.get_span(),
+        });
+        classes.push(class);
+    }
+
+    for class in &classes {
+        let mut fields: Vec<py_ir::Field> = vec![];
+        for (at, field) in class.fields() {
+            if fields.iter().any(|defined| defined.name == *field.name) {
+                errors.push(at.make_error(py_lex::msg!(DoubleField, name = field.name)));
+                continue;
+            }
+            let ty = field.ty.to_mir_ty();
+            match ty.and_then(|ty| defined_ty(&define.defs, field.ty.get_span(), ty)) {
+                Ok(ty) => fields.push(py_ir::Field {
+                    ty,
+                    name: field.name.to_string(),
+                }),
+                Err(error) => errors.push(error),
+            }
+        }
+        define.defs.new_class(defs::Class {
+            name: class.name.to_string(),
+            fields,
+            span: class.name.get_span(),
+        });
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let mut sorted = vec![];
+    for class in &classes {
+        let class = define.defs.get_class(&class.name).unwrap();
+        if let Err(error) = sort_classes(&define.defs, class, &mut vec![], &mut sorted) {
+            errors.push(error);
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    // classes which are registered before are sorted too, but they are generated already
+    let sorted = sorted
+        .into_iter()
+        .filter(|sorted| classes.iter().any(|class| **class.name == *sorted.name))
+        .cloned()
+        .collect::<Vec<_>>();
+    let structs = sorted.iter().map(|class| {
+        let (name, fields) = (class.name.clone(), class.fields.clone());
+        py_ir::Struct { name, fields }.into()
+    });
+    let structs = structs.collect::<Vec<Item>>();
+    let constructors = sorted.iter().map(|class| {
+        let params = class.fields.iter().map(|field| defs::Parameter {
+            name: field.name.clone(),
+            ty: field.ty.clone(),
+        });
+        let sign = defs::FnSign::new(class.ty(), params.collect(), class.span, class.span);
+        let name = define
+            .mangler
+            .mangle_method(&class.name, &class.name, &sign);
+        define.defs.new_fn(&class.name, &name, sign);
+        constructor(class, name).into()
+    });
+    Ok(structs.into_iter().chain(constructors).collect())
+}
+
+/// register interfaces, so that type parameters can be bound by them
+///
+/// types in signatures must be defined, except the name of the type implementing it
+fn regist_interfaces<M: Mangle>(
+    define: &mut Defines<M>,
+    items: &[parse::Item],
+) -> Result<(), Vec<Error>> {
+    let mut errors = vec![];
+    for item in items {
+        let parse::Item::Interface(interface) = item else {
+            continue;
+        };
+        let name = &interface.name;
+        if let Some(previous) = define.defs.get_interface(name) {
+            let defined = py_lex::msg!(InterfaceDefinedHere, name = **name);
+            let reason = py_lex::msg!(DoubleInterface, name = **name);
+            let error = name.make_error(reason);
+            errors.push(error.append(previous.span.make_message(defined)));
+            continue;
+        }
+        if let Some(class) = define.defs.get_class(name) {
+            let defined = py_lex::msg!(ClassDefinedHere, name = class.name);
+            let reason = py_lex::msg!(DoubleInterface, name = **name);
+            let error = name.make_error(reason);
+            errors.push(error.append(class.span.make_message(defined)));
+            continue;
+        }
+
+        let this = py_ir::types::TypeDefine::from(py_ir::types::ComplexType::no_decorators(
+            interface.this.to_string(),
+        ));
+        let ty = |ty: &PU<parse::TypeDefine>| {
+            let mir_ty = ty.to_mir_ty()?;
+            match mir_ty == this {
+                true => Ok(mir_ty),
+                false => defined_ty(&define.defs, ty.get_span(), mir_ty),
+            }
+        };
+        let fns = interface.fns().map(|define| {
+            let params = define.params.iter().map(|param| {
+                let (name, ty) = (param.name.to_string(), ty(&param.ty)?);
+                Ok(defs::Parameter { name, ty })
+            });
+            let params = params.collect::<Result<Vec<_>>>()?;
+            let (retty_span, sign_span) = (define.ty.get_span(), define.sign_span);
+            let sign = defs::FnSign::new(ty(&define.ty)?, params, retty_span, sign_span);
+            let name = define.name.to_string();
+            Ok(defs::FnSignWithName { sign, name })
+        });
+        match fns.collect::<Result<Vec<_>>>() {
+            Ok(fns) => define.defs.new_interface(defs::Interface {
+                name: name.to_string(),
+                this: interface.this.to_string(),
+                fns,
+                span: name.get_span(),
+            }),
+            Err(error) => errors.push(error),
+        }
+    }
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors),
+    }
+}
+
+/// register modules in items, and modules in them, so that paths to them can be resolved
+fn regist_modules<M: Mangle>(
+    define: &mut Defines<M>,
+    items: &[parse::Item],
+) -> Result<(), Vec<Error>> {
+    fn regist<M: Mangle>(
+        define: &mut Defines<M>,
+        item: &parse::Item,
+        module: &str,
+        errors: &mut Vec<Error>,
+    ) {
+        let inner = match item {
+            parse::Item::Module(inner) => inner,
+            parse::Item::Class(parse::Class { name, .. })
+            | parse::Item::Interface(parse::Interface { name, .. })
+                if !module.is_empty() =>
+            {
+                let reason = py_lex::msg!(NotInModule, name = **name, module = module);
+                let note = py_lex::msg!(Note, note = py_lex::msg!(NotInModuleHint));
+                errors.push(name.make_error(reason) + note);
+                return;
+            }
+            _ => return,
+        };
+        let name = &inner.name;
+        let path = defs::Module::qualify(module, name);
+        if let Some(previous) = define.defs.get_module(&path) {
+            let defined = py_lex::msg!(ModuleDefinedHere, name = path);
+            let reason = py_lex::msg!(DoubleModule, name = path);
+            let error = name.make_error(reason);
+            errors.push(error.append(previous.span.make_message(defined)));
+            return;
+        }
+        define.defs.new_module(defs::Module {
+            path: path.clone(),
+            public: inner.public.is_some(),
+            span: name.get_span(),
+        });
+        for item in &inner.items {
+            regist(define, item, &path, errors);
+        }
+    }
+
+    let mut errors = vec![];
+    for item in items {
+        regist(define, item, "", &mut errors);
+    }
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors),
+    }
+}
+
+/// check that classes implement interfaces they claim to, after all functions are
+/// registered
+fn check_implements<M: Mangle>(
+    define: &Defines<M>,
+    items: &[parse::Item],
+) -> Result<(), Vec<Error>> {
+    let mut errors = vec![];
+    for item in items {
+        let parse::Item::Class(class) = item else {
+            continue;
+        };
+        let ty = define.defs.get_class(&class.name).unwrap().ty();
+        for claim in &class.implements {
+            let Some(interface) = define.defs.get_interface(claim) else {
+                let reason = py_lex::msg!(UndefinedInterface, name = **claim);
+                errors.push(claim.make_error(reason));
+                continue;
+            };
+            let missing = define.defs.missing(&ty, interface);
+            if missing.is_empty() {
+                continue;
+            }
+            let reason = py_lex::msg!(NotImplemented, class = class.name, interface = **claim);
+            let defined = py_lex::msg!(InterfaceDefinedHere, name = **claim);
+            let mut error = claim
+                .make_error(reason)
+                .append(interface.span.make_message(defined));
+            for sign in missing {
+                error += py_lex::msg!(Note, note = py_lex::msg!(MissingFn, sign = sign));
+            }
+            errors.push(error);
+        }
+    }
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors),
+    }
+}
+
+/// `class` is set if it's a method of the class, `module` is the path of the module which
+/// the function is in
 fn fn_define_task<'d, M: Mangle>(
     define: &mut Defines<M>,
     fn_define: &'d parse::FnDefine,
-) -> Result<impl FnOnce(&'d Defines<M>) -> Result<FnDefine, Vec<Error>>, Error> {
-    let ty = fn_define.ty.to_mir_ty()?;
+    class: Option<&str>,
+    module: &str,
+) -> Result<impl FnOnce(&Defines<M>) -> FnResult + 'd, Error> {
+    let ty = match &fn_define.ty {
+        Some(ty) => Some(defined_ty(
+            &define.defs,
+            fn_define.retty_span,
+            ty.to_mir_ty()?,
+        )?),
+        None => None,
+    };
 
     let params = fn_define
         .params
         .iter()
         .try_fold(Vec::new(), |mut vec, pu| {
             let name = pu.name.to_string();
-            let ty = pu.ty.to_mir_ty()?;
+            let ty = defined_ty(&define.defs, pu.ty.get_span(), pu.ty.to_mir_ty()?)?;
             vec.push(defs::Parameter { name, ty });
             Result::Ok(vec)
         })?;
 
-    let fn_sign = defs::FnSign::new(
-        ty.clone(),
-        params.clone(),
-        fn_define.retty_span,
-        fn_define.sign_span,
-    );
+    let (retty_span, sign_span) = (fn_define.retty_span, fn_define.sign_span);
+    let fn_sign = match ty.clone() {
+        Some(ty) => defs::FnSign::new(ty, params.clone(), retty_span, sign_span),
+        None => defs::FnSign::inferred(params.clone(), retty_span, sign_span),
+    };
 
-    let mangled_name = define.regist_fn(fn_define, fn_sign)?;
+    let mangled_name = match class {
+        Some(class) => {
+            let class = define.defs.get_class(class).unwrap().clone();
+            define.regist_method(&class, fn_define, fn_sign)?
+        }
+        None => define.regist_fn(fn_define, fn_sign, module)?,
+    };
+
+    let module = module.to_owned();
+    Ok(move |define: &Defines<M>| {
+        let generics = HashMap::new();
+        generate_fn(
+            define,
+            fn_define,
+            &module,
+            mangled_name,
+            ty,
+            params,
+            generics,
+        )
+    })
+}
 
-    Ok(|define: &Defines<M>| -> Result<FnDefine, Vec<Error>> {
-        let mut statement_transmuter = {
-            let scopes = BasicScopes::default();
-            let spans = fn_define.params.iter().map(WithSpan::get_span);
-            let fn_scope = FnScope::new(&mangled_name, params.iter(), spans);
-            StatementGenerator::new(&define.defs, fn_scope, scopes)
-        };
+/// generate the function in the module, `generics` are type arguments if it's an instance
+/// of a generic function
+///
+/// a function whose body has syntax errors is registered but not generated, it fails
+/// without errors because they are reported by the parser already
+fn generate_fn<M: Mangle>(
+    define: &Defines<M>,
+    fn_define: &parse::FnDefine,
+    module: &str,
+    mangled_name: String,
+    ty: Option<py_ir::types::TypeDefine>,
+    params: Vec<defs::Parameter>,
+    generics: HashMap<String, py_ir::types::TypeDefine>,
+) -> FnResult {
+    if !fn_define.codes.errors().is_empty() {
+        return Err(vec![]);
+    }
+    let mut statement_transmuter = {
+        let scopes = BasicScopes::default();
+        let spans = fn_define
+            .params
+            .iter()
+            .map(|param| (param.get_span(), param.name.get_span()));
+        let fn_scope = FnScope::new(&mangled_name, params.iter(), spans);
+        // a generic function is indexed once, not once per instance
+        let index = define.index.is_some() && generics.is_empty();
+        StatementGenerator::new(&define.defs, &define.mangler, fn_scope, scopes)
+            .with_source_map(define.source_map.as_ref())
+            .with_index(index)
+            .with_generics(generics)
+            .with_module(module)
+    };
+
+    let body = statement_transmuter.generate(&fn_define.codes);
+
+    // parameters are visible until the end of the function body
+    let body_end = match fn_define.codes.stmts.last() {
+        Some(stmt) => stmt.get_span().end + 1,
+        None => fn_define.sign_span.end + 2,
+    };
+    for (name, param) in statement_transmuter.fn_scope.parameters() {
+        let visible = Span::new(param.define.start, body_end);
+        let param = undeclared_var(name, param);
+        if let Some(index) = statement_transmuter.index.as_mut() {
+            index.visibles.push((visible, param));
+        }
+    }
 
-        let body = match statement_transmuter.generate(&fn_define.codes) {
-            Err(error) => Err(vec![error]),
-            Ok(body) if !body.returned => {
-                let reason = format!("function `{}` is never return", fn_define.name);
-                let error = fn_define.sign_span.make_error(reason);
-                Err(vec![error])
-            }
+    let returns = statement_transmuter.fn_scope.returns;
+    let body = match body {
+        Err(error) => Err(vec![error]),
+        Ok(body) if !body.returned => {
+            let reason = py_lex::msg!(NeverReturn, name = fn_define.name);
+            let error = fn_define.sign_span.make_error(reason);
+            Err(vec![error])
+        }
+        Ok(..) if ty.is_none() && returns.is_none() => {
+            let reason = py_lex::msg!(NoReturnValue, name = fn_define.name);
+            let error = fn_define.sign_span.make_error(reason);
+            Err(vec![error])
+        }
 
-            Ok(body) => Ok(body),
-        }?;
+        Ok(body) => Ok(body),
+    };
+    let declared = statement_transmuter.fn_scope.declare_map.declare_all();
+
+    // the inferred return type is known after types are declared
+    let map = &statement_transmuter.fn_scope.declare_map;
+    let ty = match (ty, returns) {
+        (Some(ty), _) => Some(ty),
+        (None, Some(returns)) if map[returns].is_declared() => Some(map.get_type(returns).clone()),
+        (None, _) => None,
+    };
+    let overload = define.defs.get_mangled(&mangled_name).clone();
+    let overload: Overload = match &ty {
+        Some(ty) if overload.inferred => {
+            let mut sign = (*overload).clone();
+            sign.ty = ty.clone();
+            sign.into()
+        }
+        _ => overload,
+    };
 
-        statement_transmuter.fn_scope.declare_map.declare_all()?;
+    let name_at = fn_define.name.get_span();
+    statement_transmuter.refer(name_at, || index::Undeclared::Fn(overload.clone()));
 
-        let export = fn_define.export.is_some();
-        let mir_fn = mir::FnDefine {
-            export,
-            ty,
-            body,
-            params,
-            name: mangled_name,
+    if let Some(graphs) = &define.declare_graphs {
+        let locate = |span| match &define.source_map {
+            Some(source_map) => {
+                let (line, column) = source_map.line_col(span);
+                format!("{line}:{column}")
+            }
+            None => format!("token {}", span.start),
         };
-        Ok(mir_fn.into_ir(&statement_transmuter.fn_scope.declare_map))
-    })
+        let map = &statement_transmuter.fn_scope.declare_map;
+        let graph = map.to_dot(&overload.to_string(), locate);
+        graphs.lock().unwrap().push((fn_define.sign_span, graph));
+    }
+
+    if let (Some(index), Some(fn_index)) = (&define.index, statement_transmuter.index.take()) {
+        let map = &statement_transmuter.fn_scope.declare_map;
+        fn_index.declare_into(map, &mut index.lock().unwrap());
+    }
+    // errors of closures are reported with ones of the function
+    let body = match (body, std::mem::take(&mut statement_transmuter.errors)) {
+        (body, closures) if closures.is_empty() => body,
+        (Ok(..), closures) => Err(closures),
+        (Err(errors), closures) => Err(errors.into_iter().chain(closures).collect()),
+    };
+    let body = body?;
+    declared?;
+
+    let called = statement_transmuter.called_instances();
+    define.instances.lock().unwrap().extend(called);
+
+    let Some(ty) = ty else {
+        unreachable!("the return type must be declared if all groups are declared")
+    };
+
+    let export = fn_define.export.is_some();
+    let mir_fn = mir::FnDefine {
+        export,
+        ty,
+        body,
+        params,
+        name: mangled_name,
+        template: None,
+    };
+    let fn_define = mir_fn.into_ir(&statement_transmuter.fn_scope.declare_map);
+    Ok((fn_define, statement_transmuter.lifted))
 }
 
-#[cfg(feature = "parallel")]
-mod parallel {
-    use super::*;
-    use rayon::prelude::*;
+/// the function, and items of closures defined in it, which must be defined before it
+type FnResult = Result<(FnDefine, Vec<Item>), Vec<Error>>;
+
+/// items with indexes of functions they belong to
+type Indexed<T> = Vec<(usize, T)>;
+
+/// a function to generate, with the name which it's registered by in [`Defs`]
+type Task<'d, T> = (&'d parse::FnDefine, String, T);
+
+/// functions in the item and modules in it, with paths of modules they are in, and names
+/// of classes if they are methods
+fn fn_defines<'d>(
+    item: &'d parse::Item,
+    module: &str,
+) -> Vec<(String, Option<&'d str>, &'d parse::FnDefine)> {
+    match item {
+        parse::Item::FnDefine(fn_define) => vec![(module.to_owned(), None, fn_define)],
+        parse::Item::Class(class) => {
+            let name = Some(&**class.name);
+            let methods = class.methods();
+            methods
+                .map(|method| (String::new(), name, method))
+                .collect()
+        }
+        parse::Item::Module(inner) => {
+            let path = defs::Module::qualify(module, &inner.name);
+            let items = inner.items.iter();
+            items.flat_map(|item| fn_defines(item, &path)).collect()
+        }
+        parse::Item::Interface(_) | parse::Item::Comment(_) => vec![],
+    }
+}
 
-    impl<T, E> FromParallelIterator<Result<T, E>> for Results<T, E>
-    where
-        T: Send + 'static,
-        E: Send + 'static,
-    {
-        fn from_par_iter<I>(iter: I) -> Self
-        where
-            I: IntoParallelIterator<Item = Result<T, E>>,
-        {
-            let (result_s, result_r) = std::sync::mpsc::channel();
-            let state = std::thread::spawn(move || {
-                let mut items = Self::new();
-                while let Ok(result) = result_r.recv() {
-                    items.add_result(result);
+/// tasks of all functions, functions are registered so that they can be called before
+/// they are defined
+///
+/// generic functions are registered too, but they are generated as instances later
+#[allow(clippy::type_complexity)]
+fn fn_define_tasks<'d, M: Mangle>(
+    define: &mut Defines<M>,
+    items: &'d [parse::Item],
+) -> Result<Vec<Task<'d, impl FnOnce(&Defines<M>) -> FnResult + 'd>>, Vec<Error>> {
+    let (generics, fn_defines): (Vec<_>, Vec<_>) = items
+        .iter()
+        .flat_map(|item| fn_defines(item, ""))
+        .partition(|(_, class, fn_define)| class.is_none() && !fn_define.generics.is_empty());
+
+    let generics = generics
+        .into_iter()
+        .map(|(module, _, fn_define)| define.regist_generic(fn_define, &module))
+        .collect::<Results<_, _>>()
+        .take();
+    let tasks = fn_defines
+        .into_iter()
+        .map(|(module, class, fn_define)| {
+            let task = fn_define_task(define, fn_define, class, &module)?;
+            let name = match (class, fn_define.operator()) {
+                (None, None) => defs::Module::qualify(&module, &fn_define.name),
+                _ => fn_define.name.to_string(),
+            };
+            Ok((fn_define, name, task))
+        })
+        .collect::<Results<_, _>>()
+        .take();
+    match (generics, tasks) {
+        (Ok(..), Ok(tasks)) => Ok(tasks),
+        (generics, tasks) => {
+            let errors = generics.err().into_iter().chain(tasks.err()).flatten();
+            Err(errors.collect())
+        }
+    }
+}
+
+/// names which functions called in the function could be registered by, `name` is the
+/// one which the function is registered by
+///
+/// a call could be to a function in any module which the function is in, and a call with
+/// a receiver which is not a module is to a method
+fn calls_in(defs: &Defs, fn_define: &parse::FnDefine, name: &str) -> HashSet<String> {
+    let (module, _) = defs::Module::split(name);
+    let mut calls = HashSet::new();
+    for fn_call in fn_define.codes.fn_calls() {
+        let path = match &fn_call.receiver {
+            Some(receiver) => {
+                let mut modules = defs::Module::ancestors(module);
+                let object = |module| defs::Module::qualify(module, &receiver.object);
+                if !modules.any(|module| defs.get_module(&object(module)).is_some()) {
+                    calls.insert(fn_call.fn_name.to_string());
+                    continue;
                 }
-                items
+                let path = receiver
+                    .fields
+                    .iter()
+                    .fold(receiver.object.to_string(), |path, field| {
+                        defs::Module::qualify(&path, field)
+                    });
+                defs::Module::qualify(&path, &fn_call.fn_name)
+            }
+            None => fn_call.fn_name.to_string(),
+        };
+        let modules = defs::Module::ancestors(module);
+        calls.extend(modules.map(|module| defs::Module::qualify(module, &path)));
+    }
+    calls
+}
+
+/// sort functions whose return types are inferred, so that callees come before callers
+///
+/// return the order, and cycles of calls between them
+fn sort_by_calls(
+    fn_defines: &[&parse::FnDefine],
+    names: &[String],
+    calls: &[HashSet<String>],
+) -> (Vec<usize>, Vec<Vec<usize>>) {
+    let inferred = (0..fn_defines.len())
+        .filter(|idx| fn_defines[*idx].ty.is_none())
+        .collect::<Vec<_>>();
+    let callees = (0..fn_defines.len())
+        .map(|caller| {
+            let calls = |callee: &&usize| calls[caller].contains(&names[**callee]);
+            inferred.iter().filter(calls).copied().collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    /// depth first search, `path` is callers of the function
+    fn visit(
+        idx: usize,
+        callees: &[Vec<usize>],
+        visited: &mut [bool],
+        path: &mut Vec<usize>,
+        sorted: &mut (Vec<usize>, Vec<Vec<usize>>),
+    ) {
+        if let Some(at) = path.iter().position(|caller| *caller == idx) {
+            sorted.1.push(path[at..].to_vec());
+            return;
+        }
+        if visited[idx] {
+            return;
+        }
+        path.push(idx);
+        for &callee in &callees[idx] {
+            visit(callee, callees, visited, path, sorted);
+        }
+        path.pop();
+        visited[idx] = true;
+        sorted.0.push(idx);
+    }
+
+    let mut visited = vec![false; fn_defines.len()];
+    let mut sorted = Default::default();
+    for idx in inferred {
+        visit(idx, &callees, &mut visited, &mut vec![], &mut sorted);
+    }
+    sorted
+}
+
+/// generate functions whose return types are inferred, callees before callers, and set
+/// their return types so that they are visible to callers
+///
+/// return results with indexes of functions, and tasks of other functions. functions
+/// which call ones whose return types cant be inferred are dropped, because their errors
+/// are meaningless
+fn infer_return_types<M, T>(
+    define: &mut Defines<M>,
+    tasks: Vec<Task<T>>,
+) -> (Indexed<FnResult>, Indexed<T>)
+where
+    M: Mangle,
+    T: FnOnce(&Defines<M>) -> FnResult,
+{
+    let mut fn_defines = vec![];
+    let mut names = vec![];
+    let tasks = tasks
+        .into_iter()
+        .map(|(fn_define, name, task)| {
+            fn_defines.push(fn_define);
+            names.push(name);
+            task
+        })
+        .collect::<Vec<_>>();
+    let calls = fn_defines
+        .iter()
+        .zip(&names)
+        .map(|(fn_define, name)| calls_in(&define.defs, fn_define, name))
+        .collect::<Vec<_>>();
+    let (order, cycles) = sort_by_calls(&fn_defines, &names, &calls);
+
+    let mut generated = vec![];
+    // names of functions whose return types cant be inferred
+    let mut failed = HashSet::new();
+    for cycle in cycles {
+        let path = cycle.iter().chain(&cycle[..1]);
+        let path = path.map(|idx| names[*idx].clone());
+        let fn_define = fn_defines[cycle[0]];
+        let reason = py_lex::msg!(
+            RecursiveInference,
+            name = fn_define.name,
+            cycle = path.collect::<Vec<_>>().join(" -> ")
+        );
+        let error = fn_define.sign_span.make_error(reason)
+            + py_lex::msg!(Note, note = py_lex::msg!(ExplicitReturnType));
+        generated.push((cycle[0], Err(vec![error])));
+        failed.extend(cycle.iter().map(|idx| names[*idx].clone()));
+    }
+
+    let mut tasks = tasks.into_iter().map(Some).collect::<Vec<_>>();
+    for idx in order {
+        let name = &names[idx];
+        let task = tasks[idx].take().unwrap();
+        if failed.contains(name) || !calls[idx].is_disjoint(&failed) {
+            failed.insert(name.clone());
+            continue;
+        }
+        match task(define) {
+            Ok((fn_define, closures)) => {
+                define
+                    .defs
+                    .set_return_type(&fn_define.name, fn_define.ty.clone());
+                generated.push((idx, Ok((fn_define, closures))));
+            }
+            Err(errors) => {
+                failed.insert(name.clone());
+                generated.push((idx, Err(errors)));
+            }
+        }
+    }
+
+    let tasks = tasks
+        .into_iter()
+        .enumerate()
+        .filter(|(idx, _)| calls[*idx].is_disjoint(&failed))
+        .filter_map(|(idx, task)| Some((idx, task?)))
+        .collect();
+    (generated, tasks)
+}
+
+/// generate instances of generic functions which are called, until no more instances
+/// are called by generated ones, instances which are generated already are skipped
+///
+/// results are indexed from `first_idx`, in the order of calls
+fn generate_instances<M: Mangle>(define: &mut Defines<M>, first_idx: usize) -> Indexed<FnResult> {
+    let mut generated = vec![];
+    let mut seen = HashSet::new();
+    loop {
+        let mut requests = std::mem::take(define.instances.get_mut().unwrap());
+        requests.sort_by_key(|(_, at)| at.start);
+        requests.retain(|(overload, _)| {
+            seen.insert(overload.name.clone())
+                && define.defs.try_get_mangled(&overload.name).is_none()
+        });
+        if requests.is_empty() {
+            return generated;
+        }
+
+        for (overload, _) in &requests {
+            define.defs.regist_instance(overload.clone());
+        }
+        let define = &*define;
+        for (overload, at) in requests {
+            let instance = overload.instance.as_ref().unwrap();
+            let generic = define.defs.get_generic(instance.generic);
+            let (module, fn_define) = &define.templates[&instance.generic];
+            let generics = generic.generics.iter().cloned();
+            let generics: HashMap<_, _> = generics.zip(instance.args.iter().cloned()).collect();
+            let template = generics
+                .iter()
+                .map(|(param, arg)| (param.clone(), arg.into()));
+            let template = py_ir::types::Template::new(&generic.name, template.collect());
+
+            let (name, ty) = (overload.name.clone(), Some(overload.ty.clone()));
+            let params = overload.params.clone();
+            let result = generate_fn(define, fn_define, module, name, ty, params, generics);
+            let result = result.map(|(mut fn_define, closures)| {
+                fn_define.template = Some(template);
+                (fn_define, closures)
             });
-            iter.into_par_iter()
-                .for_each_with(result_s, |sender, item| {
-                    sender.send(item).unwrap();
-                });
-            state.join().unwrap()
+            let result = result.map_err(|errors| {
+                let instance_of = py_lex::msg!(InstanceOf, instance = overload);
+                let instance_of = generic.sign.sign_span.make_message(instance_of);
+                let instantiated = at.make_message(py_lex::msg!(InstantiatedHere));
+                let note = |error: Error| {
+                    error
+                        .append(instance_of.clone())
+                        .append(instantiated.clone())
+                };
+                errors.into_iter().map(note).collect()
+            });
+            generated.push((first_idx + generated.len(), result));
         }
     }
+}
+
+/// items of classes, and results of functions in the order of definitions
+fn collect_results(classes: Vec<Item>, mut results: Indexed<FnResult>) -> ItemsGenerateResult {
+    results.sort_by_key(|(idx, _)| *idx);
+    let fns = results
+        .into_iter()
+        .map(|(_, result)| result)
+        .collect::<Results<_, _>>()
+        .take()
+        .map_err(Either::Right)?;
+    let fns = fns
+        .into_iter()
+        .flat_map(|(fn_define, closures)| closures.into_iter().chain([fn_define.into()]));
+    Ok(classes.into_iter().chain(fns).collect())
+}
+
+fn undeclared_var(name: &str, var_def: &defs::VarDef) -> index::Undeclared {
+    index::Undeclared::Var {
+        name: name.to_owned(),
+        ty: var_def.ty,
+        define: var_def.define,
+    }
+}
+
+#[cfg(feature = "parallel")]
+mod parallel {
+    use super::*;
+    use rayon::prelude::*;
 
     impl<M: Mangle> Generate<[parse::Item]> for Defines<M> {
         type Forward = ItemsGenerateResult;
 
         fn generate(&mut self, items: &[parse::Item]) -> Self::Forward {
-            items
-                .iter()
-                .filter_map(|item| match item {
-                    parse::Item::FnDefine(fn_define) => Some(fn_define),
-                    parse::Item::Comment(_) => None,
-                })
-                .map(|fn_define| fn_define_task(self, fn_define).map(Box::new))
-                .collect::<Results<_, _>>()
-                .take()
-                .map_err(Either::Left)?
-                .into_par_iter()
-                .map(|task| task(self).map(Into::into))
-                .collect::<Results<_, _>>()
-                .take()
-                .map_err(Either::Right)
+            regist_modules(self, items).map_err(Either::Left)?;
+            let classes = regist_classes(self, items).map_err(Either::Left)?;
+            regist_interfaces(self, items).map_err(Either::Left)?;
+            let tasks = fn_define_tasks(self, items).map_err(Either::Left)?;
+            check_implements(self, items).map_err(Either::Left)?;
+            let first_instance = tasks.len();
+            let (mut generated, tasks) = infer_return_types(self, tasks);
+            let define = &*self;
+            let tasks = tasks.into_par_iter().map(|(idx, task)| (idx, task(define)));
+            generated.extend(tasks.collect::<Vec<_>>());
+            generated.extend(generate_instances(self, first_instance));
+            collect_results(classes, generated)
         }
     }
 }
@@ -204,60 +971,241 @@ mod normal {
         type Forward = ItemsGenerateResult;
 
         fn generate(&mut self, items: &[parse::Item]) -> Self::Forward {
-            items
-                .iter()
-                .filter_map(|item| match item {
-                    parse::Item::FnDefine(fn_define) => Some(fn_define),
-                    parse::Item::Comment(_) => None,
-                })
-                .map(|fn_define| fn_define_task(self, fn_define).map(Box::new))
-                .collect::<Results<_, _>>()
-                .take()
-                .map_err(Either::Left)?
-                .into_iter()
-                .map(|task| task(self).map(Into::into))
-                .collect::<Results<_, _>>()
-                .take()
-                .map_err(Either::Right)
+            regist_modules(self, items).map_err(Either::Left)?;
+            let classes = regist_classes(self, items).map_err(Either::Left)?;
+            regist_interfaces(self, items).map_err(Either::Left)?;
+            let tasks = fn_define_tasks(self, items).map_err(Either::Left)?;
+            check_implements(self, items).map_err(Either::Left)?;
+            let first_instance = tasks.len();
+            let (mut generated, tasks) = infer_return_types(self, tasks);
+            let define = &*self;
+            generated.extend(tasks.into_iter().map(|(idx, task)| (idx, task(define))));
+            generated.extend(generate_instances(self, first_instance));
+            collect_results(classes, generated)
         }
     }
 }
 
+/// items generated from the item, a function comes after items of closures defined in it
 impl<M: Mangle> Generate<parse::Item> for Defines<M> {
-    type Forward = Result<Option<Item>, Errors>;
+    type Forward = Result<Vec<Item>, Errors>;
 
     fn generate(&mut self, item: &parse::Item) -> Self::Forward {
         match item {
-            parse::Item::FnDefine(fn_define) => self.generate(fn_define).map(Into::into).map(Some),
-            parse::Item::Comment(..) => Ok(None),
+            // a generic function is only registered, its instances are generated with
+            // functions which call them
+            parse::Item::FnDefine(fn_define) if !fn_define.generics.is_empty() => {
+                self.regist_generic(fn_define, "").map_err(Either::Left)?;
+                Ok(vec![])
+            }
+            parse::Item::FnDefine(fn_define) => self.generate(fn_define),
+            // interfaces are only registered, they are not in ir
+            parse::Item::Interface(..) => match regist_interfaces(self, std::slice::from_ref(item))
+            {
+                Ok(()) => Ok(vec![]),
+                Err(errors) => Err(Either::Right(errors)),
+            },
+            // items in the module are generated together, so that they can call each
+            // other, and so are the struct, the constructor and methods of a class
+            parse::Item::Module(..) | parse::Item::Class(..) => {
+                match self.generate(std::slice::from_ref(item)) {
+                    Ok(items) => Ok(items),
+                    Err(Either::Left(errors)) => Err(Either::Right(errors)),
+                    Err(Either::Right(errors)) => Err(Either::Right(errors.concat())),
+                }
+            }
+            parse::Item::Comment(..) => Ok(vec![]),
         }
     }
 }
 
+/// the function, after items of closures defined in it, and instances of generic functions
+/// called in it after it
 impl<M: Mangle> Generate<parse::FnDefine> for Defines<M> {
-    type Forward = Result<FnDefine, Errors>;
+    type Forward = Result<Vec<Item>, Errors>;
 
     fn generate(&mut self, fn_define: &parse::FnDefine) -> Self::Forward {
-        fn_define_task(self, fn_define).map_err(Either::Left)?(self).map_err(Either::Right)
+        let task = fn_define_task(self, fn_define, None, "").map_err(Either::Left)?;
+        let name = fn_define.name.to_string();
+        let (generated, tasks) = infer_return_types(self, vec![(fn_define, name, task)]);
+        let define = &*self;
+        let mut results = generated.into_iter().map(|(_, result)| result);
+        let result = results.next().unwrap_or_else(|| {
+            let (_, task) = tasks.into_iter().next().unwrap();
+            task(define)
+        });
+        // instances of generic functions called in the function are generated after it
+        let mut results = vec![(0, result)];
+        results.extend(generate_instances(self, 1));
+        match collect_results(vec![], results) {
+            Ok(items) => Ok(items),
+            Err(Either::Left(errors)) => Err(Either::Right(errors)),
+            Err(Either::Right(errors)) => Err(Either::Right(errors.concat())),
+        }
     }
 }
 
 struct StatementGenerator<'w> {
     pub defs: &'w Defs,
+    mangler: &'w dyn MangleInstance,
+    /// type arguments of type parameters, if it's an instance of a generic function
+    generics: HashMap<String, py_ir::types::TypeDefine>,
+    /// path of the module which the function is in
+    module: String,
+    /// calls which may call instances of generic functions, with their spans
+    instances: Vec<(GroupIdx, Span)>,
     pub fn_scope: FnScope,
     pub scopes: BasicScopes,
+    source_map: Option<&'w py_lex::SourceMap>,
+    index: Option<index::FnIndex>,
     stmts: mir::Statements,
+    /// variables of enclosing functions and captured ones, if it's generating a closure
+    closure: Option<ClosureScope>,
+    /// groups of variables which closures are bound to, with lifted functions of closures
+    closures: HashMap<GroupIdx, Overload>,
+    /// items of closures defined in the function, which must be defined before it
+    lifted: Vec<Item>,
+    /// instances of generic functions called in closures, with their spans
+    called: Vec<(Overload, Span)>,
+    /// errors of closures, the function is generated but it's not usable
+    errors: Vec<Error>,
+}
+
+/// name of the parameter of a lifted closure, which is the environment of the closure
+const CLOSURE_ENV: &str = "_env";
+
+/// a variable of enclosing functions which is visible in a closure
+#[derive(Clone)]
+struct Visible {
+    /// types which the variable could be declared as, when the closure is defined
+    types: Vec<py_ir::types::TypeDefine>,
+    default: Option<py_ir::types::TypeDefine>,
+    mutable: bool,
+    define: Span,
+    /// the lifted function, if a closure is bound to the variable
+    closure: Option<Overload>,
+}
+
+/// a variable of enclosing functions which is used in a closure
+struct Capture {
+    name: String,
+    ty: py_ir::types::TypeDefine,
+    /// the variable in the closure
+    def: defs::VarDef,
+    /// where it's captured first
+    at: Span,
+    /// variables which are assigned in the closure are captured by reference, others are
+    /// copied into the environment of the closure
+    by_ref: bool,
+}
+
+struct ClosureScope {
+    outer: HashMap<String, Visible>,
+    captures: Vec<Capture>,
+    /// the return type of the closure, [`None`] if it's inferred
+    ty: Option<py_ir::types::TypeDefine>,
 }
 
 struct VarDeineLoc(usize);
 
-impl StatementGenerator<'_> {
-    fn new(defs: &Defs, fn_scope: FnScope, scopes: BasicScopes) -> StatementGenerator<'_> {
+fn shares_type(a: &[py_ir::types::TypeDefine], b: &[py_ir::types::TypeDefine]) -> bool {
+    a.iter().any(|ty| b.contains(ty))
+}
+
+/// if any of `sources` could be implicitly promoted to any of `targets`
+fn promotable(sources: &[py_ir::types::TypeDefine], targets: &[py_ir::types::TypeDefine]) -> bool {
+    let primitives = |types: &'_ [py_ir::types::TypeDefine]| {
+        types
+            .iter()
+            .filter_map(|ty| ty.as_primitive().copied())
+            .collect::<Vec<_>>()
+    };
+    let targets = primitives(targets);
+    primitives(sources)
+        .iter()
+        .any(|from| targets.iter().any(|to| from.promotable_to(to)))
+}
+
+impl<'w> StatementGenerator<'w> {
+    fn new(
+        defs: &'w Defs,
+        mangler: &'w dyn MangleInstance,
+        fn_scope: FnScope,
+        scopes: BasicScopes,
+    ) -> Self {
         StatementGenerator {
             defs,
+            mangler,
+            generics: Default::default(),
+            module: String::new(),
+            instances: vec![],
             fn_scope,
             scopes,
+            source_map: None,
+            index: None,
             stmts: Default::default(),
+            closure: None,
+            closures: Default::default(),
+            lifted: vec![],
+            called: vec![],
+            errors: vec![],
+        }
+    }
+
+    fn with_source_map(mut self, source_map: Option<&'w py_lex::SourceMap>) -> Self {
+        self.source_map = source_map;
+        self
+    }
+
+    fn with_index(mut self, index: bool) -> Self {
+        self.index = index.then(Default::default);
+        self
+    }
+
+    fn with_generics(mut self, generics: HashMap<String, py_ir::types::TypeDefine>) -> Self {
+        self.generics = generics;
+        self
+    }
+
+    fn with_module(mut self, module: &str) -> Self {
+        self.module = module.to_owned();
+        self
+    }
+
+    /// the type in ir, type parameters in it are replaced with type arguments
+    fn mir_ty(&self, at: Span, ty: &parse::TypeDefine) -> Result<py_ir::types::TypeDefine> {
+        let ty = ty.to_mir_ty()?.replaced(&|ty| match ty {
+            py_ir::types::TypeDefine::Complex(complex) if complex.decorators.is_empty() => {
+                self.generics.get(&complex.ty).cloned()
+            }
+            _ => None,
+        });
+        defined_ty(self.defs, at, ty)
+    }
+
+    /// the class which the value at the span is of, the value is declared as it
+    fn class_of(&mut self, at: Span, val: GroupIdx) -> Result<&'w defs::Class> {
+        let defs = self.defs;
+        let candidates = self.candidates(val);
+        let mut classes = candidates.iter().filter_map(|ty| defs.class_of(ty));
+        match (classes.next(), classes.next()) {
+            (Some(class), None) => {
+                let declare_map = &mut self.fn_scope.declare_map;
+                declare_map.declare_type(at, val, &class.ty());
+                Ok(class)
+            }
+            _ => {
+                let candidates = candidates.iter().map(ToString::to_string);
+                let ty = candidates.collect::<Vec<_>>().join(" | ");
+                Err(at.make_error(py_lex::msg!(NotAClass, ty = ty)))
+            }
+        }
+    }
+
+    /// record that the name at the span refers to the symbol
+    fn refer(&mut self, at: Span, symbol: impl FnOnce() -> index::Undeclared) {
+        if let Some(index) = self.index.as_mut() {
+            index.references.push((at, symbol()));
         }
     }
 
@@ -266,6 +1214,17 @@ impl StatementGenerator<'_> {
         self.stmts.push(stmt.into());
     }
 
+    /// mark that statements generated later are from the location
+    fn push_location(&mut self, at: Span) {
+        if let Some(source_map) = self.source_map {
+            let location = py_ir::Location {
+                file: source_map.name(at).to_owned(),
+                line: source_map.line(at),
+            };
+            self.push_stmt(location);
+        }
+    }
+
     fn temp_var_define<I>(
         &mut self,
         param_ty: GroupIdx,
@@ -311,10 +1270,613 @@ impl StatementGenerator<'_> {
         std::mem::replace(&mut self.stmts, new)
     }
 
-    fn search_value(&mut self, name: &str) -> Option<defs::VarDef> {
-        self.fn_scope
-            .search_parameter(name)
-            .or_else(|| self.scopes.search_variable(name))
+    /// search the variable, variables of enclosing functions are captured if it's
+    /// generating a closure
+    fn search_value(&mut self, name: &str, at: Span) -> Result<Option<defs::VarDef>> {
+        let def = self.fn_scope.search_parameter(name);
+        match def.or_else(|| self.scopes.search_variable(name)) {
+            Some(def) => Ok(Some(def)),
+            None => self.capture(name, at),
+        }
+    }
+
+    /// capture the variable of enclosing functions, its type must be known when the closure
+    /// is defined
+    fn capture(&mut self, name: &str, at: Span) -> Result<Option<defs::VarDef>> {
+        let Some(closure) = self.closure.as_mut() else {
+            return Ok(None);
+        };
+        if let Some(capture) = closure.captures.iter().find(|capture| capture.name == name) {
+            return Ok(Some(capture.def.clone()));
+        }
+        let Some(visible) = closure.outer.get(name) else {
+            return Ok(None);
+        };
+        let ty = match (&visible.types[..], &visible.default) {
+            ([ty], _) | (_, Some(ty)) => ty.clone(),
+            (types, None) => {
+                let types = types.iter().map(ToString::to_string);
+                let types = types.collect::<Vec<_>>().join(" | ");
+                let reason = py_lex::msg!(AmbiguousCapture, name = name, types = types);
+                return Err(at.make_error(reason));
+            }
+        };
+
+        let declare_map = &mut self.fn_scope.declare_map;
+        let group = declare_map.new_static_group(at, std::iter::once(ty.clone().into()));
+        let def = defs::VarDef {
+            ty: group,
+            mutable: visible.mutable,
+            define: visible.define,
+        };
+        if let Some(lifted) = &visible.closure {
+            self.closures.insert(group, lifted.clone());
+        }
+        closure.captures.push(Capture {
+            name: name.to_owned(),
+            ty,
+            def: def.clone(),
+            at,
+            by_ref: false,
+        });
+        Ok(Some(def))
+    }
+
+    /// mark that the variable is assigned, it's captured by reference if it's captured
+    fn assigned(&mut self, def: &defs::VarDef) {
+        let Some(closure) = self.closure.as_mut() else {
+            return;
+        };
+        let captures = closure.captures.iter_mut();
+        if let Some(capture) = captures
+            .into_iter()
+            .find(|capture| capture.def.ty == def.ty)
+        {
+            capture.by_ref = true;
+        }
+    }
+
+    /// variables which closures defined here could capture
+    fn visibles(&mut self) -> HashMap<String, Visible> {
+        let mut visibles = match &self.closure {
+            Some(closure) => closure.outer.clone(),
+            None => HashMap::new(),
+        };
+        let captures = self
+            .closure
+            .iter()
+            .flat_map(|closure| closure.captures.iter());
+        let captures = captures.map(|capture| (&capture.name, &capture.def));
+        // inner scopes shadow outer ones
+        let vars = self.fn_scope.parameters().chain(captures);
+        let vars = vars.chain(self.scopes.iter().flat_map(|scope| scope.vars.iter()));
+        let vars = vars
+            .map(|(name, def)| (name.clone(), def.clone()))
+            .collect::<Vec<_>>();
+        for (name, def) in vars {
+            let visible = Visible {
+                types: self.candidates(def.ty),
+                default: self.fn_scope.declare_map.default_of(def.ty),
+                mutable: def.mutable,
+                define: def.define,
+                closure: self.closures.get(&def.ty).cloned(),
+            };
+            visibles.insert(name, visible);
+        }
+        visibles
+    }
+
+    /// the return type of the function, [`None`] if it's inferred
+    fn return_type(&self) -> Option<py_ir::types::TypeDefine> {
+        if let Some(closure) = &self.closure {
+            return closure.ty.clone();
+        }
+        let mangled_fn = self.defs.get_mangled(&self.fn_scope.fn_name);
+        (!mangled_fn.inferred).then(|| mangled_fn.ty.clone())
+    }
+
+    /// instances of generic functions called in the function and closures in it
+    fn called_instances(&mut self) -> Vec<(Overload, Span)> {
+        let map = &self.fn_scope.declare_map;
+        let called = self.instances.iter().filter_map(|(call, at)| {
+            let overload = map[*call].result().overload();
+            overload.instance.is_some().then(|| (overload.clone(), *at))
+        });
+        let called = called.collect::<Vec<_>>();
+        std::mem::take(&mut self.called)
+            .into_iter()
+            .chain(called)
+            .collect()
+    }
+
+    fn regist_var_define(&mut self, var_define: &parse::VarDefine, ty: GroupIdx) {
+        let var_def = defs::VarDef {
+            ty,
+            mutable: true,
+            define: var_define.name.get_span(),
+        };
+        self.refer(var_def.define, || {
+            undeclared_var(&var_define.name, &var_def)
+        });
+        self.scopes.regist_variable(&var_define.name, var_def);
+    }
+
+    /// define the closure which is bound to the variable
+    ///
+    /// the closure is lifted to a function which takes its environment as the first
+    /// parameter, the environment is a struct of captured variables which is built here
+    fn define_closure(
+        &mut self,
+        var_define: &parse::VarDefine,
+        closure: &parse::Closure,
+    ) -> Result<()> {
+        let name = &**var_define.name;
+        let params = closure
+            .params
+            .iter()
+            .map(|param| {
+                let ty = self.mir_ty(param.get_span(), &param.ty)?;
+                let name = param.name.to_string();
+                Ok(defs::Parameter { ty, name })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let ty = match &closure.ty {
+            Some(ty) => Some(self.mir_ty(closure.sign_span, ty)?),
+            None => None,
+        };
+
+        let idx = self.fn_scope.closure_idx();
+        let lifted = self
+            .mangler
+            .mangle_closure(name, idx, &self.fn_scope.fn_name);
+        let mut generator = {
+            let spans = closure
+                .params
+                .iter()
+                .map(|param| (param.get_span(), param.name.get_span()));
+            let fn_scope = FnScope::new(&lifted, params.iter(), spans);
+            StatementGenerator::new(self.defs, self.mangler, fn_scope, BasicScopes::default())
+                .with_source_map(self.source_map)
+                .with_index(self.index.is_some())
+                .with_generics(self.generics.clone())
+                .with_module(&self.module)
+        };
+        generator.closure = Some(ClosureScope {
+            outer: self.visibles(),
+            captures: vec![],
+            ty: ty.clone(),
+        });
+
+        let body = match generator.generate(&closure.codes) {
+            Ok(body) => body,
+            Err(error) => {
+                self.errors.append(&mut generator.errors);
+                return Err(error);
+            }
+        };
+        // parameters are visible until the end of the closure
+        let body_end = closure.codes.get_span().end;
+        for (name, param) in generator.fn_scope.parameters() {
+            let visible = Span::new(param.define.start, body_end);
+            let param = undeclared_var(name, param);
+            if let Some(index) = generator.index.as_mut() {
+                index.visibles.push((visible, param));
+            }
+        }
+
+        let returns = generator.fn_scope.returns;
+        if !body.returned {
+            let reason = py_lex::msg!(NeverReturn, name = name);
+            return Err(closure.sign_span.make_error(reason));
+        }
+        if ty.is_none() && returns.is_none() {
+            let reason = py_lex::msg!(NoReturnValue, name = name);
+            return Err(closure.sign_span.make_error(reason));
+        }
+
+        let mut errors = std::mem::take(&mut generator.errors);
+        if let Err(declare_errors) = generator.fn_scope.declare_map.declare_all() {
+            errors.extend(declare_errors);
+        }
+        let map = &generator.fn_scope.declare_map;
+        if let (Some(index), Some(fn_index)) = (self.index.as_mut(), generator.index.take()) {
+            fn_index.declare_into(map, &mut index.closures);
+        }
+        if !errors.is_empty() {
+            let error = errors.remove(0);
+            self.errors.extend(errors);
+            return Err(error);
+        }
+
+        let called = generator.called_instances();
+        self.called.extend(called);
+        let map = &generator.fn_scope.declare_map;
+        let ty = match ty {
+            Some(ty) => ty,
+            None => map.get_type(returns.unwrap()).clone(),
+        };
+        let Some(ClosureScope { captures, .. }) = generator.closure.take() else {
+            unreachable!()
+        };
+
+        // the environment is unpacked at the beginning of the lifted function
+        let env_ty = py_ir::types::ComplexType::no_decorators(format!("{lifted} 环境"));
+        let env_ty = py_ir::types::TypeDefine::from(env_ty);
+        let mut fields = vec![];
+        let mut unpack = Statements::new();
+        let mut stores = vec![];
+        for capture in &captures {
+            let Some(def) = self.search_value(&capture.name, capture.at)? else {
+                unreachable!("captured variables are visible where the closure is defined")
+            };
+            let declare_map = &mut self.fn_scope.declare_map;
+            declare_map.declare_type(capture.at, def.ty, &capture.ty);
+
+            let ty = match capture.by_ref {
+                true => py_ir::types::TypeDefine::Ref(Box::new(capture.ty.clone())),
+                false => capture.ty.clone(),
+            };
+            let val = match capture.by_ref {
+                true => {
+                    self.assigned(&def);
+                    mir::Value::Ref(capture.name.clone())
+                }
+                false => mir::Value::Variable(capture.name.clone()),
+            };
+            fields.push(py_ir::Field {
+                ty: ty.clone(),
+                name: capture.name.clone(),
+            });
+            let env = py_ir::value::Value::Variable(CLOSURE_ENV.to_owned());
+            let init = py_ir::value::AssignValue::Field(env, capture.name.clone());
+            unpack.push(VarDefine {
+                ty,
+                name: capture.name.clone(),
+                init: Some(init),
+                is_temp: false,
+            });
+            stores.push(mir::VarStore {
+                name: name.to_owned(),
+                fields: vec![capture.name.clone()],
+                val: mir::Undeclared::new(val, def.ty),
+            });
+        }
+
+        let mut params = params;
+        let env = defs::Parameter {
+            ty: env_ty.clone(),
+            name: CLOSURE_ENV.to_owned(),
+        };
+        params.insert(0, env);
+        for stmt in body.into_ir(map).stmts {
+            unpack.push(stmt);
+        }
+        self.lifted.append(&mut generator.lifted);
+        self.lifted.push(
+            py_ir::Struct {
+                name: env_ty.to_string(),
+                fields,
+            }
+            .into(),
+        );
+        self.lifted.push(
+            FnDefine {
+                export: false,
+                ty: ty.clone(),
+                name: lifted.clone(),
+                params: params.clone(),
+                body: unpack,
+                template: None,
+            }
+            .into(),
+        );
+
+        // the variable is the environment of the closure
+        let ty_at = var_define.ty.get_span();
+        let declare_map = &mut self.fn_scope.declare_map;
+        let group = declare_map.new_static_group(ty_at, std::iter::once(env_ty.into()));
+        self.push_stmt(mir::VarDefine {
+            ty: group,
+            name: name.to_owned(),
+            init: None,
+            is_temp: false,
+        });
+        for store in stores {
+            self.push_stmt(store);
+        }
+        let var_def = defs::VarDef {
+            ty: group,
+            mutable: false,
+            define: var_define.name.get_span(),
+        };
+        self.refer(var_def.define, || undeclared_var(name, &var_def));
+        self.scopes.regist_variable(name, var_def);
+
+        let sign = defs::FnSign::new(ty, params, closure.sign_span, closure.sign_span);
+        let overload = defs::FnSignWithName { sign, name: lifted };
+        self.closures.insert(group, overload.into());
+        Ok(())
+    }
+
+    /// call the closure bound to the variable, its environment is the first argument
+    fn call_closure(
+        &mut self,
+        fn_call: &parse::FnCall,
+        def: defs::VarDef,
+        lifted: Overload,
+        mut args: Vec<(ValueHandle, Span)>,
+    ) -> Result<ValueHandle> {
+        let span = fn_call.get_span();
+        if lifted.params.len() != args.len() + 1 {
+            let reason = py_lex::msg!(
+                ClosureArgLen,
+                name = fn_call.fn_name,
+                expect = lifted.params.len() - 1,
+                got = args.len()
+            );
+            return Err(span.make_error(reason));
+        }
+        let name_at = fn_call.fn_name.get_span();
+        self.refer(name_at, || undeclared_var(&fn_call.fn_name, &def));
+
+        let env = mir::Value::Variable(fn_call.fn_name.to_string());
+        let env = mir::Undeclared::new(env, def.ty).into();
+        args.insert(0, (env, name_at));
+        self.call(&fn_call.fn_name, span, name_at, vec![lifted], vec![], args)
+    }
+
+    /// types which the group could still be declared as
+    fn candidates(&self, group: GroupIdx) -> Vec<py_ir::types::TypeDefine> {
+        self.fn_scope.declare_map[group]
+            .alives(|alives| alives.map(|(_, ty)| ty.get_type().clone()).collect())
+    }
+
+    /// convert the value by a temp variable, which could be declared as any of `targets`
+    /// which the value could be implicitly promoted to
+    fn convert(
+        &mut self,
+        at: Span,
+        targets: &[py_ir::types::TypeDefine],
+        val: ValueHandle,
+    ) -> ValueHandle {
+        let declare_map = &mut self.fn_scope.declare_map;
+        let branches = targets
+            .iter()
+            .map(|target| {
+                let filter = filters::Promotable::new(target, at);
+                BranchesBuilder::new(target.clone().into()).new_depend::<Directly, _>(
+                    declare_map,
+                    self.defs,
+                    val.ty,
+                    &filter,
+                )
+            })
+            .collect();
+        let converted = declare_map.build_group(GroupBuilder::new(at, branches));
+        self.temp_var_define(converted, converted, mir::Operate::Convert(val.handle))
+    }
+
+    /// make the value could be used as one of `targets`
+    ///
+    /// the value is converted only if it cant be declared as any of them directly, but it
+    /// could be implicitly promoted to one of them
+    fn promote(
+        &mut self,
+        at: Span,
+        targets: &[py_ir::types::TypeDefine],
+        val: ValueHandle,
+    ) -> ValueHandle {
+        let sources = self.candidates(val.ty);
+        if shares_type(&sources, targets) || !promotable(&sources, targets) {
+            return val;
+        }
+        self.convert(at, targets, val)
+    }
+
+    /// call the function defined for the operator if any operand is of a class, candidates are
+    /// functions like `yun4suan4 jia1`, and methods of the class of the first operand
+    ///
+    /// operands are given back if none of them is of a class, the operator is applied to them
+    /// directly then
+    fn overloaded(
+        &mut self,
+        op: &PU<py_lex::ops::Operators>,
+        operands: Vec<(ValueHandle, Span)>,
+    ) -> Result<Either<ValueHandle, Vec<(ValueHandle, Span)>>> {
+        let classes = operands
+            .iter()
+            .map(|(operand, _)| {
+                let candidates = self.candidates(operand.ty);
+                candidates.iter().find_map(|ty| self.defs.class_of(ty))
+            })
+            .collect::<Vec<_>>();
+        let Some(class) = classes.iter().flatten().next() else {
+            return Ok(Either::Right(operands));
+        };
+
+        let functions = self.defs.get_unmangled(op).unwrap_or_default();
+        let methods = classes[0]
+            .and_then(|class| self.defs.get_methods(&class.name, op))
+            .unwrap_or_default();
+        let generics = self.defs.get_generics(op).collect::<Vec<_>>();
+        if !op.overloadable() || functions.is_empty() && methods.is_empty() && generics.is_empty() {
+            let reason = py_lex::msg!(OperatorOnClass, op = **op, ty = class.name);
+            let mut err = op.make_error(reason);
+            if op.overloadable() {
+                err += py_lex::msg!(Note, note = py_lex::msg!(OperatorHint, op = **op));
+            }
+            return Err(err);
+        }
+
+        let overloads = functions.iter().chain(methods).cloned().collect();
+        let span = operands
+            .iter()
+            .fold(op.get_span(), |span, (_, at)| span.merge(*at));
+        self.call(op, span, op.get_span(), overloads, generics, operands)
+            .map(Either::Left)
+    }
+
+    /// the name which the function is registered by, functions in the module and modules
+    /// which it's in shadow ones outside them
+    fn resolve_fn(&self, name: &str) -> String {
+        let defined = |qualified: &String| {
+            self.defs.get_unmangled(qualified).is_some()
+                || self.defs.get_generics(qualified).next().is_some()
+        };
+        let modules = defs::Module::ancestors(&self.module);
+        let mut qualified = modules.map(|module| defs::Module::qualify(module, name));
+        qualified.find(defined).unwrap_or_else(|| name.to_owned())
+    }
+
+    /// the module which the path is to, [`None`] if the first name is not a module
+    ///
+    /// the first module is searched in the module which the function is in, and modules
+    /// which it's in
+    fn resolve_module<'p>(
+        &self,
+        path: impl IntoIterator<Item = &'p PU<parse::Ident>>,
+    ) -> Result<Option<String>> {
+        let mut path = path.into_iter();
+        let Some(first) = path.next() else {
+            return Ok(None);
+        };
+        let modules = defs::Module::ancestors(&self.module);
+        let mut qualified = modules.map(|module| defs::Module::qualify(module, first));
+        let Some(mut module) = qualified.find(|path| self.defs.get_module(path).is_some()) else {
+            return Ok(None);
+        };
+        for name in path {
+            let inner = defs::Module::qualify(&module, name);
+            let Some(def) = self.defs.get_module(&inner) else {
+                let reason = py_lex::msg!(UndefinedModule, name = inner);
+                return Err(name.make_error(reason));
+            };
+            if !def.public && !defs::Module::contains(&module, &self.module) {
+                let reason = py_lex::msg!(PrivateItem, name = inner, module = module);
+                let note = py_lex::msg!(Note, note = py_lex::msg!(PublicHint));
+                return Err(name.make_error(reason) + note);
+            }
+            module = inner;
+        }
+        Ok(Some(module))
+    }
+
+    /// overloads and generic functions which are registered by the name, ones in other
+    /// modules are visible only if they are marked with `gong1kai1`
+    fn visible_fns(&self, name: &str, at: Span) -> Result<(Vec<Overload>, Vec<&'w defs::Generic>)> {
+        let defs = self.defs;
+        let overloads = defs.get_unmangled(name).unwrap_or_default().to_vec();
+        let generics = defs.get_generics(name).collect::<Vec<_>>();
+        let (module, _) = defs::Module::split(name);
+        if defs::Module::contains(module, &self.module) {
+            return Ok((overloads, generics));
+        }
+        let defined = !overloads.is_empty() || !generics.is_empty();
+        let overloads = overloads
+            .into_iter()
+            .filter(|overload| defs.is_public(&overload.name))
+            .collect::<Vec<_>>();
+        let generics = generics
+            .into_iter()
+            .filter(|generic| generic.public)
+            .collect::<Vec<_>>();
+        if defined && overloads.is_empty() && generics.is_empty() {
+            let reason = py_lex::msg!(PrivateItem, name = name, module = module);
+            let note = py_lex::msg!(Note, note = py_lex::msg!(PublicHint));
+            return Err(at.make_error(reason) + note);
+        }
+        Ok((overloads, generics))
+    }
+
+    /// the function as a value, which could be any of its overloads, `name` is the one
+    /// which the function is registered by
+    ///
+    /// overloads whose return types are inferred are excluded, because their types may be
+    /// unknown yet
+    fn fn_value(&mut self, name: &str, at: Span) -> Result<ValueHandle> {
+        let (functions, generics) = self.visible_fns(name, at)?;
+        let (inferred, overloads): (Vec<_>, Vec<_>) = functions
+            .into_iter()
+            .partition(|overload| overload.inferred);
+        if overloads.is_empty() {
+            let reason = match (inferred.is_empty(), generics.first()) {
+                (false, _) => py_lex::msg!(InferredFnValue, name = name),
+                (true, Some(_)) => py_lex::msg!(GenericFnValue, name = name),
+                (true, None) => py_lex::msg!(UndefinedVar, name = name),
+            };
+            return Err(at.make_error(reason));
+        }
+
+        let branches = overloads
+            .iter()
+            .map(|overload| {
+                let ty = py_ir::types::TypeDefine::from(overload.fn_type());
+                BranchesBuilder::new(ty.into())
+            })
+            .collect();
+        let ty = self
+            .fn_scope
+            .declare_map
+            .build_group(GroupBuilder::new(at, branches));
+        let fns = overloads
+            .iter()
+            .map(|overload| (overload.fn_type(), overload.name.clone()));
+        let val = mir::Value::Fn(fns.collect());
+        self.refer(at, || index::Undeclared::FnValue { ty, overloads });
+        Ok(mir::Undeclared::new(val, ty).into())
+    }
+
+    /// call the function which the variable points to
+    fn indirect_call(
+        &mut self,
+        fn_call: &parse::FnCall,
+        def: defs::VarDef,
+        args: Vec<(ValueHandle, Span)>,
+    ) -> Result<ValueHandle> {
+        let name = &fn_call.fn_name;
+        let span = fn_call.get_span();
+        let candidates = self.candidates(def.ty);
+        let fn_ty = match &candidates[..] {
+            [py_ir::types::TypeDefine::Fn(fn_ty)] => fn_ty.clone(),
+            _ => {
+                let types = candidates.iter().map(ToString::to_string);
+                let types = types.collect::<Vec<_>>().join(" | ");
+                let reason = py_lex::msg!(AmbiguousFnValue, name = name, types = types);
+                return Err(span.make_error(reason));
+            }
+        };
+        if fn_ty.params.len() != args.len() {
+            let (expect, got) = (fn_ty.params.len(), args.len());
+            let reason = py_lex::msg!(
+                IndirectCallArgLen,
+                name = name,
+                ty = fn_ty,
+                expect = expect,
+                got = got
+            );
+            return Err(span.make_error(reason));
+        }
+        let name_at = fn_call.fn_name.get_span();
+        self.refer(name_at, || undeclared_var(name, &def));
+
+        let args = fn_ty
+            .params
+            .iter()
+            .zip(args)
+            .map(|(param, (arg, at))| {
+                let targets = std::slice::from_ref(param);
+                let arg = self.promote(at, targets, arg);
+                let declare_map = &mut self.fn_scope.declare_map;
+                let ty = declare_map.new_static_group(at, std::iter::once(param.clone().into()));
+                declare_map.merge_group(at, ty, arg.ty);
+                arg.handle
+            })
+            .collect();
+
+        let callee = mir::Undeclared::new(mir::Value::Variable(name.to_string()), def.ty);
+        let declare_map = &mut self.fn_scope.declare_map;
+        let ty = declare_map.new_static_group(span, std::iter::once((*fn_ty.ty).into()));
+        Ok(self.temp_var_define(ty, ty, mir::IndirectCall { callee, args }))
     }
 
     fn in_new_basic_scope<R>(&mut self, active: impl FnOnce(&mut Self) -> R) -> R {
@@ -344,6 +1906,10 @@ impl Generate<parse::Statement> for StatementGenerator<'_> {
                 return Ok(None);
             }
             parse::Statement::Comment(..) => return Ok(None),
+            // see `generate_fn`
+            parse::Statement::Invalid(..) => {
+                unreachable!("bodies with syntax errors are not generated")
+            }
         }
         .map(Some)
     }
@@ -381,29 +1947,198 @@ impl Generate<parse::FnCall> for StatementGenerator<'_> {
     type Forward = Result<ValueHandle>;
 
     fn generate(&mut self, fn_call: &parse::FnCall) -> Self::Forward {
-        let args = fn_call.args.iter().try_fold(vec![], |mut args, expr| {
-            args.push(self.generate(expr)?.handle);
-            Result::Ok(args)
-        })?;
+        // a receiver which is not a variable could be a path to a module
+        let module = match &fn_call.receiver {
+            Some(receiver) => {
+                let object = &receiver.object;
+                match self.search_value(object, object.get_span())? {
+                    Some(..) => None,
+                    None => self.resolve_module(std::iter::once(object).chain(&receiver.fields))?,
+                }
+            }
+            None => None,
+        };
+        // the receiver of a method is passed as the first argument
+        let receiver = match (&fn_call.receiver, &module) {
+            (Some(receiver), None) => Some((receiver.get_span(), self.generate(receiver)?)),
+            _ => None,
+        };
+        let mut args = vec![];
+        let mut args_spans = vec![];
+        for expr in fn_call.args.iter() {
+            args.push(self.generate(expr)?);
+            args_spans.push(expr.get_span());
+        }
 
-        let Some(overloads) = self.defs.get_unmangled(&fn_call.fn_name) else {
-            return Err(fn_call.make_error(format!("call undefinded function {}", fn_call.fn_name)));
+        let (name, overloads, generics) = match receiver {
+            Some((at, receiver)) => {
+                let class = self.class_of(at, receiver.ty)?;
+                let Some(methods) = self.defs.get_methods(&class.name, &fn_call.fn_name) else {
+                    let defined = py_lex::msg!(ClassDefinedHere, name = class.name);
+                    let reason = py_lex::msg!(NoMethod, class = class.name, name = fn_call.fn_name);
+                    let error = fn_call.make_error(reason);
+                    return Err(error.append(class.span.make_message(defined)));
+                };
+                args.insert(0, receiver);
+                args_spans.insert(0, at);
+                (fn_call.fn_name.to_string(), methods.to_vec(), vec![])
+            }
+            None if module.is_some() => {
+                let name = defs::Module::qualify(module.as_deref().unwrap(), &fn_call.fn_name);
+                let (overloads, generics) = self.visible_fns(&name, fn_call.get_span())?;
+                if overloads.is_empty() && generics.is_empty() {
+                    let reason = py_lex::msg!(UndefinedFn, name = name);
+                    return Err(fn_call.make_error(reason));
+                }
+                (name, overloads, generics)
+            }
+            None => {
+                // variables which point to functions shadow functions with the same name
+                let name_at = fn_call.fn_name.get_span();
+                if let Some(def) = self.search_value(&fn_call.fn_name, name_at)? {
+                    if let Some(lifted) = self.closures.get(&def.ty).cloned() {
+                        let args = args.into_iter().zip(args_spans).collect();
+                        return self.call_closure(fn_call, def, lifted, args);
+                    }
+                    let candidates = self.candidates(def.ty);
+                    let is_fn = |ty: &py_ir::types::TypeDefine| ty.as_fn().is_some();
+                    if !candidates.is_empty() && candidates.iter().all(is_fn) {
+                        let args = args.into_iter().zip(args_spans).collect();
+                        return self.indirect_call(fn_call, def, args);
+                    }
+                }
+                let name = self.resolve_fn(&fn_call.fn_name);
+                let (overloads, generics) = self.visible_fns(&name, fn_call.get_span())?;
+                if overloads.is_empty() && generics.is_empty() {
+                    let reason = py_lex::msg!(UndefinedFn, name = fn_call.fn_name);
+                    return Err(fn_call.make_error(reason));
+                }
+                (name, overloads, generics)
+            }
         };
 
-        let args_spans = fn_call
-            .args
+        let name_at = fn_call.fn_name.get_span();
+        let args = args.into_iter().zip(args_spans).collect();
+        let span = fn_call.get_span();
+        self.call(&name, span, name_at, overloads, generics, args)
+    }
+}
+
+impl StatementGenerator<'_> {
+    /// resolve the call to one of overloads and instances of generic functions, then lower
+    /// it to a [`mir::FnCall`]
+    fn call(
+        &mut self,
+        name: &str,
+        span: Span,
+        name_at: Span,
+        mut overloads: Vec<Overload>,
+        generics: Vec<&defs::Generic>,
+        args: Vec<(ValueHandle, Span)>,
+    ) -> Result<ValueHandle> {
+        let (args, args_spans): (Vec<_>, Vec<_>) = args.into_iter().unzip();
+        let arg_types = args
             .iter()
-            .map(|pu| pu.get_span())
+            .map(|arg| self.candidates(arg.ty))
             .collect::<Vec<_>>();
 
-        let overload_len_filter =
-            filters::FnParamLen::new(Some(&fn_call.fn_name), args.len(), fn_call.get_span());
+        // instances of generic functions for inferred type arguments are overloads too
+        let mut uninferred = vec![];
+        // type arguments which dont implement interfaces their type parameters are bound by
+        let mut unsatisfied = vec![];
+        if !generics.is_empty() {
+            let defaults = args
+                .iter()
+                .map(|arg| self.fn_scope.declare_map.default_of(arg.ty))
+                .collect::<Vec<_>>();
+            for generic in &generics {
+                let Some(args) = generic.infer(&arg_types, &defaults) else {
+                    uninferred.push(*generic);
+                    continue;
+                };
+                if generic.unsatisfied(self.defs, &args).is_some() {
+                    unsatisfied.push((*generic, args));
+                    continue;
+                }
+                let sign = generic.instantiate(&args);
+                let name = self.mangler.mangle_instance(&generic.name, &args, &sign);
+                overloads.push(defs::FnSignWithName { sign, name }.into());
+            }
+        }
 
-        let branch_builder = |overload: &Overload| {
-            let mut branch_builder = BranchesBuilder::new(Type::Overload(overload.clone()));
+        let resolution = overload::Resolution::new(&overloads, &arg_types);
+        if !resolution.is_viable() {
+            let mut err = resolution.make_error(name, span, &args_spans);
+            for generic in uninferred {
+                let defined = py_lex::msg!(CandidateDefinedHere, sign = generic);
+                err = err.append(generic.sign.sign_span.make_message(defined));
+                let reason = match generic.sign.params.len() {
+                    len if len != args.len() => {
+                        py_lex::msg!(ParamLenMismatch, expect = len, got = args.len())
+                    }
+                    _ => py_lex::msg!(CantInferTypeArgs),
+                };
+                err += py_lex::msg!(Note, note = reason);
+            }
+            for (generic, args) in unsatisfied {
+                let (idx, interface) = generic.unsatisfied(self.defs, &args).unwrap();
+                let defined = py_lex::msg!(CandidateDefinedHere, sign = generic);
+                err = err.append(generic.sign.sign_span.make_message(defined));
+                let reason = py_lex::msg!(
+                    BoundUnsatisfied,
+                    ty = args[idx],
+                    param = generic.generics[idx],
+                    interface = interface.name
+                );
+                err += py_lex::msg!(Note, note = reason);
+                for sign in self.defs.missing(&args[idx], interface) {
+                    err += py_lex::msg!(Note, note = py_lex::msg!(MissingFn, sign = sign));
+                }
+            }
+            return Err(err);
+        }
+
+        // arguments are promoted if any of the best overloads needs them to be
+        let args = args
+            .into_iter()
+            .enumerate()
+            .map(|(nth, arg)| {
+                let mut promoted = false;
+                let mut targets = vec![];
+                for candidate in &resolution.candidates {
+                    let Ok(conversions) = &candidate.conversions else {
+                        continue;
+                    };
+                    if !resolution.is_best(candidate) {
+                        continue;
+                    }
+                    promoted |= conversions[nth] == overload::Conversion::Promotion;
+                    let param = &candidate.overload.params[nth].ty;
+                    if !targets.contains(param) {
+                        targets.push(param.clone());
+                    }
+                }
+                if promoted {
+                    self.convert(args_spans[nth], &targets, arg).handle
+                } else {
+                    arg.handle
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let overload_len_filter = filters::FnParamLen::new(Some(name), args.len(), span);
+
+        let branch_builder = |candidate: &overload::Candidate| {
+            let ty = Type::Overload(candidate.overload.clone());
+            if let Some(better) = resolution.outranked_by(candidate) {
+                let better = Type::Overload(better.overload.clone());
+                return BranchesBuilder::new_failed(ty, DeclareError::Outranked { better });
+            }
+            let mut branch_builder = BranchesBuilder::new(ty);
             if branch_builder.filter_self(self.defs, &overload_len_filter) {
                 // length of overload.params are equal to arg's
-                for ((param, arg), span) in overload.params.iter().zip(&args).zip(&args_spans) {
+                let params = &candidate.overload.params;
+                for ((param, arg), span) in params.iter().zip(&args).zip(&args_spans) {
                     let filter = filters::TypeEqual::new(&param.ty, *span);
                     let declare_map = &mut self.fn_scope.declare_map;
                     branch_builder = branch_builder.new_depend::<Directly, _>(
@@ -417,37 +2152,100 @@ impl Generate<parse::FnCall> for StatementGenerator<'_> {
             branch_builder
         };
 
-        let branch_builders = overloads.iter().map(branch_builder).collect();
+        let branch_builders = resolution.candidates.iter().map(branch_builder).collect();
         let overload = self
             .fn_scope
             .declare_map
-            .build_group(GroupBuilder::new(fn_call.get_span(), branch_builders));
+            .build_group(GroupBuilder::new(span, branch_builders));
+
+        self.refer(name_at, || index::Undeclared::Call(overload));
+        if overloads.iter().any(|overload| overload.instance.is_some()) {
+            self.instances.push((overload, span));
+        }
 
         Ok(self.temp_var_define(overload, overload, mir::FnCall { args }))
     }
 }
 
+impl Generate<parse::Member> for StatementGenerator<'_> {
+    type Forward = Result<ValueHandle>;
+
+    fn generate(&mut self, member: &parse::Member) -> Self::Forward {
+        let object = &member.object;
+        let Some(def) = self.search_value(object, object.get_span())? else {
+            // a function in a module, like `shu4 de1 jia`
+            let (name, modules) = member.fields.split_last().unwrap();
+            if let Some(module) = self.resolve_module(std::iter::once(object).chain(modules))? {
+                let name = defs::Module::qualify(&module, name);
+                return self.fn_value(&name, member.get_span());
+            }
+            return Err(object.make_error(py_lex::msg!(UndefinedVar, name = **object)));
+        };
+        if self.closures.contains_key(&def.ty) {
+            return Err(object.make_error(py_lex::msg!(ClosureAsValue, name = **object)));
+        }
+        self.refer(object.get_span(), || undeclared_var(object, &def));
+
+        let val = mir::Value::Variable(object.to_string());
+        let mut val: ValueHandle = mir::Undeclared::new(val, def.ty).into();
+        let mut at = object.get_span();
+        for field in &member.fields {
+            let class = self.class_of(at, val.ty)?;
+            let field_ty = field_of(class, field)?.ty.clone();
+            let ty = self
+                .fn_scope
+                .declare_map
+                .new_static_group(field.get_span(), std::iter::once(field_ty.into()));
+            let init = mir::AssignValue::Field(val.handle, field.to_string());
+            val = self.temp_var_define(ty, ty, init);
+            at = at.merge(field.get_span());
+        }
+        Ok(val)
+    }
+}
+
 impl Generate<parse::VarStore> for StatementGenerator<'_> {
     type Forward = Result<mir::VarStore>;
 
     fn generate(&mut self, var_store: &parse::VarStore) -> Self::Forward {
         let name = var_store.name.to_string();
-        let val = self.generate(&var_store.assign.val)?.handle;
+        let val = self.generate(&var_store.assign.val)?;
 
         let val_at = var_store.assign.val.get_span();
 
-        let Some(var_def) = self.search_value(&name) else {
-            return Err(val_at.make_error(format!("use of undefined variable {}", name)));
+        let name_at = var_store.name.get_span();
+
+        let Some(var_def) = self.search_value(&name, name_at)? else {
+            return Err(val_at.make_error(py_lex::msg!(UndefinedVar, name = name)));
         };
         if !var_def.mutable {
-            return Err(val_at.make_error(format!("cant assign to a immmutable variable {}", name)));
+            return Err(val_at.make_error(py_lex::msg!(AssignImmutable, name = name)));
+        }
+        self.assigned(&var_def);
+        self.refer(name_at, || undeclared_var(&name, &var_def));
+
+        // the type of the field which the value is stored into
+        let (mut ty, mut at) = (var_def.ty, name_at);
+        for field in &var_store.fields {
+            let class = self.class_of(at, ty)?;
+            let field_ty = field_of(class, field)?.ty.clone();
+            ty = self
+                .fn_scope
+                .declare_map
+                .new_static_group(field.get_span(), std::iter::once(field_ty.into()));
+            at = at.merge(field.get_span());
         }
 
-        self.fn_scope
-            .declare_map
-            .merge_group(val_at, var_def.ty, val.ty);
+        let targets = self.candidates(ty);
+        let val = self.promote(val_at, &targets, val).handle;
+        self.fn_scope.declare_map.merge_group(val_at, ty, val.ty);
 
-        Ok(mir::VarStore { name, val })
+        let fields = var_store.fields.iter().map(|field| field.to_string());
+        Ok(mir::VarStore {
+            name,
+            fields: fields.collect(),
+            val,
+        })
     }
 }
 
@@ -455,25 +2253,56 @@ impl Generate<parse::VarDefine> for StatementGenerator<'_> {
     type Forward = Result<Option<mir::VarDefine>>;
 
     fn generate(&mut self, var_define: &parse::VarDefine) -> Self::Forward {
-        let ty = var_define.ty.to_mir_ty()?;
-        let ty = self
-            .fn_scope
-            .declare_map
-            .new_static_group(var_define.ty.get_span(), std::iter::once(ty.into()));
-        self.scopes
-            .regist_variable(&var_define.name, defs::VarDef { ty, mutable: true });
-
-        let init = match &var_define.init {
-            Some(var_assign) => {
+        if let Some(closure) = var_define.closure() {
+            if var_define.ty.is_some() {
+                return Err(closure.sign_span.make_error(py_lex::msg!(ClosureNotBound)));
+            }
+            self.define_closure(var_define, closure)?;
+            return Ok(None);
+        }
+        let ty_at = var_define.ty.get_span();
+        let (ty, init) = match &*var_define.ty {
+            Some(ty) => {
+                let ty = self.mir_ty(ty_at, ty)?;
+                let ty = self
+                    .fn_scope
+                    .declare_map
+                    .new_static_group(ty_at, std::iter::once(ty.into()));
+                self.regist_var_define(var_define, ty);
+
+                let init = match &var_define.init {
+                    Some(var_assign) => {
+                        let init = self.generate(&var_assign.val)?;
+                        let at = var_assign.val.get_span();
+                        let targets = self.candidates(ty);
+                        let init = self.promote(at, &targets, init);
+                        self.fn_scope.declare_map.merge_group(at, ty, init.ty);
+                        Some(init)
+                    }
+                    None => None,
+                };
+                (ty, init)
+            }
+            // the type of the variable is the type of its initial value
+            None => {
+                let Some(var_assign) = &var_define.init else {
+                    unreachable!("variables whose type is inferred must be initialized")
+                };
                 let init = self.generate(&var_assign.val)?;
+                self.fn_scope
+                    .declare_map
+                    .regist_inferred(init.ty, &var_define.name);
+                self.regist_var_define(var_define, init.ty);
+                (init.ty, Some(init))
+            }
+        };
 
+        let init = match init {
+            Some(init) => {
                 if let Some(loc) = init.loc {
                     self.rename_var_define(loc, &var_define.name);
                     return Ok(None);
                 }
-                let at = var_assign.val.get_span();
-                self.fn_scope.declare_map.merge_group(at, ty, init.ty);
-
                 Some(mir::Undeclared::new(init.handle.val.into(), init.handle.ty))
             }
             None => None,
@@ -534,11 +2363,20 @@ impl Generate<parse::Return> for StatementGenerator<'_> {
     fn generate(&mut self, ret: &parse::Return) -> Self::Forward {
         let val = match &ret.val {
             Some(expr) => {
-                let val = self.generate(expr)?;
-                let mangled_fn = self.defs.get_mangled(&self.fn_scope.fn_name);
-                self.fn_scope
-                    .declare_map
-                    .declare_type(expr.get_span(), val.ty, &mangled_fn.ty);
+                let mut val = self.generate(expr)?;
+                let retty = self.return_type();
+                if let Some(retty) = &retty {
+                    val = self.promote(expr.get_span(), std::slice::from_ref(retty), val);
+                }
+                let declare_map = &mut self.fn_scope.declare_map;
+                match (retty, self.fn_scope.returns) {
+                    (Some(retty), _) => declare_map.declare_type(expr.get_span(), val.ty, &retty),
+                    // all returned values must have the same type
+                    (None, Some(returns)) => {
+                        declare_map.merge_group(expr.get_span(), returns, val.ty)
+                    }
+                    (None, None) => self.fn_scope.returns = Some(val.ty),
+                }
                 Some(val.handle)
             }
             None => None,
@@ -554,10 +2392,24 @@ impl Generate<parse::CodeBlock> for StatementGenerator<'_> {
         self.in_new_basic_scope(|g| {
             let current_scope = g.take_stmts();
             for stmt in &item.stmts {
-                if let Some(stmt) = g.generate(stmt)? {
+                if !matches!(**stmt, parse::Statement::Comment(..)) {
+                    g.push_location(stmt.get_span());
+                }
+                if let Some(stmt) = g.generate(&**stmt)? {
                     g.push_stmt(stmt);
                 }
             }
+
+            // variables are visible until the end of the block
+            if let (Some(index), Some(last)) = (g.index.as_mut(), item.stmts.last()) {
+                let block_end = last.get_span().end + 1;
+                for (name, var_def) in &g.scopes.last().unwrap().vars {
+                    let visible = Span::new(var_def.define.start, block_end);
+                    index
+                        .visibles
+                        .push((visible, undeclared_var(name, var_def)));
+                }
+            }
             Ok(g.replace_stmts(current_scope))
         })
     }
@@ -568,11 +2420,13 @@ impl Generate<parse::Conditions> for StatementGenerator<'_> {
 
     fn generate(&mut self, conds: &parse::Conditions) -> Self::Forward {
         let (compute, val) = self.in_new_basic_scope(|g| {
+            // statements before the condition must not be taken into it
+            let current_scope = g.take_stmts();
             let mut last_condition = g.generate(&conds[0])?;
             for arg in conds.iter().skip(1) {
                 last_condition = g.generate(arg)?;
             }
-            Ok((g.take_stmts(), last_condition.handle))
+            Ok((g.replace_stmts(current_scope), last_condition.handle))
         })?;
 
         // type check
@@ -592,11 +2446,35 @@ impl Generate<parse::Expr> for StatementGenerator<'_> {
         let mut vals = Vec::new();
         for item in expr.iter() {
             match item {
-                parse::ExprItem::AtomicExpr(atomic) => vals.push(self.generate(atomic)?),
+                parse::ExprItem::AtomicExpr(atomic) => {
+                    vals.push((self.generate(atomic)?, atomic.get_span()))
+                }
                 parse::ExprItem::Operators(op) => match op.associativity() {
                     py_lex::ops::OperatorAssociativity::Binary => {
                         let r = vals.pop().unwrap();
                         let l = vals.pop().unwrap();
+                        let span = l.1.merge(r.1);
+                        let (mut l, mut r) = match self.overloaded(op, vec![l, r])? {
+                            Either::Left(val) => {
+                                vals.push((val, span));
+                                continue;
+                            }
+                            Either::Right(operands) => {
+                                let mut operands = operands.into_iter().map(|(val, _)| val);
+                                (operands.next().unwrap(), operands.next().unwrap())
+                            }
+                        };
+                        // the narrower operand is promoted if they cant be a same type
+                        let (lt, rt) = (self.candidates(l.ty), self.candidates(r.ty));
+                        if !shares_type(&lt, &rt) {
+                            let all = lt.iter().chain(&rt).cloned().collect::<Vec<_>>();
+                            if promotable(&lt, &rt) {
+                                l = self.convert(expr.get_span(), &all, l);
+                            }
+                            if promotable(&rt, &lt) {
+                                r = self.convert(expr.get_span(), &all, r);
+                            }
+                        }
                         self.fn_scope
                             .declare_map
                             .merge_group(expr.get_span(), l.ty, r.ty);
@@ -615,21 +2493,29 @@ impl Generate<parse::Expr> for StatementGenerator<'_> {
                         };
 
                         let init = mir::Operate::Binary(**op, l.handle, r.handle);
-                        vals.push(self.temp_var_define(param_ty, result_ty, init));
+                        vals.push((self.temp_var_define(param_ty, result_ty, init), span));
                     }
                     py_lex::ops::OperatorAssociativity::Unary => {
                         let v = vals.pop().unwrap();
+                        let span = op.get_span().merge(v.1);
+                        let v = match self.overloaded(op, vec![v])? {
+                            Either::Left(val) => {
+                                vals.push((val, span));
+                                continue;
+                            }
+                            Either::Right(mut operands) => operands.pop().unwrap().0,
+                        };
                         let ty = v.ty;
 
                         let init = mir::Operate::Unary(**op, v.handle);
-                        vals.push(self.temp_var_define(ty, ty, init));
+                        vals.push((self.temp_var_define(ty, ty, init), span));
                     }
 
                     py_lex::ops::OperatorAssociativity::None => unreachable!(),
                 },
             }
         }
-        vals.pop().ok_or_else(|| unreachable!())
+        vals.pop().map(|(val, _)| val).ok_or_else(|| unreachable!())
     }
 }
 
@@ -647,21 +2533,27 @@ impl Generate<PU<parse::AtomicExpr>> for StatementGenerator<'_> {
             },
 
             parse::AtomicExpr::StringLiteral(_str) => {
-                // TODO: init for array
-                todo!("a VarDefine statement will be generate...")
+                return Err(atomic.make_error(py_lex::msg!(StringLiteral)));
             }
             parse::AtomicExpr::FnCall(fn_call) => return self.generate(fn_call),
+            parse::AtomicExpr::Member(member) => return self.generate(member),
+            parse::AtomicExpr::Closure(closure) => {
+                return Err(closure.sign_span.make_error(py_lex::msg!(ClosureNotBound)));
+            }
             parse::AtomicExpr::Variable(name) => {
-                let Some(def) = self.search_value(name) else {
-                    return Err(atomic.make_error("use of undefined variable"));
+                let Some(def) = self.search_value(name, atomic.get_span())? else {
+                    return self.fn_value(&self.resolve_fn(name), atomic.get_span());
                 };
+                if self.closures.contains_key(&def.ty) {
+                    return Err(atomic.make_error(py_lex::msg!(ClosureAsValue, name = name)));
+                }
+                self.refer(atomic.get_span(), || undeclared_var(name, &def));
 
                 let val = mir::Value::Variable(name.to_string());
                 return Ok(mir::Undeclared::new(val, def.ty).into());
             }
             parse::AtomicExpr::Array(ref _array) => {
-                // elements in arrray must be same type
-                todo!()
+                return Err(atomic.make_error(py_lex::msg!(ArrayLiteral)));
             }
         };
 
@@ -669,6 +2561,352 @@ impl Generate<PU<parse::AtomicExpr>> for StatementGenerator<'_> {
             let branches = mir::Undeclared::literal_branches(&literal);
             GroupBuilder::new(atomic.get_span(), branches)
         });
+        if let Some(default) = mir::Undeclared::literal_default(&literal) {
+            self.fn_scope.declare_map.set_default(ty, default.into());
+        }
         Ok(mir::Undeclared::new(literal.into(), ty).into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// generate the source which is parsed cleanly, and format errors of it
+    fn semantic_errors(src: &str) -> Vec<String> {
+        let (chars, mut parser) = crate::tokenize_test(src);
+        let (items, errors) = parse::parse_items(&mut parser);
+        assert!(errors.is_empty());
+
+        let mut defines: Defines = Default::default();
+        let errors = match defines.generate(items.as_slice()).unwrap_err() {
+            Either::Left(errors) => errors,
+            Either::Right(errors) => errors.into_iter().flatten().collect(),
+        };
+        let error_handler = (&chars, parser.buffer());
+        errors
+            .into_iter()
+            .map(|error| py_lex::Token::handle_error(&error_handler, error))
+            .collect()
+    }
+
+    /// the message of the formatted error, without its location
+    fn message(error: &str) -> &str {
+        let first_line = error.lines().next().unwrap();
+        first_line.split_once("]: ").unwrap().1
+    }
+
+    #[test]
+    fn declared_type_of_stored_value() {
+        // the sum is stored into `a` directly, it's declared as the type of `a`
+        let src = "zheng3 zhu3 can1 jie2 han2 kuan1 128 zheng3 a wei2 1 jia1 2 fen1 fan3 0 fen1 jie2";
+        let (_, mut parser) = crate::tokenize_test(src);
+        let (items, _) = parse::parse_items(&mut parser);
+        let mut defines: Defines = Default::default();
+        let ir = defines.generate(items.as_slice()).unwrap();
+        let [Item::FnDefine(define)] = &ir[..] else {
+            panic!("{ir:?}")
+        };
+        let a = define.body.iter().find_map(|stmt| match stmt {
+            py_ir::Statement::VarDefine(var_define) if var_define.name == "a" => Some(var_define),
+            _ => None,
+        });
+        let i128 = py_ir::types::TypeDefine::from(py_ir::types::PrimitiveType::I128);
+        assert_eq!(a.unwrap().ty, i128);
+    }
+
+    #[test]
+    fn inference_cycle() {
+        let src = "f can1 jie2 han2 fan3 ya1 ru4 g fen1 jie2\n\
+                   g can1 jie2 han2 fan3 ya1 ru4 f fen1 jie2\n\
+                   zheng3 h can1 jie2 han2 fan3 ya1 ru4 g fen1 jie2";
+        let errors = semantic_errors(src);
+        // `h` calls functions in the cycle, so it's not generated
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            message(&errors[0]),
+            "cant infer the return type of `f`, because it calls itself: f -> g -> f"
+        );
+        assert!(errors[0].contains("note: "), "{}", errors[0]);
+    }
+
+    #[test]
+    fn ambiguous_variable() {
+        let src =
+            "kuan1 64 wu2fu2 zheng3 f can1 kuan1 64 wu2fu2 zheng3 x jie2 han2 fan3 x fen1 jie2\n\
+                   kuan1 128 zheng3 f can1 kuan1 128 zheng3 x jie2 han2 fan3 x fen1 jie2\n\
+                   zheng3 zhu3 can1 jie2 han2 zi4dong4 a wei2 ya1 1 ru4 f fen1 \
+                   a wei2 ya1 2 ru4 f fen1 fan3 0 fen1 jie2";
+        let errors = semantic_errors(src);
+        // the call in the assignment is merged with `a`, so it's not reported again, but
+        // the literals are
+        assert_eq!(errors.len(), 3);
+        assert_eq!(
+            message(&errors[0]),
+            "cant infer the type of `a`, it could be declared as any of:"
+        );
+    }
+
+    #[test]
+    fn literal_overflow() {
+        let src = "zheng3 zhu3 can1 jie2 han2 fan3 10000000000000000000 fen1 jie2";
+        let errors = semantic_errors(src);
+        // the return type is merged with the literal, so it's not reported again
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            message(&errors[0]),
+            "cant infer the type of the literal `10000000000000000000`, it's too large for some types"
+        );
+    }
+
+    #[test]
+    fn no_matching_overload() {
+        let src = "fu2 f can1 fu2 x jie2 han2 fan3 x fen1 jie2\n\
+                   fu2 f can1 fu2 x fen1 fu2 y jie2 han2 fan3 x fen1 jie2\n\
+                   zheng3 zhu3 can1 jie2 han2 ya1 1 ru4 f fen1 fan3 0 fen1 jie2";
+        let errors = semantic_errors(src);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            message(&errors[0]),
+            "no overload of `f` could be called with these arguments"
+        );
+    }
+
+    #[test]
+    fn ambiguous_call() {
+        let src = "kuan1 128 zheng3 f can1 zheng3 x fen1 kuan1 128 zheng3 y jie2 \
+                   han2 fan3 y fen1 jie2\n\
+                   kuan1 128 zheng3 f can1 kuan1 128 zheng3 x fen1 zheng3 y jie2 \
+                   han2 fan3 x fen1 jie2\n\
+                   zheng3 zhu3 can1 jie2 han2 zheng3 a wei2 1 fen1 \
+                   ya1 a fen1 a ru4 f fen1 fan3 0 fen1 jie2";
+        let errors = semantic_errors(src);
+        // arguments are ambiguous because of the call, so they are not reported
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            message(&errors[0]),
+            "this call is ambiguous, it could call any of these overloads:"
+        );
+    }
+
+    #[test]
+    fn instance_error() {
+        let src = "fan4xing2 can1 T jie2 T jia can1 T x jie2 han2 fan3 x jia1 1 fen1 jie2\n\
+                   kuan1 64 fu2 g can1 kuan1 64 fu2 a jie2 han2 fan3 ya1 a ru4 jia fen1 jie2";
+        let errors = semantic_errors(src);
+        assert!(!errors.is_empty());
+        for error in &errors {
+            // the generic function and the call which instantiates it
+            let defined = "]: in `jia<f64>(f64) -> f64`, an instance of the generic function \
+                           defined here\nat line 1";
+            let instantiated = "]: instantiated here\nat line 2";
+            assert!(error.contains(defined), "{error}");
+            assert!(error.contains(instantiated), "{error}");
+        }
+    }
+
+    #[test]
+    fn missing_field() {
+        let src = "lei4 Dian3 han2 zheng3 x fen1 jie2\n\
+                   zheng3 zhu3 can1 Dian3 p jie2 han2 fan3 p de1 y fen1 jie2";
+        let errors = semantic_errors(src);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(message(&errors[0]), "class `Dian3` has no field `y`");
+        // where the class is defined
+        assert!(errors[0].contains("\nat line 1 | "), "{}", errors[0]);
+    }
+
+    #[test]
+    fn pointer_to_class() {
+        let src = "lei4 Dian3 han2 zheng3 x fen1 jie2\n\
+                   zheng3 zhu3 can1 zhi3 Dian3 p jie2 han2 fan3 0 fen1 jie2";
+        let errors = semantic_errors(src);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            message(&errors[0]),
+            "type `Dian3` with `she4`, `zu3`, `yin3` or `zhi3` is not supported now"
+        );
+    }
+
+    #[test]
+    fn method_changes_instance() {
+        // the instance is passed by value, and parameters cant be assigned to
+        let src = "lei4 Dian3 han2\n\
+                   zheng3 x fen1\n\
+                   zheng3 yi2 can1 Dian3 p fen1 zheng3 k jie2 han2 p de1 x wei2 k fen1 \
+                   fan3 k fen1 jie2\n\
+                   jie2";
+        let errors = semantic_errors(src);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            message(&errors[0]),
+            "cant assign to an immutable variable p"
+        );
+    }
+
+    #[test]
+    fn not_implemented() {
+        let src = "jie1kou3 Ke3jia1 can1 T jie2 han2 T jia can1 T a fen1 T b jie2 fen1 jie2\n\
+                   lei4 Dian3 shi2xian4 Ke3jia1 han2 zheng3 x fen1 jie2\n\
+                   zheng3 zhu3 can1 jie2 han2 fan3 0 fen1 jie2";
+        let errors = semantic_errors(src);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            message(&errors[0]),
+            "class `Dian3` doesnt implement interface `Ke3jia1`"
+        );
+        let note = "note: `jia(Dian3, Dian3) -> Dian3` is missing";
+        assert!(errors[0].contains(note), "{}", errors[0]);
+    }
+
+    #[test]
+    fn bound_unsatisfied() {
+        let src = "jie1kou3 Ke3jia1 can1 T jie2 han2 T jia can1 T a fen1 T b jie2 fen1 jie2\n\
+                   fan4xing2 can1 T shi2xian4 Ke3jia1 jie2 T shuang1 can1 T x jie2 \
+                   han2 fan3 ya1 x fen1 x ru4 jia fen1 jie2\n\
+                   kuan1 64 fu2 f can1 kuan1 64 fu2 x jie2 han2 fan3 ya1 x ru4 shuang1 fen1 jie2";
+        let errors = semantic_errors(src);
+        assert_eq!(errors.len(), 1);
+        let notes = [
+            "note: `f64` cant be passed as `T`, because it doesnt implement `Ke3jia1`",
+            "note: `jia(f64, f64) -> f64` is missing",
+        ];
+        for note in notes {
+            assert!(errors[0].contains(note), "{}", errors[0]);
+        }
+    }
+
+    #[test]
+    fn operator_on_class() {
+        let src = "lei4 Dian3 han2 zheng3 x fen1 jie2\n\
+                   Dian3 f can1 Dian3 a jie2 han2 fan3 a jia1 a fen1 jie2";
+        let errors = semantic_errors(src);
+        assert_eq!(errors.len(), 1);
+        let note = "note: define a function like `yun4suan4 jia1` to apply it to classes";
+        assert!(errors[0].contains(note), "{}", errors[0]);
+    }
+
+    #[test]
+    fn operator_param_len() {
+        let src = "lei4 Dian3 han2 zheng3 x fen1 jie2\n\
+                   Dian3 yun4suan4 jia1 can1 Dian3 a jie2 han2 fan3 a fen1 jie2";
+        let errors = semantic_errors(src);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            message(&errors[0]),
+            "operator `jia1` takes 2 operands, but 1 parameters are given"
+        );
+    }
+
+    #[test]
+    fn inferred_fn_value() {
+        let src = "f can1 zheng3 a jie2 han2 fan3 a fen1 jie2\n\
+                   zheng3 g can1 jie2 han2 zi4dong4 h wei2 f fen1 fan3 1 fen1 jie2";
+        let errors = semantic_errors(src);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            message(&errors[0]),
+            "the return type of `f` must be written explicitly to use it as a value"
+        );
+    }
+
+    #[test]
+    fn indirect_call_arg_len() {
+        let src = "zheng3 f can1 han2zhi3 zheng3 can1 zheng3 jie2 g jie2 han2 \
+                   fan3 ya1 1 fen1 2 ru4 g fen1 jie2";
+        let errors = semantic_errors(src);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            message(&errors[0]),
+            "`g` is a `fn(i64) -> i64`, it takes 1 arguments, but 2 are given"
+        );
+    }
+
+    #[test]
+    fn closure_not_bound() {
+        let src = "zheng3 f can1 jie2 han2 \
+                   zheng3 g wei2 bi4bao1 can1 jie2 han2 fan3 1 fen1 jie2 fen1 fan3 1 fen1 jie2";
+        let errors = semantic_errors(src);
+        assert_eq!(errors.len(), 1);
+        assert!(message(&errors[0]).starts_with("closures can only initialize variables"));
+    }
+
+    #[test]
+    fn closure_arg_len() {
+        let src = "zheng3 f can1 zheng3 n jie2 han2 \
+                   zi4dong4 g wei2 bi4bao1 can1 zheng3 x jie2 han2 fan3 x jia1 n fen1 jie2 fen1 \
+                   fan3 ya1 ru4 g fen1 jie2";
+        let errors = semantic_errors(src);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            message(&errors[0]),
+            "closure `g` takes 1 arguments, but 0 are given"
+        );
+    }
+
+    #[test]
+    fn private_item() {
+        let src = "mo2kuai4 shu4 han2 zheng3 f can1 jie2 han2 fan3 1 fen1 jie2 jie2\n\
+                   zheng3 g can1 jie2 han2 fan3 ya1 ru4 shu4 de1 f fen1 jie2";
+        let errors = semantic_errors(src);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            message(&errors[0]),
+            "`shu4 de1 f` is private, it cant be used outside the module `shu4`"
+        );
+        let note = "note: mark it with `gong1kai1` to use it outside the module";
+        assert!(errors[0].contains(note), "{}", errors[0]);
+    }
+
+    #[test]
+    fn private_generic() {
+        let src = "mo2kuai4 shu4 han2 fan4xing2 can1 T jie2 T f can1 T x jie2 han2 fan3 x fen1 jie2 jie2\n\
+                   zheng3 g can1 jie2 han2 fan3 ya1 1 ru4 shu4 de1 f fen1 jie2";
+        let errors = semantic_errors(src);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            message(&errors[0]),
+            "`shu4 de1 f` is private, it cant be used outside the module `shu4`"
+        );
+    }
+
+    #[test]
+    fn types_in_modules() {
+        let src = "mo2kuai4 shu4 han2\n\
+                   lei4 Dian3 han2 zheng3 x fen1 jie2\n\
+                   jie1kou3 Ke3jia1 can1 T jie2 han2 T jia can1 T a fen1 T b jie2 fen1 jie2\n\
+                   jie2";
+        let errors = semantic_errors(src);
+        let messages = errors.iter().map(|error| message(error));
+        assert_eq!(
+            messages.collect::<Vec<_>>(),
+            [
+                "`Dian3` cant be defined in the module `shu4`, classes and interfaces must be defined outside modules",
+                "`Ke3jia1` cant be defined in the module `shu4`, classes and interfaces must be defined outside modules",
+            ]
+        );
+        let note = "note: types are not qualified by modules, define it outside modules and functions in modules could still use it";
+        assert!(errors[0].contains(note), "{}", errors[0]);
+    }
+
+    #[test]
+    fn module_paths() {
+        let src = "mo2kuai4 shu4 han2 jie2 mo2kuai4 shu4 han2 jie2\n\
+                   mo2kuai4 xue2 han2 lei4 Dian3 han2 zheng3 x fen1 jie2 jie2\n\
+                   zheng3 g can1 jie2 han2 fan3 ya1 ru4 shu4 de1 nei4 de1 f fen1 jie2";
+        let errors = semantic_errors(src);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(message(&errors[0]), "double define for module `shu4`");
+        let in_module = "`Dian3` cant be defined in the module `xue2`";
+        assert!(message(&errors[1]).starts_with(in_module), "{}", errors[1]);
+
+        let src = "mo2kuai4 shu4 han2 jie2\n\
+                   zheng3 g can1 jie2 han2 fan3 ya1 ru4 shu4 de1 nei4 de1 f fen1 jie2";
+        let errors = semantic_errors(src);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            message(&errors[0]),
+            "use of undefined module `shu4 de1 nei4`"
+        );
+    }
+}
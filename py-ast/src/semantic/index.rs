@@ -0,0 +1,123 @@
+use py_declare::*;
+use py_ir::types::TypeDefine;
+use terl::Span;
+
+/// a variable or a parameter
+#[derive(Debug, Clone)]
+pub struct VarSymbol {
+    pub name: String,
+    /// [`None`] if the type cant be declared
+    pub ty: Option<TypeDefine>,
+    /// span of the name where the variable is defined
+    pub define: Span,
+}
+
+/// what a name in source code refers to
+#[derive(Debug, Clone)]
+pub enum Symbol {
+    /// a function, for calls it's the overload which is chosen
+    Fn(Overload),
+    Var(VarSymbol),
+}
+
+/// names in source code and what they refer to, used by editor tooling
+///
+/// this will be collected only if [`Defines::index`] is set
+///
+/// [`Defines::index`]: super::Defines::index
+#[derive(Debug, Default, Clone)]
+pub struct Index {
+    /// spans of names, and what they refer to
+    pub references: Vec<(Span, Symbol)>,
+    /// variables, and spans where they are visible
+    pub visibles: Vec<(Span, VarSymbol)>,
+}
+
+impl Index {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// the symbol which the name at the token refers to
+    pub fn reference(&self, token: usize) -> Option<&Symbol> {
+        self.references
+            .iter()
+            .find(|(at, _)| at.start <= token && token < at.end)
+            .map(|(_, symbol)| symbol)
+    }
+
+    /// variables which are visible at the token
+    pub fn visibles(&self, token: usize) -> impl Iterator<Item = &VarSymbol> {
+        self.visibles
+            .iter()
+            .filter(move |(at, _)| at.start <= token && token < at.end)
+            .map(|(_, var)| var)
+    }
+}
+
+/// [`Symbol`] whose type may not be declared yet
+pub(crate) enum Undeclared {
+    Fn(Overload),
+    Call(GroupIdx),
+    /// a function as a value, which is one of overloads
+    FnValue {
+        ty: GroupIdx,
+        overloads: Vec<Overload>,
+    },
+    Var {
+        name: String,
+        ty: GroupIdx,
+        define: Span,
+    },
+}
+
+/// [`Index`] of a function, which is collected while generating its body
+#[derive(Default)]
+pub(crate) struct FnIndex {
+    pub references: Vec<(Span, Undeclared)>,
+    pub visibles: Vec<(Span, Undeclared)>,
+    /// symbols in closures defined in the function, which are declared already
+    pub closures: Index,
+}
+
+impl FnIndex {
+    fn declare(map: &DeclareGraph, symbol: Undeclared) -> Option<Symbol> {
+        match symbol {
+            Undeclared::Fn(overload) => Some(Symbol::Fn(overload)),
+            Undeclared::Call(group) => map[group]
+                .is_declared()
+                .then(|| Symbol::Fn(map[group].result().overload().clone())),
+            Undeclared::FnValue { ty, overloads } => {
+                let declared = map[ty].is_declared().then(|| map.get_type(ty))?;
+                let overload = overloads
+                    .into_iter()
+                    .find(|overload| Some(&overload.fn_type()) == declared.as_fn())?;
+                Some(Symbol::Fn(overload))
+            }
+            Undeclared::Var { name, ty, define } => Some(Symbol::Var(VarSymbol {
+                name,
+                ty: map[ty].is_declared().then(|| map.get_type(ty).clone()),
+                define,
+            })),
+        }
+    }
+
+    /// declare types of symbols as much as possible, and append them to the [`Index`]
+    pub fn declare_into(self, map: &DeclareGraph, index: &mut Index) {
+        index.references.extend(self.closures.references);
+        index.visibles.extend(self.closures.visibles);
+        index.references.extend(
+            self.references
+                .into_iter()
+                .filter_map(|(at, symbol)| Some((at, Self::declare(map, symbol)?))),
+        );
+        index
+            .visibles
+            .extend(self.visibles.into_iter().filter_map(|(at, symbol)| {
+                match Self::declare(map, symbol)? {
+                    Symbol::Var(var) => Some((at, var)),
+                    Symbol::Fn(..) => None,
+                }
+            }));
+    }
+}
@@ -10,12 +10,27 @@ pub enum ManglePrefix {
 pub enum MangleItem<'m> {
     Fn {
         name: Cow<'m, str>,
+        /// type arguments of an instance of a generic function, must be [`MangleItem::Type`]
+        generics: Vec<MangleUnit<'m>>,
         /// must be [`MangleItem::Type`]
         params: Vec<MangleUnit<'m>>,
     },
     Type {
         ty: Cow<'m, str>,
     },
+    /// type of functions, `ty` and `params` must be [`MangleItem::Type`] or
+    /// [`MangleItem::FnType`]
+    FnType {
+        ty: Box<MangleUnit<'m>>,
+        params: Vec<MangleUnit<'m>>,
+    },
+    /// the function lifted from a closure, `idx` is the closure's index in the function
+    /// `within`, which is mangled
+    Closure {
+        name: Cow<'m, str>,
+        idx: usize,
+        within: Cow<'m, str>,
+    },
     Val(),
 }
 
@@ -46,15 +61,36 @@ impl Mangle for ChineseMangler {
         let prefix = mangle_prefex(&unit.prefix);
 
         match unit.item {
-            MangleItem::Fn { name, params } => {
+            MangleItem::Fn {
+                name,
+                generics,
+                params,
+            } => {
                 use std::fmt::Write;
-                let mut output = format!("{prefix}{name} 参");
+                let mut output = format!("{prefix}{name}");
+                if !generics.is_empty() {
+                    output += " 型";
+                    for generic in generics.into_iter() {
+                        write!(&mut output, " {}", Self::mangle(generic)).ok();
+                    }
+                }
+                output += " 参";
                 for param in params.into_iter() {
                     write!(&mut output, " {}", Self::mangle(param)).ok();
                 }
                 format!("{output} 结")
             }
             MangleItem::Type { ty } => prefix + &ty,
+            MangleItem::FnType { ty, params } => {
+                use std::fmt::Write;
+                let mut output = format!("函 {} 参", Self::mangle(*ty));
+                for param in params.into_iter() {
+                    write!(&mut output, " {}", Self::mangle(param)).ok();
+                }
+                format!("{output} 结")
+            }
+            // the enclosing function is mangled with the prefix already
+            MangleItem::Closure { name, idx, within } => format!("{name} 闭 {idx} 于 {within}"),
             MangleItem::Val() => todo!(),
         }
     }
@@ -104,19 +140,118 @@ impl<M: Mangle> Mangler<M> {
             py_ir::types::TypeDefine::Primitive(pty) => self.mangle_unit(MangleItem::Type {
                 ty: Cow::Owned(pty.to_string()),
             }),
-            py_ir::types::TypeDefine::Complex(_) => todo!(),
+            py_ir::types::TypeDefine::Complex(ty) if ty.decorators.is_empty() => {
+                self.mangle_unit(MangleItem::Type {
+                    ty: Cow::Owned(ty.ty.clone()),
+                })
+            }
+            py_ir::types::TypeDefine::Complex(_) => {
+                unreachable!("types with decorators are rejected when they are resolved")
+            }
+            py_ir::types::TypeDefine::Fn(fn_ty) => self.mangle_unit(MangleItem::FnType {
+                ty: Box::new(self.mangle_ty(&fn_ty.ty)),
+                params: fn_ty.params.iter().map(|ty| self.mangle_ty(ty)).collect(),
+            }),
+            py_ir::types::TypeDefine::Ref(_) => {
+                unreachable!("references only appear in closures, not in signatures")
+            }
         }
     }
 
+    /// functions in modules are mangled under prefixes of modules, like `模块的函数`
+    ///
+    /// `name` is qualified, see [`py_declare::defs::Module::qualify`]
     pub fn mangle_fn(&self, name: &str, sign: &py_declare::defs::FnSign) -> String {
         let params = sign
             .params
             .iter()
             .map(|param| self.mangle_ty(&param.ty))
             .collect::<Vec<_>>();
-        self.mangle(MangleItem::Fn {
+        self.mangle_qualified(name, vec![], params)
+    }
+
+    fn mangle_qualified(
+        &self,
+        name: &str,
+        generics: Vec<MangleUnit>,
+        params: Vec<MangleUnit>,
+    ) -> String {
+        let (module, name) = py_declare::defs::Module::split(name);
+        let mut prefix = self.prefix.clone();
+        let modules = py_declare::defs::Module::names(module);
+        prefix.extend(modules.map(|module| ManglePrefix::Mod(module.to_owned())));
+        M::mangle(MangleUnit {
+            prefix: Cow::Owned(prefix),
+            item: MangleItem::Fn {
+                name: Cow::Borrowed(name),
+                generics,
+                params,
+            },
+        })
+    }
+
+    /// methods are mangled under the prefix of the class, like `类型的方法`
+    pub fn mangle_method(
+        &self,
+        class: &str,
+        name: &str,
+        sign: &py_declare::defs::FnSign,
+    ) -> String {
+        let params = sign
+            .params
+            .iter()
+            .map(|param| self.mangle_ty(&param.ty))
+            .collect::<Vec<_>>();
+        let mut prefix = self.prefix.clone();
+        prefix.push(ManglePrefix::Type(class.to_owned()));
+        M::mangle(MangleUnit {
+            prefix: Cow::Owned(prefix),
+            item: MangleItem::Fn {
+                name: Cow::Borrowed(name),
+                generics: vec![],
+                params,
+            },
+        })
+    }
+}
+
+/// mangle names of instances of generic functions and closures, with a [`Mangler`] whose
+/// [`Mangle`] is erased
+pub trait MangleInstance: Sync {
+    /// `args` are type arguments, and `sign` is the signature of the instance
+    fn mangle_instance(
+        &self,
+        name: &str,
+        args: &[py_ir::types::TypeDefine],
+        sign: &py_declare::defs::FnSign,
+    ) -> String;
+
+    /// `name` is the variable which the closure is bound to, and `within` is the mangled
+    /// name of the function which the closure is defined in
+    fn mangle_closure(&self, name: &str, idx: usize, within: &str) -> String;
+}
+
+impl<M: Mangle> MangleInstance for Mangler<M> {
+    fn mangle_instance(
+        &self,
+        name: &str,
+        args: &[py_ir::types::TypeDefine],
+        sign: &py_declare::defs::FnSign,
+    ) -> String {
+        let generics = args.iter().map(|arg| self.mangle_ty(arg)).collect();
+        let params = sign
+            .params
+            .iter()
+            .map(|param| self.mangle_ty(&param.ty))
+            .collect();
+        self.mangle_qualified(name, generics, params)
+    }
+
+    fn mangle_closure(&self, name: &str, idx: usize, within: &str) -> String {
+        self.mangle(MangleItem::Closure {
             name: Cow::Borrowed(name),
-            params,
+            idx,
+            within: Cow::Borrowed(within),
         })
     }
 }
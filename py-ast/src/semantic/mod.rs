@@ -1,5 +1,7 @@
 mod generator;
+mod index;
 mod scope;
 pub use generator::*;
+pub use index::*;
 pub use scope::*;
 pub mod mangle;
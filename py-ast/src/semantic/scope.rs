@@ -1,15 +1,54 @@
-use super::mangle::*;
+use super::{mangle::*, Index};
 use py_declare::*;
 
-use std::collections::HashMap;
+use py_lex::SourceMap;
+use std::{collections::HashMap, sync::Mutex};
 use terl::*;
 
 pub struct Defines<M: Mangle = DefaultMangler> {
     pub defs: Defs,
     pub mangler: Mangler<M>,
+    /// [`py_ir::Location`]s will be generated only if this is set
+    pub source_map: Option<SourceMap>,
+    /// [`Index`] will be collected only if this is set
+    pub index: Option<Mutex<Index>>,
+    /// [`DeclareGraph`]s of functions will be dumped only if this is set
+    pub declare_graphs: Option<Mutex<Vec<(Span, String)>>>,
+    /// instances of generic functions which are called, with spans of calls
+    pub(crate) instances: Mutex<Vec<(Overload, Span)>>,
+    /// generic functions by their indexes in [`Defs`], with paths of modules they are in
+    pub(crate) templates: HashMap<usize, (String, crate::parse::FnDefine)>,
+}
+
+/// functions defined for operators must be able to replace them, and their return types
+/// must be written explicitly because operators are not taken as calls while sorting
+/// functions by calls
+fn check_operator(fn_define: &crate::parse::FnDefine) -> Result<()> {
+    let Some(op) = fn_define.operator() else {
+        return Ok(());
+    };
+    if !op.overloadable() {
+        let reason = py_lex::msg!(NotOverloadable, op = op);
+        return Err(fn_define.sign_span.make_error(reason));
+    }
+    if fn_define.params.len() != op.cost() {
+        let (expect, got) = (op.cost(), fn_define.params.len());
+        let reason = py_lex::msg!(OperatorParamLen, op = op, expect = expect, got = got);
+        return Err(fn_define.sign_span.make_error(reason));
+    }
+    if fn_define.ty.is_none() {
+        let reason = py_lex::msg!(OperatorInferredReturn, op = op);
+        return Err(fn_define.sign_span.make_error(reason));
+    }
+    Ok(())
 }
 
 impl<M: Mangle> Defines<M> {
+    /// register a function in the module, `module` is empty for functions outside modules
+    ///
+    /// functions defined for operators are registered by names of operators wherever they
+    /// are
+    ///
     /// # Return
     ///
     /// mangled_name
@@ -17,27 +56,138 @@ impl<M: Mangle> Defines<M> {
         &mut self,
         fn_define: &crate::parse::FnDefine,
         fn_sign: defs::FnSign,
+        module: &str,
     ) -> Result<String> {
-        let mangled_name = self.mangler.mangle_fn(&fn_define.name, &fn_sign);
-
-        if let Some(previous) = self.defs.try_get_mangled(&mangled_name) {
-            let previous_define = previous
-                .sign_span
-                .make_message(format!("funcion {} has been definded here", fn_define.name));
-            let mut err = fn_sign
-                .sign_span
-                .make_error(format!("double define for function {}", fn_define.name))
-                .append(previous_define);
-            if previous.ty != fn_sign.ty {
-                err += "note: overload which only return type is differnet is not allowed";
-            }
-            err += format!("note: if you want to overload funcion {}, you can define them with different parameters",fn_define.name);
-            return Err(err);
+        check_operator(fn_define)?;
+        let name = match fn_define.operator() {
+            Some(..) => fn_define.name.to_string(),
+            None => defs::Module::qualify(module, &fn_define.name),
+        };
+        let mangled_name = self.mangler.mangle_fn(&name, &fn_sign);
+        self.check_double_define(fn_define, &mangled_name, &fn_sign)?;
+        self.defs.new_fn(&name, &mangled_name, fn_sign);
+        if fn_define.public.is_some() {
+            self.defs.publish_fn(&mangled_name);
+        }
+        Ok(mangled_name)
+    }
+
+    /// register a method of the class, its first parameter receives the instance
+    ///
+    /// # Return
+    ///
+    /// mangled_name
+    pub fn regist_method(
+        &mut self,
+        class: &defs::Class,
+        fn_define: &crate::parse::FnDefine,
+        fn_sign: defs::FnSign,
+    ) -> Result<String> {
+        if !fn_define.generics.is_empty() {
+            let reason = py_lex::msg!(GenericMethod, name = fn_define.name);
+            return Err(fn_define.sign_span.make_error(reason));
+        }
+        check_operator(fn_define)?;
+        if fn_sign.params.first().map(|param| &param.ty) != Some(&class.ty()) {
+            let reason = py_lex::msg!(ExpectReceiver, name = fn_define.name, class = class.name);
+            return Err(fn_define.sign_span.make_error(reason));
         }
 
-        self.defs.new_fn(&fn_define.name, &mangled_name, fn_sign);
+        let mangled_name = self
+            .mangler
+            .mangle_method(&class.name, &fn_define.name, &fn_sign);
+        self.check_double_define(fn_define, &mangled_name, &fn_sign)?;
+        self.defs
+            .new_method(&class.name, &fn_define.name, &mangled_name, fn_sign);
         Ok(mangled_name)
     }
+
+    fn check_double_define(
+        &self,
+        fn_define: &crate::parse::FnDefine,
+        mangled_name: &str,
+        fn_sign: &defs::FnSign,
+    ) -> Result<()> {
+        let Some(previous) = self.defs.try_get_mangled(mangled_name) else {
+            return Ok(());
+        };
+        let previous_define = previous
+            .sign_span
+            .make_message(py_lex::msg!(FnDefinedHere, name = fn_define.name));
+        let mut err = fn_sign
+            .sign_span
+            .make_error(py_lex::msg!(DoubleDefine, name = fn_define.name))
+            .append(previous_define);
+        if !previous.inferred && !fn_sign.inferred && previous.ty != fn_sign.ty {
+            err += py_lex::msg!(Note, note = py_lex::msg!(OverloadByReturnType));
+        }
+        err += py_lex::msg!(
+            Note,
+            note = py_lex::msg!(OverloadHint, name = fn_define.name)
+        );
+        Err(err)
+    }
+
+    /// register a generic function in the module, its body is checked when it's
+    /// instantiated
+    ///
+    /// # Return
+    ///
+    /// index of the generic function
+    pub fn regist_generic(
+        &mut self,
+        fn_define: &crate::parse::FnDefine,
+        module: &str,
+    ) -> Result<usize> {
+        if let Some(export) = fn_define.export {
+            let reason = py_lex::msg!(GenericExport, name = fn_define.name);
+            return Err(export.make_error(reason));
+        }
+        let Some(ty) = &fn_define.ty else {
+            let reason = py_lex::msg!(GenericInferredReturn, name = fn_define.name);
+            return Err(fn_define.sign_span.make_error(reason));
+        };
+        check_operator(fn_define)?;
+
+        let params = fn_define
+            .params
+            .iter()
+            .map(|pu| {
+                let name = pu.name.to_string();
+                let ty = pu.ty.to_mir_ty()?;
+                Ok(defs::Parameter { name, ty })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let (retty_span, sign_span) = (fn_define.retty_span, fn_define.sign_span);
+        let sign = defs::FnSign::new(ty.to_mir_ty()?, params, retty_span, sign_span);
+        let generics = fn_define.generics.iter();
+        let generics = generics.map(|param| param.name.to_string()).collect();
+        let bounds = fn_define
+            .generics
+            .iter()
+            .map(|param| {
+                let bounds = param.bounds.iter().map(|bound| {
+                    if self.defs.get_interface(bound).is_none() {
+                        let reason = py_lex::msg!(UndefinedInterface, name = **bound);
+                        return Err(bound.make_error(reason));
+                    }
+                    Ok(bound.to_string())
+                });
+                bounds.collect::<Result<Vec<_>>>()
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let name = match fn_define.operator() {
+            Some(..) => fn_define.name.to_string(),
+            None => defs::Module::qualify(module, &fn_define.name),
+        };
+        let idx = self.defs.new_generic(&name, generics, bounds, sign);
+        if fn_define.public.is_some() {
+            self.defs.publish_generic(idx);
+        }
+        let template = (module.to_owned(), fn_define.clone());
+        self.templates.insert(idx, template);
+        Ok(idx)
+    }
 }
 
 impl<M: Mangle> Default for Defines<M> {
@@ -45,13 +195,56 @@ impl<M: Mangle> Default for Defines<M> {
         Self {
             defs: Default::default(),
             mangler: Default::default(),
+            source_map: None,
+            index: None,
+            declare_graphs: None,
+            instances: Default::default(),
+            templates: Default::default(),
         }
     }
 }
 
 impl<M: Mangle> Defines<M> {
     pub fn new(defs: Defs, mangler: Mangler<M>) -> Self {
-        Self { defs, mangler }
+        Self {
+            defs,
+            mangler,
+            source_map: None,
+            index: None,
+            declare_graphs: None,
+            instances: Default::default(),
+            templates: Default::default(),
+        }
+    }
+
+    pub fn with_source_map(mut self, source_map: SourceMap) -> Self {
+        self.source_map = Some(source_map);
+        self
+    }
+
+    pub fn with_index(mut self) -> Self {
+        self.index = Some(Default::default());
+        self
+    }
+
+    /// take the collected [`Index`], return [`None`] if [`Defines::with_index`] was not called
+    pub fn take_index(&mut self) -> Option<Index> {
+        self.index.take().map(|index| index.into_inner().unwrap())
+    }
+
+    pub fn with_declare_graphs(mut self) -> Self {
+        self.declare_graphs = Some(Default::default());
+        self
+    }
+
+    /// take [`DeclareGraph`]s of functions in graphviz's dot language, in the order of
+    /// definitions
+    ///
+    /// return [`None`] if [`Defines::with_declare_graphs`] was not called
+    pub fn take_declare_graphs(&mut self) -> Option<Vec<String>> {
+        let mut graphs = self.declare_graphs.take()?.into_inner().unwrap();
+        graphs.sort_by_key(|(at, _)| at.start);
+        Some(graphs.into_iter().map(|(_, graph)| graph).collect())
     }
 }
 
@@ -70,25 +263,32 @@ pub struct FnScope {
     pub fn_name: String,
     // a counter
     temps: usize,
-    parameters: HashMap<String, GroupIdx>,
+    // a counter of closures defined in the function
+    closures: usize,
+    parameters: HashMap<String, defs::VarDef>,
     pub declare_map: DeclareGraph,
+    /// type of the first value returned by `fan3`, used if the return type is inferred
+    pub returns: Option<GroupIdx>,
 }
 
 impl FnScope {
     pub fn new<'p, PI, SI>(fn_name: impl ToString, params: PI, spans: SI) -> Self
     where
         PI: IntoIterator<Item = &'p defs::Parameter>,
-        SI: IntoIterator<Item = Span>,
+        SI: IntoIterator<Item = (Span, Span)>,
     {
         let mut declare_map = DeclareGraph::default();
         let parameters = spans
             .into_iter()
             .zip(params)
-            .map(|(at, param)| {
-                (
-                    param.name.clone(),
-                    declare_map.new_static_group(at, std::iter::once(param.ty.clone().into())),
-                )
+            .map(|((at, name_at), param)| {
+                let ty = declare_map.new_static_group(at, std::iter::once(param.ty.clone().into()));
+                let var_def = defs::VarDef {
+                    ty,
+                    mutable: false,
+                    define: name_at,
+                };
+                (param.name.clone(), var_def)
             })
             .collect();
 
@@ -106,12 +306,20 @@ impl FnScope {
         (format!("_{}", self.temps), self.temps += 1).0
     }
 
+    /// index of the next closure defined in the function
+    #[inline]
+    pub fn closure_idx(&mut self) -> usize {
+        (self.closures, self.closures += 1).0
+    }
+
     #[inline]
     pub fn search_parameter(&mut self, name: &str) -> Option<defs::VarDef> {
-        self.parameters.get(name).map(|ty| defs::VarDef {
-            ty: *ty,
-            mutable: false,
-        })
+        self.parameters.get(name).cloned()
+    }
+
+    #[inline]
+    pub fn parameters(&self) -> impl Iterator<Item = (&String, &defs::VarDef)> {
+        self.parameters.iter()
     }
 }
 
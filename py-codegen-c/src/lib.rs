@@ -1,6 +1,30 @@
+mod symbols;
 mod translate;
 
-pub struct CBackend;
+pub use symbols::Symbols;
+
+pub struct CBackend {
+    config: Config,
+}
+
+/// options of the c code generation backend
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Config {
+    /// emit `#line` directives before statements, so that debuggers can find source codes
+    pub line_directives: bool,
+    /// use readable names for functions instead of base32 encoded mangled names
+    pub readable_names: bool,
+}
+
+impl Config {
+    /// config for debugging: all debug helpers are enabled
+    pub fn debug() -> Self {
+        Self {
+            line_directives: true,
+            readable_names: true,
+        }
+    }
+}
 
 use std::fmt::Write;
 
@@ -17,33 +41,38 @@ pub struct FileModule {
     buffer: Buffer,
     c_file: String,
     h_file: String,
-    label_idx: usize,
+    indent: usize,
+    line_directives: bool,
+    symbols: Symbols,
+    /// variables in scopes, and if they are references, see [`py_ir::types::TypeDefine::Ref`]
+    vars: Vec<std::collections::HashMap<String, bool>>,
 }
 
-struct Label(String);
-
 impl FileModule {
-    pub fn new(name: String) -> Self {
-        const HEADER_FILES: &str = "#include <math.h>\n#include <stdbool.h>\n#include <stdint.h>\n";
+    pub fn new(name: String, config: Config) -> Self {
+        const HEADER_FILES: &str = concat!(
+            "#include <math.h>\n#include <stdbool.h>\n#include <stddef.h>\n#include <stdint.h>\n",
+            // 128 bit integers are extensions of gcc and clang, fallback to _BitInt of c23
+            "#ifndef PY_INT128\n#define PY_INT128\n",
+            "#if defined(__SIZEOF_INT128__)\n",
+            "typedef __int128 py_i128;\ntypedef unsigned __int128 py_u128;\n",
+            "#elif defined(__BITINT_MAXWIDTH__) && __BITINT_MAXWIDTH__ >= 128\n",
+            "typedef _BitInt(128) py_i128;\ntypedef unsigned _BitInt(128) py_u128;\n",
+            "#else\n#error \"128 bit integers are not supported by this compiler\"\n#endif\n",
+            "#endif\n"
+        );
         Self {
             name,
             buffer: Buffer::C,
             c_file: String::from(HEADER_FILES),
             h_file: String::from(HEADER_FILES),
-            label_idx: 0,
+            indent: 0,
+            line_directives: config.line_directives,
+            symbols: Symbols::new(config.readable_names),
+            vars: vec![],
         }
     }
 
-    fn label(&mut self) -> Label {
-        let label = format!("L{}", self.label_idx);
-        self.label_idx += 1;
-        Label(label)
-    }
-
-    fn goto(&mut self, label: &Label) -> Result<(), std::fmt::Error> {
-        write!(self, "goto {};", label.0)
-    }
-
     fn swap_to(&mut self, target: Buffer) {
         if self.buffer != target {
             std::mem::swap(&mut self.c_file, &mut self.h_file);
@@ -73,18 +102,105 @@ impl FileModule {
         self.write_char(';')
     }
 
-    fn if_else(
+    /// start a new line with indentation
+    fn newline(&mut self) -> Result<(), std::fmt::Error> {
+        self.write_char('\n')?;
+        for _ in 0..self.indent {
+            self.write_str("    ")?;
+        }
+        Ok(())
+    }
+
+    /// write statements line by line, without braces
+    fn statements(
         &mut self,
-        cond: &py_ir::value::Value,
-        then: &Label,
-        or: &Label,
+        stmts: &[py_ir::Statement<py_ir::value::Value>],
     ) -> Result<(), std::fmt::Error> {
-        self.write_str("if(")?;
-        self.translate(cond)?;
-        self.write_char(')')?;
-        self.goto(then)?;
-        self.write_str("else ")?;
-        self.goto(or)
+        for stmt in stmts {
+            match stmt {
+                py_ir::Statement::Location(..) if !self.line_directives => continue,
+                // compute the condition of the first branch before `if`,
+                // so that it can be `else if` when the following ones dont need computing
+                py_ir::Statement::If(if_) => self.statements(&if_.branches[0].cond.compute)?,
+                _ => {}
+            }
+            self.newline()?;
+            self.translate(stmt)?;
+        }
+        Ok(())
+    }
+
+    /// if the variable is a reference, it's dereferenced when it's used
+    fn is_ref(&self, name: &str) -> bool {
+        let mut scopes = self.vars.iter().rev();
+        scopes.find_map(|scope| scope.get(name).copied()) == Some(true)
+    }
+
+    /// declare `name` as the type, `name` could be a declarator already, or be empty for
+    /// abstract declarators like in casts
+    ///
+    /// types of function pointers wrap names, like `int64_t (*f)(double)`
+    fn declare(&mut self, ty: &py_ir::types::TypeDefine, name: &str) -> std::fmt::Result {
+        use py_ir::types::TypeDefine;
+        let name = match ty {
+            TypeDefine::Fn(fn_ty) => {
+                let params = self.render(|s| match fn_ty.params.is_empty() {
+                    true => s.write_str("void"),
+                    false => s.translate(&*fn_ty.params),
+                })?;
+                return self.declare(&fn_ty.ty, &format!("(*{name})({params})"));
+            }
+            TypeDefine::Ref(inner) if matches!(**inner, TypeDefine::Fn(..)) => {
+                return self.declare(inner, &format!("*{name}"));
+            }
+            TypeDefine::Ref(inner) => {
+                self.translate(&**inner)?;
+                self.write_char('*')?;
+                name
+            }
+            TypeDefine::Primitive(ty) => {
+                self.translate(ty)?;
+                name
+            }
+            TypeDefine::Complex(ty) => {
+                self.translate(ty)?;
+                name
+            }
+        };
+        match name.is_empty() {
+            true => Ok(()),
+            false => write!(self, " {name}"),
+        }
+    }
+
+    /// what the writer writes, instead of writing it into the file
+    fn render(
+        &mut self,
+        writer: impl FnOnce(&mut Self) -> std::fmt::Result,
+    ) -> Result<String, std::fmt::Error> {
+        let file = std::mem::take(&mut self.c_file);
+        let result = writer(self);
+        let rendered = std::mem::replace(&mut self.c_file, file);
+        result.map(|()| rendered)
+    }
+
+    /// integer literals in c are at most 64 bits wide, wider ones are built by shifting
+    fn integer(
+        &mut self,
+        int: u128,
+        ty: &py_ir::types::PrimitiveType,
+    ) -> Result<(), std::fmt::Error> {
+        match u64::try_from(int) {
+            // literals which cant be represented by int64_t must be unsigned
+            Ok(int) if int > i64::MAX as u64 => write!(self, "{int}u"),
+            Ok(int) => write!(self, "{int}"),
+            Err(_) => {
+                self.write_str("((")?;
+                self.translate(ty)?;
+                let (high, low) = ((int >> 64) as u64, int as u64);
+                write!(self, ")(((py_u128)0x{high:x}u<<64)|0x{low:x}u))")
+            }
+        }
     }
 
     #[inline]
@@ -101,6 +217,12 @@ impl FileModule {
     pub fn h_file(&self) -> &str {
         &self.h_file
     }
+
+    /// names of functions and structs in c, and the mangled names they came from
+    #[inline]
+    pub fn symbols(&self) -> &Symbols {
+        &self.symbols
+    }
 }
 
 impl std::fmt::Write for FileModule {
@@ -112,18 +234,18 @@ impl std::fmt::Write for FileModule {
 impl py_codegen::Backend for CBackend {
     type Error = std::fmt::Error;
 
-    type Config = ();
+    type Config = Config;
 
     type Module<'m> = FileModule
     where
         Self: 'm;
 
-    fn init(_config: Self::Config) -> Self {
-        CBackend
+    fn init(config: Self::Config) -> Self {
+        CBackend { config }
     }
 
     fn module(&self, name: &str, items: &[py_ir::Item]) -> Result<Self::Module<'_>, Self::Error> {
-        let mut module = FileModule::new(name.to_string());
+        let mut module = FileModule::new(name.to_string(), self.config);
         for item in items {
             module.translate(item)?;
         }
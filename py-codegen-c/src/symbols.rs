@@ -0,0 +1,111 @@
+use std::collections::{BTreeMap, HashMap};
+
+fn encode_base32(src: &str) -> String {
+    base32::encode(base32::Alphabet::Crockford, src.as_bytes())
+}
+
+/// transliterate a mangled name into a readable c identifier
+///
+/// * ascii alphanumerics are kept
+/// * continuous whitespaces, `_` and `的` become one `_`
+/// * `参` and `结` are dropped
+/// * other characters are written as their unicode in hex
+///
+/// so, `fib 参 i64 结` will become `py_fib_i64`
+fn transliterate(mangled: &str) -> String {
+    let mut c_name = String::from("py_");
+    for c in mangled.chars() {
+        match c {
+            '参' | '结' => continue,
+            c if c.is_whitespace() || c == '的' || c == '_' => {
+                if !c_name.ends_with('_') {
+                    c_name.push('_');
+                }
+            }
+            c if c.is_ascii_alphanumeric() => c_name.push(c),
+            c => {
+                if !c_name.ends_with('_') {
+                    c_name.push('_');
+                }
+                c_name += &format!("u{:x}_", c as u32);
+            }
+        }
+    }
+    if c_name.ends_with('_') {
+        c_name.pop();
+    }
+    c_name
+}
+
+/// names of functions and structs in c, and mangled names they came from
+#[derive(Debug, Default)]
+pub struct Symbols {
+    readable: bool,
+    /// mangled -> c
+    c_names: HashMap<String, String>,
+    /// c -> mangled
+    mangled: BTreeMap<String, String>,
+}
+
+impl Symbols {
+    pub fn new(readable: bool) -> Self {
+        Self {
+            readable,
+            ..Default::default()
+        }
+    }
+
+    /// get the name in c of the mangled name, a new name will be generated if it's not exist
+    pub fn c_name(&mut self, mangled: &str) -> &str {
+        if !self.c_names.contains_key(mangled) {
+            let c_name = if self.readable {
+                let base = transliterate(mangled);
+                let mut c_name = base.clone();
+                let mut suffix = 0;
+                while self.mangled.contains_key(&c_name) {
+                    suffix += 1;
+                    c_name = format!("{base}_{suffix}");
+                }
+                c_name
+            } else {
+                format!("_{}", encode_base32(mangled))
+            };
+            self.mangled.insert(c_name.clone(), mangled.to_owned());
+            self.c_names.insert(mangled.to_owned(), c_name);
+        }
+        &self.c_names[mangled]
+    }
+
+    /// map from names in c to the mangled names
+    #[inline]
+    pub fn mangled(&self) -> &BTreeMap<String, String> {
+        &self.mangled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn readable() {
+        assert_eq!(transliterate("fib 参 i64 结"), "py_fib_i64");
+        assert_eq!(transliterate("zhu3 参 结"), "py_zhu3");
+        assert_eq!(transliterate("a 参 i64 f32 结"), "py_a_i64_f32");
+        assert_eq!(transliterate("lei4的fang1fa3 参 结"), "py_lei4_fang1fa3");
+        assert_eq!(transliterate("b 参 字 结"), "py_b_u5b57");
+    }
+
+    #[test]
+    fn collision_free() {
+        let mut symbols = Symbols::new(true);
+        let a = symbols.c_name("a 参 i64 结").to_owned();
+        let b = symbols.c_name("a_i64 参 结").to_owned();
+        let c = symbols.c_name("a__i64 参 结").to_owned();
+        assert_eq!(a, "py_a_i64");
+        assert_eq!(b, "py_a_i64_1");
+        assert_eq!(c, "py_a_i64_2");
+        assert_eq!(symbols.c_name("a 参 i64 结"), a);
+        assert_eq!(symbols.mangled()[&c], "a__i64 参 结");
+    }
+}
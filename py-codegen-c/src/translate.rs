@@ -6,51 +6,91 @@ use std::fmt::Write;
 
 use py_ir::value::Value as IRValue;
 
-fn encode_base32(src: &str) -> String {
-    base32::encode(base32::Alphabet::Crockford, src.as_bytes())
+/// if there are no statements but [`py_ir::Location`]s
+fn is_empty(stmts: &py_ir::Statements<IRValue>) -> bool {
+    stmts
+        .iter()
+        .all(|stmt| matches!(stmt, py_ir::Statement::Location(..)))
 }
 
 impl Translate<py_ir::Item<IRValue>> for crate::FileModule {
     fn translate(&mut self, item: &py_ir::Item) -> std::fmt::Result {
         match item {
             py_ir::Item::FnDefine(item) => self.translate(item),
+            py_ir::Item::Struct(item) => self.translate(item),
+            py_ir::Item::Global(item) => self.translate(item),
         }
     }
 }
+impl Translate<py_ir::Struct> for crate::FileModule {
+    fn translate(&mut self, item: &py_ir::Struct) -> std::fmt::Result {
+        let write_struct = |s: &mut crate::FileModule| {
+            let name = s.symbols.c_name(&item.name).to_owned();
+            write!(s, "\ntypedef struct {name} {{")?;
+            s.indent += 1;
+            for field in &item.fields {
+                s.newline()?;
+                s.declare(&field.ty, &field.name)?;
+                s.eol()?;
+            }
+            s.indent -= 1;
+            write!(s, "\n}} {name};\n")
+        };
+        // exported functions may take or return it
+        self.write_header_file(write_struct)?;
+        self.write_source_file(write_struct)
+    }
+}
+impl Translate<py_ir::Global> for crate::FileModule {
+    fn translate(&mut self, item: &py_ir::Global) -> std::fmt::Result {
+        // variables with static storage are zero-initialized
+        self.write_source_file(|s| {
+            s.write_char('\n')?;
+            s.declare(&item.ty, &item.name)?;
+            s.eol()?;
+            s.write_char('\n')
+        })
+    }
+}
 impl Translate<py_ir::FnDefine<IRValue>> for crate::FileModule {
     fn translate(&mut self, item: &py_ir::FnDefine<IRValue>) -> std::fmt::Result {
         let write_sign = |s: &mut crate::FileModule| {
-            s.translate(&item.ty)?;
-            write!(s, " _{}(", encode_base32(&item.name))?;
-            s.translate(&*item.params)?;
-            s.write_char(')')
+            let name = s.symbols.c_name(&item.name).to_owned();
+            let params = s.render(|s| s.translate(&*item.params))?;
+            s.declare(&item.ty, &format!("{name}({params})"))
         };
 
         if item.export {
             self.write_header_file(|s| {
                 write_sign(s)?;
-                s.eol()
+                s.eol()?;
+                s.write_char('\n')
             })?;
         }
 
         self.write_source_file(|s| {
+            s.write_char('\n')?;
             write_sign(s)?;
-            s.translate(&item.body)
+            s.write_char(' ')?;
+            s.translate(&item.body)?;
+            s.write_char('\n')
         })
     }
 }
 impl Translate<py_ir::Parameter<py_ir::types::TypeDefine>> for crate::FileModule {
     fn translate(&mut self, item: &py_ir::Parameter<py_ir::types::TypeDefine>) -> std::fmt::Result {
-        self.translate(&item.ty)?;
-        write!(self, " {}", item.name)
+        self.declare(&item.ty, &item.name)
     }
 }
 impl Translate<py_ir::Statements<IRValue>> for crate::FileModule {
     fn translate(&mut self, item: &py_ir::Statements<IRValue>) -> std::fmt::Result {
         self.write_char('{')?;
-        for item in &**item {
-            self.translate(item)?;
-        }
+        self.indent += 1;
+        self.vars.push(Default::default());
+        self.statements(item)?;
+        self.vars.pop();
+        self.indent -= 1;
+        self.newline()?;
         self.write_char('}')
     }
 }
@@ -63,15 +103,19 @@ impl Translate<py_ir::Statement<IRValue>> for crate::FileModule {
             py_ir::Statement::If(item) => self.translate(item),
             py_ir::Statement::While(item) => self.translate(item),
             py_ir::Statement::Return(item) => self.translate(item),
+            py_ir::Statement::Location(item) => self.translate(item),
         }
     }
 }
 impl Translate<py_ir::VarDefine<IRValue>> for crate::FileModule {
     fn translate(&mut self, item: &py_ir::VarDefine<IRValue>) -> std::fmt::Result {
-        self.translate(&item.ty)?;
-        write!(self, " {}", item.name)?;
+        self.declare(&item.ty, &item.name)?;
+        if let Some(scope) = self.vars.last_mut() {
+            let is_ref = matches!(item.ty, py_ir::types::TypeDefine::Ref(..));
+            scope.insert(item.name.clone(), is_ref);
+        }
         if let Some(init) = &item.init {
-            self.write_char('=')?;
+            self.write_str(" = ")?;
             self.translate(init)?;
         }
         self.eol()
@@ -79,78 +123,95 @@ impl Translate<py_ir::VarDefine<IRValue>> for crate::FileModule {
 }
 impl Translate<py_ir::VarStore<IRValue>> for crate::FileModule {
     fn translate(&mut self, item: &py_ir::VarStore<IRValue>) -> std::fmt::Result {
-        self.write_str(&item.name)?;
-        self.write_char('=')?;
+        match self.is_ref(&item.name) {
+            true => write!(self, "(*{})", item.name)?,
+            false => self.write_str(&item.name)?,
+        }
+        for field in &item.fields {
+            write!(self, ".{field}")?;
+        }
+        self.write_str(" = ")?;
         self.translate(&item.val)?;
         self.eol()
     }
 }
 impl Translate<py_ir::If<IRValue>> for crate::FileModule {
     fn translate(&mut self, item: &py_ir::If<IRValue>) -> std::fmt::Result {
-        let conds = (0..item.branches.len() + 1)
-            .map(|_| self.label())
-            .collect::<Vec<_>>();
-        let codes = (0..item.branches.len() + 1)
-            .map(|_| self.label())
-            .collect::<Vec<_>>();
-
-        let else_block = conds.last().unwrap();
-        let code_after = codes.last().unwrap();
-
-        for branch_idx in 0..item.branches.len() {
-            self.translate(&conds[branch_idx])?;
-            self.translate(&item.branches[branch_idx].cond)?;
-            self.if_else(
-                &item.branches[branch_idx].cond.val,
-                &codes[branch_idx],
-                &conds[branch_idx + 1],
-            )?;
-
-            self.translate(&codes[branch_idx])?;
-            self.translate(&item.branches[branch_idx].body)?;
-            self.goto(code_after)?;
+        // the condition of the first branch has been computed before `if`, see
+        // [`crate::FileModule::statements`], others whose conditions need computing
+        // are nested in `else` blocks
+        let mut nested = 0;
+        for (idx, branch) in item.branches.iter().enumerate() {
+            if idx != 0 {
+                self.write_str(" else ")?;
+                if !is_empty(&branch.cond.compute) {
+                    self.write_char('{')?;
+                    self.indent += 1;
+                    nested += 1;
+                    self.translate(&branch.cond)?;
+                    self.newline()?;
+                }
+            }
+            self.write_str("if (")?;
+            self.translate(&branch.cond.val)?;
+            self.write_str(") ")?;
+            self.translate(&branch.body)?;
         }
 
-        self.translate(else_block)?;
         if let Some(else_) = &item.else_ {
+            self.write_str(" else ")?;
             self.translate(else_)?;
-        } else {
-            self.eol()?;
         }
 
-        self.translate(code_after)
+        for _ in 0..nested {
+            self.indent -= 1;
+            self.newline()?;
+            self.write_char('}')?;
+        }
+        Ok(())
     }
 }
 impl Translate<py_ir::While<IRValue>> for crate::FileModule {
     fn translate(&mut self, item: &py_ir::While<IRValue>) -> std::fmt::Result {
-        let lcond = self.label();
-        let lbody = self.label();
-        let lafter = self.label();
+        if is_empty(&item.cond.compute) {
+            self.write_str("while (")?;
+            self.translate(&item.cond.val)?;
+            self.write_str(") ")?;
+            return self.translate(&item.body);
+        }
 
-        self.translate(&lcond)?;
+        // the condition is computed at the beginning of each loop
+        self.write_str("while (1) {")?;
+        self.indent += 1;
         self.translate(&item.cond)?;
-        self.if_else(&item.cond.val, &lbody, &lafter)?;
-
-        self.translate(&lbody)?;
-        self.translate(&item.body)?;
-        self.goto(&lcond)?;
-
-        self.translate(&lafter)
+        self.newline()?;
+        self.write_str("if (!")?;
+        self.translate(&item.cond.val)?;
+        self.write_str(") break;")?;
+        self.statements(&item.body)?;
+        self.indent -= 1;
+        self.newline()?;
+        self.write_char('}')
     }
 }
 impl Translate<py_ir::Condition<IRValue>> for crate::FileModule {
     fn translate(&mut self, item: &py_ir::Condition<IRValue>) -> std::fmt::Result {
-        for item in &**item.compute {
-            self.translate(item)?;
-        }
-        Ok(())
+        self.statements(&item.compute)
     }
 }
-impl Translate<crate::Label> for crate::FileModule {
-    fn translate(&mut self, item: &crate::Label) -> std::fmt::Result {
-        self.write_str(&item.0)?;
-        self.write_char(':')?;
-        self.eol()
+impl Translate<py_ir::Location> for crate::FileModule {
+    fn translate(&mut self, item: &py_ir::Location) -> std::fmt::Result {
+        if !self.line_directives {
+            return Ok(());
+        }
+        write!(self, "#line {} \"", item.line)?;
+        for c in item.file.chars() {
+            match c {
+                '\\' | '"' => write!(self, "\\{c}")?,
+                c => self.write_char(c)?,
+            }
+        }
+        self.write_char('"')
     }
 }
 impl Translate<py_ir::Return<IRValue>> for crate::FileModule {
@@ -166,12 +227,23 @@ impl Translate<py_ir::value::AssignValue> for crate::FileModule {
     fn translate(&mut self, item: &py_ir::value::AssignValue) -> std::fmt::Result {
         match item {
             py_ir::value::AssignValue::FnCall(fn_call) => {
-                write!(self, "_{}(", encode_base32(&fn_call.fn_name))?;
+                let name = self.symbols.c_name(&fn_call.fn_name).to_owned();
+                write!(self, "{name}(")?;
                 self.translate(&*fn_call.args)?;
                 self.write_char(')')
             }
+            py_ir::value::AssignValue::IndirectCall(call) => {
+                self.translate(&call.callee)?;
+                self.write_char('(')?;
+                self.translate(&*call.args)?;
+                self.write_char(')')
+            }
             py_ir::value::AssignValue::Value(value) => self.translate(value),
-            py_ir::value::AssignValue::Operate(op, _) => match op {
+            py_ir::value::AssignValue::Field(value, field) => {
+                self.translate(value)?;
+                write!(self, ".{field}")
+            }
+            py_ir::value::AssignValue::Operate(op, ty) => match op {
                 py_ir::value::Operate::Unary(op, v) => {
                     let op = match op {
                         py_lex::ops::Operators::Not => "!",
@@ -217,16 +289,25 @@ impl Translate<py_ir::value::AssignValue> for crate::FileModule {
                         py_lex::ops::Operators::Pow => {
                             self.write_str("pow(")?;
                             self.translate(l)?;
-                            self.write_char(',')?;
+                            self.write_str(", ")?;
                             self.translate(r)?;
                             return self.write_char(')');
                         }
                         _ => panic!("unreadable or todo"),
                     };
                     self.translate(l)?;
-                    self.write_str(op)?;
+                    match op {
+                        "." => self.write_str(op)?,
+                        op => write!(self, " {op} ")?,
+                    }
                     self.translate(r)
                 }
+                py_ir::value::Operate::Convert(_, v) => {
+                    self.write_char('(')?;
+                    self.translate(ty)?;
+                    self.write_char(')')?;
+                    self.translate(v)
+                }
             },
         }
     }
@@ -234,17 +315,24 @@ impl Translate<py_ir::value::AssignValue> for crate::FileModule {
 impl Translate<py_ir::value::Value> for crate::FileModule {
     fn translate(&mut self, item: &py_ir::value::Value) -> std::fmt::Result {
         match item {
+            IRValue::Variable(var) if self.is_ref(var) => write!(self, "(*{var})"),
             IRValue::Variable(var) => self.write_str(var),
+            IRValue::Literal(py_ir::value::Literal::Integer(int), ty) => self.integer(*int, ty),
             IRValue::Literal(l, _) => write!(self, "{l}"),
+            IRValue::Fn(name) => {
+                let name = self.symbols.c_name(name).to_owned();
+                self.write_str(&name)
+            }
+            // a reference is passed as it is
+            IRValue::Ref(var) if self.is_ref(var) => self.write_str(var),
+            IRValue::Ref(var) => write!(self, "&{var}"),
         }
     }
 }
+/// the type name, like types in casts, see [`crate::FileModule::declare`] for declarations
 impl Translate<py_ir::types::TypeDefine> for crate::FileModule {
     fn translate(&mut self, item: &py_ir::types::TypeDefine) -> std::fmt::Result {
-        match item {
-            py_ir::types::TypeDefine::Primitive(item) => self.translate(item),
-            py_ir::types::TypeDefine::Complex(item) => self.translate(item),
-        }
+        self.declare(item, "")
     }
 }
 impl Translate<py_ir::types::PrimitiveType> for crate::FileModule {
@@ -259,10 +347,10 @@ impl Translate<py_ir::types::PrimitiveType> for crate::FileModule {
             py_ir::types::PrimitiveType::U32 => "uint32_t",
             py_ir::types::PrimitiveType::I64 => "int64_t",
             py_ir::types::PrimitiveType::U64 => "uint64_t",
-            py_ir::types::PrimitiveType::I128 => todo!("unsuppert"),
-            py_ir::types::PrimitiveType::U128 => todo!("unsuppert"),
-            py_ir::types::PrimitiveType::Usize => todo!("unsuppert"),
-            py_ir::types::PrimitiveType::Isize => todo!("unsuppert"),
+            py_ir::types::PrimitiveType::I128 => "py_i128",
+            py_ir::types::PrimitiveType::U128 => "py_u128",
+            py_ir::types::PrimitiveType::Usize => "size_t",
+            py_ir::types::PrimitiveType::Isize => "intptr_t",
             py_ir::types::PrimitiveType::F32 => "float",
             py_ir::types::PrimitiveType::F64 => "double",
         };
@@ -270,8 +358,22 @@ impl Translate<py_ir::types::PrimitiveType> for crate::FileModule {
     }
 }
 impl Translate<py_ir::types::ComplexType> for crate::FileModule {
-    fn translate(&mut self, _item: &py_ir::types::ComplexType) -> std::fmt::Result {
-        todo!()
+    fn translate(&mut self, item: &py_ir::types::ComplexType) -> std::fmt::Result {
+        // a struct
+        let name = self.symbols.c_name(&item.ty).to_owned();
+        self.write_str(&name)?;
+        // decorators are from the outermost one, declarators are from the innermost one
+        for decorator in item.decorators.iter().rev() {
+            match decorator {
+                py_ir::types::TypeDecorators::Const => self.write_str(" const")?,
+                py_ir::types::TypeDecorators::Array
+                | py_ir::types::TypeDecorators::Reference
+                | py_ir::types::TypeDecorators::Pointer => self.write_str("*")?,
+                // sized arrays are declared with names, like `int64_t a[4]`
+                py_ir::types::TypeDecorators::SizedArray(..) => return Err(std::fmt::Error),
+            }
+        }
+        Ok(())
     }
 }
 impl<Item> Translate<[Item]> for crate::FileModule
@@ -283,10 +385,316 @@ where
             let n = item.len();
             for arg in &item[0..n - 1] {
                 self.translate(arg)?;
-                self.write_char(',')?;
+                self.write_str(", ")?;
             }
             self.translate(&item[n - 1])?;
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use py_ir::types::PrimitiveType;
+
+    fn literal(int: u128, ty: PrimitiveType) -> String {
+        let mut module = crate::FileModule::new("test".to_owned(), Default::default());
+        module.c_file.clear();
+        module
+            .translate(&IRValue::Literal(py_ir::value::Literal::Integer(int), ty))
+            .unwrap();
+        module.c_file
+    }
+
+    #[test]
+    fn wide_integers() {
+        assert_eq!(literal(42, PrimitiveType::I128), "42");
+        assert_eq!(
+            literal(u64::MAX as _, PrimitiveType::U64),
+            "18446744073709551615u"
+        );
+        assert_eq!(
+            literal(u128::MAX, PrimitiveType::U128),
+            "((py_u128)(((py_u128)0xffffffffffffffffu<<64)|0xffffffffffffffffu))"
+        );
+        assert_eq!(
+            literal(1 << 64, PrimitiveType::I128),
+            "((py_i128)(((py_u128)0x1u<<64)|0x0u))"
+        );
+    }
+
+    #[test]
+    fn conversions() {
+        let mut module = crate::FileModule::new("test".to_owned(), Default::default());
+        module.c_file.clear();
+        let convert = py_ir::value::Operate::Convert(PrimitiveType::I64, var("a"));
+        module
+            .translate(&py_ir::value::AssignValue::Operate(
+                convert,
+                PrimitiveType::I128,
+            ))
+            .unwrap();
+        assert_eq!(module.c_file, "(py_i128)a");
+    }
+
+    #[test]
+    fn globals() {
+        let mut module = crate::FileModule::new("test".to_owned(), Default::default());
+        module.c_file.clear();
+        let global = py_ir::Global {
+            ty: PrimitiveType::I64.into(),
+            name: "g".to_owned(),
+        };
+        module.translate(&py_ir::Item::from(global)).unwrap();
+        module.translate(&ret("g")).unwrap();
+        assert_eq!(module.c_file, "\nint64_t g;\nreturn g;");
+    }
+
+    #[test]
+    fn structs() {
+        use py_ir::types::{ComplexType, TypeDefine};
+        let mut module = crate::FileModule::new("test".to_owned(), crate::Config::debug());
+        module.c_file.clear();
+        module.h_file.clear();
+        let point = TypeDefine::from(ComplexType::no_decorators("dian3".to_owned()));
+        let field = |name: &str, ty: TypeDefine| py_ir::Field {
+            ty,
+            name: name.to_owned(),
+        };
+        let struct_ = py_ir::Struct {
+            name: "xian4".to_owned(),
+            fields: vec![
+                field("a", point.clone()),
+                field("n", PrimitiveType::I64.into()),
+            ],
+        };
+        module.translate(&struct_).unwrap();
+        let expect = [
+            "",
+            "typedef struct py_xian4 {",
+            "    py_dian3 a;",
+            "    int64_t n;",
+            "} py_xian4;",
+            "",
+        ];
+        assert_eq!(module.c_file, expect.join("\n"));
+        assert_eq!(module.h_file, module.c_file);
+
+        module.c_file.clear();
+        let store = py_ir::VarStore {
+            name: "l".to_owned(),
+            fields: vec!["a".to_owned(), "x".to_owned()],
+            val: var("b"),
+        };
+        module.translate(&store).unwrap();
+        let load = py_ir::value::AssignValue::Field(var("l"), "n".to_owned());
+        module.translate(&load).unwrap();
+        assert_eq!(module.c_file, "l.a.x = b;l.n");
+    }
+
+    #[test]
+    fn decorated_types() {
+        use py_ir::types::{ComplexType, TypeDecorators};
+        let translate = |decorators| {
+            let mut module = crate::FileModule::new("test".to_owned(), crate::Config::debug());
+            module.c_file.clear();
+            let ty = ComplexType {
+                decorators,
+                ty: "dian3".to_owned(),
+            };
+            module.translate(&ty).map(|()| module.c_file)
+        };
+        use TypeDecorators::*;
+        assert_eq!(translate(vec![Pointer]).unwrap(), "py_dian3*");
+        assert_eq!(
+            translate(vec![Const, Reference, Pointer]).unwrap(),
+            "py_dian3** const"
+        );
+        assert!(translate(vec![Pointer, SizedArray(4)]).is_err());
+    }
+
+    #[test]
+    fn fn_pointers() {
+        use py_ir::types::{FnType, TypeDefine};
+        let mut module = crate::FileModule::new("test".to_owned(), crate::Config::debug());
+        module.c_file.clear();
+        let ty = FnType {
+            ty: Box::new(PrimitiveType::I64.into()),
+            params: vec![PrimitiveType::I64.into(), PrimitiveType::F64.into()],
+        };
+        let callback = FnType {
+            ty: Box::new(ty.clone().into()),
+            params: vec![],
+        };
+        let define = py_ir::VarDefine {
+            ty: TypeDefine::from(callback.clone()),
+            name: "f".to_owned(),
+            init: Some(IRValue::Fn("g".to_owned()).into()),
+            is_temp: false,
+        };
+        module.translate(&define).unwrap();
+        let call = py_ir::value::IndirectCall {
+            callee: var("h"),
+            ty: ty.clone(),
+            args: vec![var("a"), var("b")],
+        };
+        module
+            .translate(&py_ir::value::AssignValue::from(call))
+            .unwrap();
+        assert_eq!(
+            module.c_file,
+            "int64_t (*(*f)(void))(int64_t, double) = py_g;h(a, b)"
+        );
+
+        // the function returns a function pointer
+        module.c_file.clear();
+        let define = py_ir::FnDefine {
+            export: false,
+            ty: ty.into(),
+            name: "k".to_owned(),
+            params: vec![py_ir::Parameter {
+                ty: callback.into(),
+                name: "c".to_owned(),
+            }],
+            body: Default::default(),
+            template: None,
+        };
+        module.translate(&define).unwrap();
+        assert_eq!(
+            module.c_file,
+            "\nint64_t (*py_k(int64_t (*(*c)(void))(int64_t, double)))(int64_t, double) {\n}\n"
+        );
+    }
+
+    fn var(name: &str) -> IRValue {
+        IRValue::Variable(name.to_owned())
+    }
+
+    fn ret(name: &str) -> py_ir::Statement<IRValue> {
+        py_ir::Return {
+            val: Some(var(name)),
+        }
+        .into()
+    }
+
+    fn cond(name: &str, compute: Vec<py_ir::Statement<IRValue>>) -> py_ir::Condition<IRValue> {
+        py_ir::Condition {
+            val: var(name),
+            compute: compute.into(),
+        }
+    }
+
+    fn statements(stmts: Vec<py_ir::Statement<IRValue>>) -> String {
+        let mut module = crate::FileModule::new("test".to_owned(), Default::default());
+        module.c_file.clear();
+        module.translate(&py_ir::Statements::from(stmts)).unwrap();
+        module.c_file
+    }
+
+    #[test]
+    fn structured_control_flow() {
+        let compute = || {
+            vec![py_ir::VarStore {
+                name: "c".to_owned(),
+                fields: vec![],
+                val: var("d"),
+            }
+            .into()]
+        };
+        let if_ = py_ir::If {
+            branches: vec![
+                py_ir::IfBranch {
+                    cond: cond("a", compute()),
+                    body: vec![ret("a")].into(),
+                },
+                py_ir::IfBranch {
+                    cond: cond("b", vec![]),
+                    body: vec![ret("b")].into(),
+                },
+                py_ir::IfBranch {
+                    cond: cond("c", compute()),
+                    body: vec![ret("c")].into(),
+                },
+            ],
+            else_: Some(vec![ret("d")].into()),
+        };
+        let while_ = py_ir::While {
+            cond: cond("c", compute()),
+            body: vec![].into(),
+        };
+
+        let expect = [
+            "{",
+            "    c = d;",
+            "    if (a) {",
+            "        return a;",
+            "    } else if (b) {",
+            "        return b;",
+            "    } else {",
+            "        c = d;",
+            "        if (c) {",
+            "            return c;",
+            "        } else {",
+            "            return d;",
+            "        }",
+            "    }",
+            "    while (1) {",
+            "        c = d;",
+            "        if (!c) break;",
+            "    }",
+            "}",
+        ];
+        assert_eq!(
+            statements(vec![if_.into(), while_.into()]),
+            expect.join("\n")
+        );
+    }
+
+    #[test]
+    fn references() {
+        use py_ir::types::TypeDefine;
+        let define = |name: &str, ty: TypeDefine, init: IRValue| {
+            py_ir::VarDefine {
+                ty,
+                name: name.to_owned(),
+                init: Some(init.into()),
+                is_temp: false,
+            }
+            .into()
+        };
+        let store = |name: &str, val: IRValue| {
+            py_ir::VarStore {
+                name: name.to_owned(),
+                fields: vec![],
+                val,
+            }
+            .into()
+        };
+        let i64_ref = TypeDefine::Ref(Box::new(PrimitiveType::I64.into()));
+        let stmts = vec![
+            define("n", i64_ref.clone(), var("a")),
+            define("m", i64_ref.clone(), IRValue::Ref("n".to_owned())),
+            store("n", var("n")),
+            // a variable which is not a reference shadows it
+            py_ir::Statement::from(py_ir::Statements::from(vec![
+                define("n", PrimitiveType::I64.into(), var("b")),
+                store("n", var("m")),
+                define("k", i64_ref, IRValue::Ref("n".to_owned())),
+            ])),
+        ];
+        let expect = [
+            "{",
+            "    int64_t* n = a;",
+            "    int64_t* m = n;",
+            "    (*n) = (*n);",
+            "    {",
+            "        int64_t n = b;",
+            "        n = (*m);",
+            "        int64_t* k = &n;",
+            "    }",
+            "}",
+        ];
+        assert_eq!(statements(stmts), expect.join("\n"));
+    }
+}
@@ -3,8 +3,9 @@ use inkwell::{
     builder::{Builder, BuilderError},
     context::Context,
     module::Module,
-    types::{BasicType, BasicTypeEnum},
+    types::{BasicType, BasicTypeEnum, FunctionType, IntType, StructType},
     values::{BasicValue, BasicValueEnum, FunctionValue},
+    AddressSpace,
 };
 use py_ir::value as ir_value;
 use py_ir::value::AssignValue as IRAssignValue;
@@ -16,13 +17,19 @@ pub struct ModuleGen<'ctx> {
     pub builder: Builder<'ctx>,
     pub module: Module<'ctx>,
     pub defines: Defines<'ctx>,
+    /// type of pointer-sized integers, which depends on the data layout of the module
+    pub ptr_int: IntType<'ctx>,
 }
 
 pub trait CodeGen<CGU> {
     fn generate(&mut self, cgu: &CGU) -> Result<(), BuilderError>;
 }
 
-fn type_scast<'ctx>(context: &'ctx Context, ty: &ir_types::TypeDefine) -> BasicTypeEnum<'ctx> {
+fn type_scast<'ctx>(
+    context: &'ctx Context,
+    ptr_int: IntType<'ctx>,
+    ty: &ir_types::TypeDefine,
+) -> BasicTypeEnum<'ctx> {
     use ir_types::*;
 
     match ty {
@@ -33,19 +40,112 @@ fn type_scast<'ctx>(context: &'ctx Context, ty: &ir_types::TypeDefine) -> BasicT
             PrimitiveType::I32 | PrimitiveType::U32 => context.i32_type().into(),
             PrimitiveType::I64 | PrimitiveType::U64 => context.i64_type().into(),
             PrimitiveType::I128 | PrimitiveType::U128 => context.i128_type().into(),
-            PrimitiveType::Usize | PrimitiveType::Isize => context.i64_type().into(),
+            PrimitiveType::Usize | PrimitiveType::Isize => ptr_int.into(),
             PrimitiveType::F32 => context.f32_type().into(),
             PrimitiveType::F64 => context.f64_type().into(),
         },
-        TypeDefine::Complex(_ty) => {
-            todo!()
+        TypeDefine::Complex(ty) => {
+            // decorators are from the outermost one, and pointers are opaque so that only
+            // the outermost one matters
+            let outermost = ty
+                .decorators
+                .iter()
+                .position(|dec| *dec != TypeDecorators::Const);
+            match outermost.map(|idx| (idx, &ty.decorators[idx])) {
+                Some((idx, TypeDecorators::SizedArray(len))) => {
+                    let inner = ComplexType {
+                        decorators: ty.decorators[idx + 1..].to_vec(),
+                        ty: ty.ty.clone(),
+                    };
+                    let inner = type_scast(context, ptr_int, &inner.into());
+                    inner.array_type(*len as u32).into()
+                }
+                Some(..) => context.ptr_type(AddressSpace::default()).into(),
+                // structs are declared before being used, see [`ModuleGen::declare_struct`]
+                None => context.get_struct_type(&ty.ty).unwrap().into(),
+            }
         }
+        // pointers are opaque, types of functions are given when they are called
+        TypeDefine::Fn(_) | TypeDefine::Ref(_) => context.ptr_type(AddressSpace::default()).into(),
     }
 }
 
+/// type of functions which values of the function type point to
+fn fn_type_scast<'ctx>(
+    context: &'ctx Context,
+    ptr_int: IntType<'ctx>,
+    ty: &ir_types::FnType,
+) -> FunctionType<'ctx> {
+    let params = ty
+        .params
+        .iter()
+        .map(|param| type_scast(context, ptr_int, param).into())
+        .collect::<Vec<_>>();
+    type_scast(context, ptr_int, &ty.ty).fn_type(&params, false)
+}
+
+fn struct_name(ty: StructType) -> String {
+    ty.get_name().unwrap().to_str().unwrap().to_owned()
+}
+
 impl<'ctx> ModuleGen<'ctx> {
     fn type_cast(&self, ty: &ir_types::TypeDefine) -> BasicTypeEnum<'ctx> {
-        type_scast(self.context, ty)
+        type_scast(self.context, self.ptr_int, ty)
+    }
+
+    /// declare the struct in the context, structs its fields are of must be declared before
+    pub fn declare_struct(&mut self, struct_: &py_ir::Struct) {
+        if self.defines.structs.contains_key(&struct_.name) {
+            return;
+        }
+
+        // the context may be shared by modules which declared the struct
+        if self.context.get_struct_type(&struct_.name).is_none() {
+            let fields = struct_
+                .fields
+                .iter()
+                .map(|field| self.type_cast(&field.ty))
+                .collect::<Vec<_>>();
+            let ty = self.context.opaque_struct_type(&struct_.name);
+            ty.set_body(&fields, false);
+        }
+        let fields = struct_.fields.iter().map(|field| field.name.clone());
+        self.defines
+            .regist_struct(struct_.name.clone(), fields.collect());
+    }
+
+    /// declare the function in the module without body, so that it can be called
+    ///
+    /// functions declared but not defined will be linked from other modules
+    pub fn declare(&mut self, fn_define: &py_ir::FnDefine<IRValue>) -> FunctionValue<'ctx> {
+        if let Some(fn_) = self.defines.fns.get(&fn_define.name) {
+            return *fn_;
+        }
+
+        let retty = self.type_cast(&fn_define.ty);
+        let param_ty = fn_define
+            .params
+            .iter()
+            .map(|param| self.type_cast(&param.ty).into())
+            .collect::<Vec<_>>();
+        let fn_ty = retty.fn_type(&param_ty, false);
+
+        let fn_ = self.module.add_function(&fn_define.name, fn_ty, None);
+        self.defines.regist_fn(fn_define.name.clone(), fn_);
+        fn_
+    }
+
+    /// add the global to the module, it's defined in the module if `define`, or it will be
+    /// linked from other modules like functions
+    pub fn declare_global(&mut self, global: &py_ir::Global, define: bool) {
+        let ty = self.type_cast(&global.ty);
+        let val = self.module.add_global(ty, None, &global.name);
+        if define {
+            val.set_initializer(&ty.const_zero());
+        }
+        let pointer = val.as_pointer_value();
+        let var = AllocVariable { ty, pointer };
+        self.defines.regist_global(global.name.clone(), var);
     }
 }
 
@@ -53,22 +153,16 @@ impl CodeGen<py_ir::Item> for ModuleGen<'_> {
     fn generate(&mut self, cgu: &py_ir::Item) -> Result<(), BuilderError> {
         match cgu {
             py_ir::Item::FnDefine(cgu) => self.generate(cgu),
+            // declared before generating, see [`ModuleGen::declare_struct`] and
+            // [`ModuleGen::declare_global`]
+            py_ir::Item::Struct(..) | py_ir::Item::Global(..) => Ok(()),
         }
     }
 }
 
 impl CodeGen<py_ir::FnDefine<IRValue>> for ModuleGen<'_> {
     fn generate(&mut self, cgu: &py_ir::FnDefine<IRValue>) -> Result<(), BuilderError> {
-        let retty = self.type_cast(&cgu.ty);
-        let param_ty = cgu
-            .params
-            .iter()
-            .map(|param| self.type_cast(&param.ty).into())
-            .collect::<Vec<_>>();
-        let fn_ty = retty.fn_type(&param_ty, false);
-
-        let fn_ = self.module.add_function(&cgu.name, fn_ty, None);
-        self.defines.regist_fn(cgu.name.clone(), fn_);
+        let fn_ = self.declare(cgu);
         let entry = self.context.append_basic_block(fn_, "entry");
         self.builder.position_at_end(entry);
 
@@ -83,6 +177,7 @@ impl CodeGen<py_ir::FnDefine<IRValue>> for ModuleGen<'_> {
             context: self.context,
             builder: &self.builder,
             defines: &mut self.defines,
+            ptr_int: self.ptr_int,
             current_fn: fn_,
             fn_scope: FnScope::new(params),
         };
@@ -97,13 +192,14 @@ struct FnGen<'mg, 'ctx> {
     context: &'ctx Context,
     builder: &'mg Builder<'ctx>,
     defines: &'mg mut Defines<'ctx>,
+    ptr_int: IntType<'ctx>,
     current_fn: FunctionValue<'ctx>,
     fn_scope: FnScope<'ctx>,
 }
 
 impl<'ctx> FnGen<'_, 'ctx> {
     fn type_cast(&self, ty: &ir_types::TypeDefine) -> BasicTypeEnum<'ctx> {
-        type_scast(self.context, ty)
+        type_scast(self.context, self.ptr_int, ty)
     }
 
     fn get_val(&self, name: &str) -> &(dyn Variable<'ctx> + 'ctx) {
@@ -119,6 +215,7 @@ impl<'ctx> FnGen<'_, 'ctx> {
                     .find_map(|map| map.get(name))
                     .map(|val| &**val)
             })
+            .or_else(|| self.globals.get(name).map(|val| val as &dyn Variable))
             .unwrap()
     }
 
@@ -152,6 +249,14 @@ impl<'ctx> FnGen<'_, 'ctx> {
         ty: &ir_types::PrimitiveType,
     ) -> Result<BasicValueEnum<'ctx>, BuilderError> {
         let ret = match literal {
+            ir_value::Literal::Integer(int)
+                if matches!(
+                    ty,
+                    ir_types::PrimitiveType::Isize | ir_types::PrimitiveType::Usize
+                ) =>
+            {
+                self.ptr_int.const_int(*int as _, ty.is_signed()).into()
+            }
             ir_value::Literal::Integer(int) if ty.is_integer() => match ty.width() {
                 1 => self
                     .context
@@ -181,7 +286,7 @@ impl<'ctx> FnGen<'_, 'ctx> {
                 128 => self
                     .context
                     .i128_type()
-                    .const_int(*int as _, ty.is_signed())
+                    .const_int_arbitrary_precision(&[*int as u64, (*int >> 64) as u64])
                     .into(),
                 _ => unreachable!(),
             },
@@ -204,12 +309,44 @@ impl<'ctx> FnGen<'_, 'ctx> {
         match val {
             IRValue::Variable(variable) => self.get_val(variable).load(self.builder),
             IRValue::Literal(literal, ty) => self.literal(literal, ty),
+            IRValue::Fn(name) => {
+                let fn_ = self.get_fn(name).as_global_value().as_pointer_value();
+                Ok(fn_.into())
+            }
+            IRValue::Ref(name) => Ok(self.get_val(name).address().into()),
         }
     }
 
+    /// the value of the struct with the field in the path replaced by `val`
+    fn insert_field(
+        &self,
+        object: BasicValueEnum<'ctx>,
+        fields: &[String],
+        val: BasicValueEnum<'ctx>,
+    ) -> Result<BasicValueEnum<'ctx>, BuilderError> {
+        let Some((field, rest)) = fields.split_first() else {
+            return Ok(val);
+        };
+        let object = object.into_struct_value();
+        let idx = self.field_index(&struct_name(object.get_type()), field);
+        let val = if rest.is_empty() {
+            val
+        } else {
+            let inner = self.builder.build_extract_value(object, idx, "")?;
+            self.insert_field(inner, rest, val)?
+        };
+        let object = self.builder.build_insert_value(object, val, idx, "")?;
+        Ok(object.into_struct_value().into())
+    }
+
     fn eval_assign_val(&self, val: &IRAssignValue) -> Result<BasicValueEnum<'ctx>, BuilderError> {
         match val {
             IRAssignValue::Value(val) => self.eval_val(val),
+            IRAssignValue::Field(val, field) => {
+                let val = self.eval_val(val)?.into_struct_value();
+                let idx = self.field_index(&struct_name(val.get_type()), field);
+                self.builder.build_extract_value(val, idx, "")
+            }
             IRAssignValue::FnCall(fn_call) => {
                 let fn_ = self.get_fn(&fn_call.fn_name);
                 let args = fn_call.args.iter().try_fold(vec![], |mut vec, arg| {
@@ -225,6 +362,21 @@ impl<'ctx> FnGen<'_, 'ctx> {
                     .unwrap_left();
                 Ok(val)
             }
+            IRAssignValue::IndirectCall(call) => {
+                let callee = self.eval_val(&call.callee)?.into_pointer_value();
+                let args = call.args.iter().try_fold(vec![], |mut vec, arg| {
+                    vec.push(self.eval_val(arg)?.into());
+                    Ok(vec)
+                })?;
+
+                let fn_ty = fn_type_scast(self.context, self.ptr_int, &call.ty);
+                let val = self
+                    .builder
+                    .build_indirect_call(fn_ty, callee, &args, "")?
+                    .try_as_basic_value()
+                    .unwrap_left();
+                Ok(val)
+            }
             IRAssignValue::Operate(op, ty) => match op {
                 py_ir::value::Operate::Unary(op, val) => {
                     let val = self.eval_val(val)?;
@@ -235,6 +387,11 @@ impl<'ctx> FnGen<'_, 'ctx> {
                     let r = self.eval_val(r)?;
                     crate::operators::binary(self.builder, *ty, *op, l, r, "")
                 }
+                py_ir::value::Operate::Convert(from, val) => {
+                    let val = self.eval_val(val)?;
+                    let to = self.type_cast(&(*ty).into());
+                    crate::operators::convert(self.builder, *from, to, val, "")
+                }
             },
         }
     }
@@ -271,6 +428,8 @@ impl CodeGen<py_ir::Statement<IRValue>> for FnGen<'_, '_> {
             py_ir::Statement::While(cgu) => self.generate(cgu),
             py_ir::Statement::Return(cgu) => self.generate(cgu),
             py_ir::Statement::Block(cgu) => self.generate(cgu),
+            // TODO: debug information
+            py_ir::Statement::Location(_) => Ok(()),
         }
     }
 }
@@ -278,6 +437,13 @@ impl CodeGen<py_ir::Statement<IRValue>> for FnGen<'_, '_> {
 /// alloca, eval, store
 impl CodeGen<py_ir::VarDefine<IRValue>> for FnGen<'_, '_> {
     fn generate(&mut self, cgu: &py_ir::VarDefine<IRValue>) -> Result<(), BuilderError> {
+        // a reference is used as the variable it points to
+        if let (ir_types::TypeDefine::Ref(ty), Some(init)) = (&cgu.ty, &cgu.init) {
+            let ty = self.type_cast(ty);
+            let pointer = self.eval_assign_val(init)?.into_pointer_value();
+            self.regist_var(cgu.name.clone(), AllocVariable { ty, pointer });
+            return Ok(());
+        }
         let init = match &cgu.init {
             Some(init) => {
                 let val = self.eval_assign_val(init)?;
@@ -307,6 +473,10 @@ impl CodeGen<py_ir::VarStore<IRValue>> for FnGen<'_, '_> {
     fn generate(&mut self, cgu: &py_ir::VarStore<IRValue>) -> Result<(), BuilderError> {
         let val = self.eval_val(&cgu.val)?;
         let s = self.get_val(&cgu.name);
+        let val = match cgu.fields.is_empty() {
+            true => val,
+            false => self.insert_field(s.load(self.builder)?, &cgu.fields, val)?,
+        };
         s.store(self.builder, val)?;
         Ok(())
     }
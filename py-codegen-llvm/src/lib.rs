@@ -6,10 +6,19 @@ use std::error::Error;
 
 use codegen::CodeGen;
 pub use inkwell;
-use inkwell::{context::Context, module::Module};
+use inkwell::{
+    context::Context,
+    module::Module,
+    targets::{
+        CodeModel, InitializationConfig, RelocMode, Target, TargetData, TargetMachine, TargetTriple,
+    },
+    OptimizationLevel,
+};
 
 pub struct LLVMBackend {
     context: Context,
+    /// target triple and data layout of modules, unset for the default data layout
+    target: Option<(TargetTriple, TargetData)>,
 }
 
 impl py_codegen::Backend for LLVMBackend {
@@ -20,16 +29,80 @@ impl py_codegen::Backend for LLVMBackend {
     fn init(_config: Self::Config) -> Self {
         Self {
             context: Context::create(),
+            target: None,
         }
     }
 
     fn module(&self, name: &str, items: &[py_ir::Item]) -> Result<Module<'_>, Self::Error> {
+        self.linked_module(name, items, &[])
+    }
+}
+
+impl LLVMBackend {
+    /// backend whose modules are generated for the host, like modules executed with jit
+    ///
+    /// widths of pointer-sized integers are the host's
+    pub fn native() -> Result<Self, Box<dyn Error>> {
+        Target::initialize_native(&InitializationConfig::default())?;
+        let triple = TargetMachine::get_default_triple();
+        let machine = Target::from_triple(&triple)
+            .map_err(|e| e.to_string())?
+            .create_target_machine(
+                &triple,
+                "",
+                "",
+                OptimizationLevel::Default,
+                RelocMode::Default,
+                CodeModel::JITDefault,
+            )
+            .ok_or("target machine of the host cant be created")?;
+        Ok(Self {
+            context: Context::create(),
+            target: Some((triple, machine.get_target_data())),
+        })
+    }
+
+    /// like [`py_codegen::Backend::module`], but functions and globals in `externs` are
+    /// declared in the module, so that they can be used, and linked from other modules later
+    pub fn linked_module(
+        &self,
+        name: &str,
+        items: &[py_ir::Item],
+        externs: &[py_ir::Item],
+    ) -> Result<Module<'_>, Box<dyn Error>> {
+        let module = self.context.create_module(name);
+        let ptr_int = match &self.target {
+            Some((triple, target_data)) => {
+                module.set_triple(triple);
+                module.set_data_layout(&target_data.get_data_layout());
+                self.context.ptr_sized_int_type(target_data, None)
+            }
+            // pointers are 64 bits wide in the default data layout
+            None => self.context.i64_type(),
+        };
         let mut mod_gen = codegen::ModuleGen {
             context: &self.context,
             builder: self.context.create_builder(),
-            module: self.context.create_module(name),
+            module,
             defines: Default::default(),
+            ptr_int,
         };
+        // declare all structs and functions first, so that they can be used before being
+        // defined
+        for item in externs.iter().chain(items) {
+            match item {
+                py_ir::Item::Struct(struct_) => mod_gen.declare_struct(struct_),
+                py_ir::Item::FnDefine(..) | py_ir::Item::Global(..) => {}
+            }
+        }
+        for (idx, item) in externs.iter().chain(items).enumerate() {
+            match item {
+                py_ir::Item::FnDefine(fn_define) => _ = mod_gen.declare(fn_define),
+                // globals in `externs` are defined in other modules
+                py_ir::Item::Global(global) => mod_gen.declare_global(global, idx >= externs.len()),
+                py_ir::Item::Struct(..) => {}
+            }
+        }
         for item in items {
             mod_gen.generate(item)?;
         }
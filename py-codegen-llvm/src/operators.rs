@@ -1,5 +1,6 @@
 use inkwell::{
     builder::{Builder, BuilderError},
+    types::BasicTypeEnum,
     values::BasicValueEnum,
 };
 use py_ir::types::PrimitiveType;
@@ -151,3 +152,30 @@ pub fn unary<'ctx>(
         Ok(val)
     }
 }
+
+/// implicit promotion of the value from the type `from` to the type `to`
+pub fn convert<'ctx>(
+    builder: &Builder<'ctx>,
+    from: PrimitiveType,
+    to: BasicTypeEnum<'ctx>,
+    val: BasicValueEnum<'ctx>,
+    name: &str,
+) -> Result<BasicValueEnum<'ctx>, BuilderError> {
+    let val = if from.is_float() {
+        let to = to.into_float_type();
+        builder
+            .build_float_ext(val.into_float_value(), to, name)?
+            .into()
+    } else if from.is_signed() {
+        let to = to.into_int_type();
+        builder
+            .build_int_s_extend(val.into_int_value(), to, name)?
+            .into()
+    } else {
+        let to = to.into_int_type();
+        builder
+            .build_int_z_extend(val.into_int_value(), to, name)?
+            .into()
+    };
+    Ok(val)
+}
@@ -7,12 +7,17 @@ use inkwell::values::{BasicValueEnum, FunctionValue, PointerValue};
 /// this is not the most elegant way, but it works for now
 pub struct Defines<'ctx> {
     pub fns: HashMap<String, FunctionValue<'ctx>>,
+    /// names of fields of structs, in order
+    pub structs: HashMap<String, Vec<String>>,
+    pub globals: HashMap<String, AllocVariable<'ctx>>,
 }
 
 impl<'ctx> Defines<'ctx> {
     pub fn new() -> Self {
         Self {
             fns: Default::default(),
+            structs: Default::default(),
+            globals: Default::default(),
         }
     }
 
@@ -23,6 +28,19 @@ impl<'ctx> Defines<'ctx> {
     pub fn regist_fn(&mut self, name: String, val: FunctionValue<'ctx>) {
         self.fns.insert(name, val);
     }
+
+    pub fn field_index(&self, struct_: &str, field: &str) -> u32 {
+        let fields = self.structs.get(struct_).unwrap();
+        fields.iter().position(|name| name == field).unwrap() as _
+    }
+
+    pub fn regist_struct(&mut self, name: String, fields: Vec<String>) {
+        self.structs.insert(name, fields);
+    }
+
+    pub fn regist_global(&mut self, name: String, val: AllocVariable<'ctx>) {
+        self.globals.insert(name, val);
+    }
 }
 
 impl Default for Defines<'_> {
@@ -59,6 +77,8 @@ pub trait Variable<'ctx> {
         builder: &Builder<'ctx>,
         value: BasicValueEnum<'ctx>,
     ) -> Result<(), BuilderError>;
+    /// the pointer to the variable, which is taken by references
+    fn address(&self) -> PointerValue<'ctx>;
 }
 
 /// variables from allocation, like heap/stack variables
@@ -82,6 +102,9 @@ impl<'ctx> Variable<'ctx> for AllocVariable<'ctx> {
     ) -> Result<(), BuilderError> {
         builder.build_store(self.pointer, value).map(|_| ())
     }
+    fn address(&self) -> PointerValue<'ctx> {
+        self.pointer
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -101,4 +124,8 @@ impl<'ctx> Variable<'ctx> for ComputeResult<'ctx> {
     ) -> Result<(), BuilderError> {
         unreachable!("this invalid operation should be filtered in mir")
     }
+
+    fn address(&self) -> PointerValue<'ctx> {
+        unreachable!("only variables which could be assigned are referenced")
+    }
 }
@@ -116,6 +116,14 @@ impl BranchesBuilder {
         }
     }
 
+    /// a branch which is failed from the start, because the type is impossible
+    pub fn new_failed(ty: Type, reason: DeclareError) -> Self {
+        Self {
+            state: Err(reason.with_previous(ty)),
+            depends: Default::default(),
+        }
+    }
+
     /// # Return
     ///
     /// is self.state ok
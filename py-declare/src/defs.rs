@@ -1,12 +1,18 @@
 use crate::*;
-use py_ir::types::TypeDefine;
+use py_ir::types::{ComplexType, FnType, TypeDefine};
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use terl::Span;
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Defs {
     pub(crate) fn_signs: FnSigns,
+    pub(crate) generics: Generics,
+    pub(crate) classes: HashMap<String, Class>,
+    pub(crate) interfaces: HashMap<String, Interface>,
+    pub(crate) modules: HashMap<String, Module>,
+    /// mangled names of functions which could be used outside their modules
+    pub(crate) public: HashSet<String>,
 }
 
 impl Defs {
@@ -29,13 +35,392 @@ impl Defs {
     pub fn get_unmangled(&self, name: &str) -> Option<&[Overload]> {
         self.fn_signs.get_unmangled(name)
     }
+
+    /// set the return type of a function whose return type is inferred
+    pub fn set_return_type(&mut self, mangled: &str, ty: TypeDefine) {
+        self.fn_signs.set_return_type(mangled, ty)
+    }
+
+    /// return the index of the generic function, see [`Generic::idx`]
+    pub fn new_generic(
+        &mut self,
+        name: &str,
+        generics: Vec<String>,
+        bounds: Vec<Vec<String>>,
+        sign: FnSign,
+    ) -> usize {
+        self.generics.new_generic(name, generics, bounds, sign)
+    }
+
+    /// generic functions with the name
+    pub fn get_generics(&self, name: &str) -> impl Iterator<Item = &Generic> {
+        self.generics.get_generics(name)
+    }
+
+    pub fn get_generic(&self, idx: usize) -> &Generic {
+        &self.generics.generics[idx]
+    }
+
+    /// regist an instance of a generic function, so that it could be found by
+    /// [`Defs::get_mangled`], but it's not an overload which could be called by its name
+    pub fn regist_instance(&mut self, instance: Overload) {
+        self.fn_signs.regist_instance(instance)
+    }
+
+    pub fn new_class(&mut self, class: Class) {
+        self.classes.insert(class.name.clone(), class);
+    }
+
+    pub fn get_class(&self, name: &str) -> Option<&Class> {
+        self.classes.get(name)
+    }
+
+    /// the class which the type is, if it's a class
+    pub fn class_of(&self, ty: &TypeDefine) -> Option<&Class> {
+        match ty {
+            TypeDefine::Complex(ty) if ty.decorators.is_empty() => self.get_class(&ty.ty),
+            _ => None,
+        }
+    }
+
+    /// a method could be found by [`Defs::get_mangled`], but it's not an overload which
+    /// could be called by its name
+    pub fn new_method(&mut self, class: &str, name: &str, mangled: &str, sign: FnSign) -> Type {
+        self.fn_signs.new_method(class, name, mangled, sign)
+    }
+
+    pub fn get_methods(&self, class: &str, name: &str) -> Option<&[Overload]> {
+        self.fn_signs.get_methods(class, name)
+    }
+
+    pub fn new_interface(&mut self, interface: Interface) {
+        self.interfaces.insert(interface.name.clone(), interface);
+    }
+
+    pub fn get_interface(&self, name: &str) -> Option<&Interface> {
+        self.interfaces.get(name)
+    }
+
+    pub fn new_module(&mut self, module: Module) {
+        self.modules.insert(module.path.clone(), module);
+    }
+
+    /// the module with the qualified name, see [`Module::qualify`]
+    pub fn get_module(&self, path: &str) -> Option<&Module> {
+        self.modules.get(path)
+    }
+
+    /// mark that the function could be used outside the module which it's in
+    pub fn publish_fn(&mut self, mangled: &str) {
+        self.public.insert(mangled.to_owned());
+    }
+
+    pub fn is_public(&self, mangled: &str) -> bool {
+        self.public.contains(mangled)
+    }
+
+    /// mark that the generic function could be used outside the module which it's in
+    pub fn publish_generic(&mut self, idx: usize) {
+        self.generics.generics[idx].public = true;
+    }
+
+    /// signatures which the interface requires, but the type has no functions with
+    ///
+    /// functions are searched by their names, and methods of the class are searched too if
+    /// the type is a class
+    pub fn missing(&self, ty: &TypeDefine, interface: &Interface) -> Vec<FnSignWithName> {
+        let implemented = |required: &FnSignWithName| {
+            let fns = self.get_unmangled(&required.name).unwrap_or_default();
+            let methods = self
+                .class_of(ty)
+                .and_then(|class| self.get_methods(&class.name, &required.name))
+                .unwrap_or_default();
+            fns.iter().chain(methods).any(|overload| {
+                let params = overload.params.iter().map(|param| &param.ty);
+                params.eq(required.params.iter().map(|param| &param.ty))
+                    && (overload.inferred || overload.ty == required.ty)
+            })
+        };
+        interface
+            .required(ty)
+            .filter(|required| !implemented(required))
+            .collect()
+    }
+}
+
+/// paths are written like `shu4 de1 nei4 de1 jia`
+const PATH_SEPARATOR: &str = " de1 ";
+
+/// a module, names of items in it are qualified by the path of the module, see
+/// [`Module::qualify`]
+///
+/// the path of items outside modules is empty
+#[derive(Debug, Clone)]
+pub struct Module {
+    /// the qualified name
+    pub path: String,
+    /// if it could be used outside the module which it's in
+    pub public: bool,
+    /// span of the name
+    pub span: Span,
+}
+
+impl Module {
+    /// the qualified name of the item in the module
+    pub fn qualify(module: &str, name: &str) -> String {
+        match module.is_empty() {
+            true => name.to_owned(),
+            false => format!("{module}{PATH_SEPARATOR}{name}"),
+        }
+    }
+
+    /// the path of the module which the item is in, and the name of the item
+    pub fn split(qualified: &str) -> (&str, &str) {
+        qualified
+            .rsplit_once(PATH_SEPARATOR)
+            .unwrap_or(("", qualified))
+    }
+
+    /// names of modules in the path, from the outermost one
+    pub fn names(path: &str) -> impl Iterator<Item = &str> {
+        path.split(PATH_SEPARATOR).filter(|name| !name.is_empty())
+    }
+
+    /// the module and modules which it's in, from the innermost one to the empty path
+    pub fn ancestors(path: &str) -> impl Iterator<Item = &str> {
+        std::iter::successors(Some(path), |path| {
+            (!path.is_empty()).then(|| Self::split(path).0)
+        })
+    }
+
+    /// if `inner` is the module `outer`, or a module in it
+    pub fn contains(outer: &str, inner: &str) -> bool {
+        outer.is_empty()
+            || inner == outer
+            || inner
+                .strip_prefix(outer)
+                .is_some_and(|rest| rest.starts_with(PATH_SEPARATOR))
+    }
+}
+
+/// a class, whose values are [`py_ir::Struct`]s
+#[derive(Debug, Clone)]
+pub struct Class {
+    pub name: String,
+    pub fields: Vec<py_ir::Field>,
+    /// span of the name
+    pub span: Span,
+}
+
+impl Class {
+    pub fn field(&self, name: &str) -> Option<&py_ir::Field> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    pub fn ty(&self) -> TypeDefine {
+        ComplexType::no_decorators(self.name.clone()).into()
+    }
+}
+
+/// functions which types implementing the interface must have
+#[derive(Debug, Clone)]
+pub struct Interface {
+    pub name: String,
+    /// the name which the type implementing the interface is written as in signatures
+    pub this: String,
+    /// signatures with unmangled names
+    pub fns: Vec<FnSignWithName>,
+    /// span of the name
+    pub span: Span,
+}
+
+impl Interface {
+    /// signatures which the type must have functions with
+    pub fn required<'i>(&'i self, ty: &'i TypeDefine) -> impl Iterator<Item = FnSignWithName> + 'i {
+        let replace = move |this: &TypeDefine| {
+            this.replaced(&|this| match this {
+                TypeDefine::Complex(this) if this.decorators.is_empty() && this.ty == self.this => {
+                    Some(ty.clone())
+                }
+                _ => None,
+            })
+        };
+        self.fns.iter().map(move |required| {
+            let mut sign = required.sign.clone();
+            sign.ty = replace(&sign.ty);
+            for param in &mut sign.params {
+                param.ty = replace(&param.ty);
+            }
+            FnSignWithName {
+                sign,
+                name: required.name.clone(),
+            }
+        })
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct Generics {
+    generics: Vec<Generic>,
+    names: HashMap<String, Vec<usize>>,
 }
 
-#[derive(Default)]
+impl Generics {
+    pub fn new_generic(
+        &mut self,
+        name: &str,
+        generics: Vec<String>,
+        bounds: Vec<Vec<String>>,
+        sign: FnSign,
+    ) -> usize {
+        let idx = self.generics.len();
+        self.generics.push(Generic {
+            idx,
+            name: name.to_owned(),
+            generics,
+            bounds,
+            sign,
+            public: false,
+        });
+        self.names.entry(name.to_owned()).or_default().push(idx);
+        idx
+    }
+
+    pub fn get_generics(&self, name: &str) -> impl Iterator<Item = &Generic> {
+        let idxs = self.names.get(name).map(|idxs| &**idxs).unwrap_or_default();
+        idxs.iter().map(|idx| &self.generics[*idx])
+    }
+}
+
+/// a generic function, whose instances are generated for type arguments of calls
+#[derive(Debug, Clone)]
+pub struct Generic {
+    /// index of the generic function in the order they are registered
+    pub idx: usize,
+    /// unmangled name
+    pub name: String,
+    /// names of type parameters
+    pub generics: Vec<String>,
+    /// names of interfaces which type arguments of each type parameter must implement
+    pub bounds: Vec<Vec<String>>,
+    /// the signature, with type parameters in it
+    pub sign: FnSign,
+    /// if it could be used outside the module which it's in
+    pub public: bool,
+}
+
+impl Generic {
+    /// the index of the type parameter which the type is
+    pub fn type_param(&self, ty: &TypeDefine) -> Option<usize> {
+        match ty {
+            TypeDefine::Complex(ty) if ty.decorators.is_empty() => {
+                self.generics.iter().position(|name| *name == ty.ty)
+            }
+            _ => None,
+        }
+    }
+
+    /// replace type parameters in the type with type arguments
+    pub fn instantiate_ty(&self, ty: &TypeDefine, args: &[TypeDefine]) -> TypeDefine {
+        ty.replaced(&|ty| self.type_param(ty).map(|idx| args[idx].clone()))
+    }
+
+    /// the signature of the instance for type arguments
+    pub fn instantiate(&self, args: &[TypeDefine]) -> FnSign {
+        let params = self
+            .sign
+            .params
+            .iter()
+            .map(|param| Parameter {
+                ty: self.instantiate_ty(&param.ty, args),
+                name: param.name.clone(),
+            })
+            .collect();
+        let ty = self.instantiate_ty(&self.sign.ty, args);
+        let mut sign = FnSign::new(ty, params, self.sign.retty_span, self.sign.sign_span);
+        sign.instance = Some(Instance {
+            generic: self.idx,
+            args: args.to_vec(),
+        });
+        sign
+    }
+
+    /// the first type argument which doesnt implement an interface its type parameter is
+    /// bound by, with the index of the type parameter and the interface
+    pub fn unsatisfied<'d>(
+        &self,
+        defs: &'d Defs,
+        args: &[TypeDefine],
+    ) -> Option<(usize, &'d Interface)> {
+        let mut bounds = self.bounds.iter().zip(args).enumerate();
+        bounds.find_map(|(idx, (bounds, arg))| {
+            let arg = Type::from(arg.clone());
+            let mut interfaces = bounds.iter().filter_map(|name| defs.get_interface(name));
+            interfaces
+                .find(|interface| {
+                    let filter = filters::Implements::new(defs, interface, self.sign.sign_span);
+                    !BranchFilter::<Directly>::satisfy(&filter, &arg)
+                })
+                .map(|interface| (idx, interface))
+        })
+    }
+
+    /// type arguments which the function is called with
+    ///
+    /// `args` are types which each argument could be declared as, and `defaults` are types
+    /// which they are declared as if nothing else constrains them. a type argument is the
+    /// only type which all arguments passed as its type parameter could be declared as, or
+    /// the first default type of them which they all could be declared as. it's `None` if
+    /// any type parameter cant be inferred so
+    pub fn infer(
+        &self,
+        args: &[Vec<TypeDefine>],
+        defaults: &[Option<TypeDefine>],
+    ) -> Option<Vec<TypeDefine>> {
+        if self.sign.params.len() != args.len() {
+            return None;
+        }
+        (0..self.generics.len())
+            .map(|idx| {
+                let passed = self
+                    .sign
+                    .params
+                    .iter()
+                    .zip(args.iter().zip(defaults))
+                    .filter(|(param, _)| self.type_param(&param.ty) == Some(idx))
+                    .map(|(_, arg)| arg)
+                    .collect::<Vec<_>>();
+                let (first, _) = passed.first()?;
+                let mut candidates = (*first).clone();
+                for (arg, _) in &passed[1..] {
+                    candidates.retain(|ty| arg.contains(ty));
+                }
+                if let [ty] = &*candidates {
+                    return Some(ty.clone());
+                }
+                let mut defaults = passed.iter().filter_map(|(_, default)| default.as_ref());
+                defaults
+                    .find(|default| candidates.contains(default))
+                    .cloned()
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Display for Generic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}<{}>", self.name, self.generics.join(", "))?;
+        fmt_sign(&self.sign, f)
+    }
+}
+
+#[derive(Default, Clone)]
 pub struct FnSigns {
     fn_signs: Vec<Overload>,
     unmangled: HashMap<String, Vec<Overload>>,
     mangled: HashMap<String, Overload>,
+    /// (class, name) -> methods
+    methods: HashMap<(String, String), Vec<Overload>>,
 }
 
 impl FnSigns {
@@ -60,10 +445,32 @@ impl FnSigns {
         Type::Overload(overload)
     }
 
+    pub fn new_method(&mut self, class: &str, name: &str, mangled: &str, sign: FnSign) -> Type {
+        let value = defs::FnSignWithName {
+            sign,
+            name: mangled.to_owned(),
+        };
+
+        let overload: Overload = value.into();
+
+        self.fn_signs.push(overload.clone());
+        self.methods
+            .entry((class.to_owned(), name.to_owned()))
+            .or_default()
+            .push(overload.clone());
+        self.mangled.insert(mangled.to_owned(), overload.clone());
+        Type::Overload(overload)
+    }
+
     pub fn get_unmangled(&self, name: &str) -> Option<&[Overload]> {
         self.unmangled.get(name).map(|v| &**v)
     }
 
+    pub fn get_methods(&self, class: &str, name: &str) -> Option<&[Overload]> {
+        let key = (class.to_owned(), name.to_owned());
+        self.methods.get(&key).map(|v| &**v)
+    }
+
     pub fn get_mangled(&self, name: &str) -> &Overload {
         self.mangled.get(name).unwrap()
     }
@@ -72,6 +479,31 @@ impl FnSigns {
         self.mangled.get(name)
     }
 
+    pub fn regist_instance(&mut self, instance: Overload) {
+        self.fn_signs.push(instance.clone());
+        self.mangled.insert(instance.name.clone(), instance);
+    }
+
+    pub fn set_return_type(&mut self, mangled: &str, ty: TypeDefine) {
+        let previous = self.mangled.get(mangled).unwrap().clone();
+        let mut value = (*previous).clone();
+        value.sign.ty = ty;
+        let overload: Overload = value.into();
+
+        let replace = |overloads: &mut [Overload]| {
+            for item in overloads {
+                if Overload::ptr_eq(item, &previous) {
+                    *item = overload.clone();
+                }
+            }
+        };
+        replace(&mut self.fn_signs);
+        for overloads in self.unmangled.values_mut().chain(self.methods.values_mut()) {
+            replace(overloads);
+        }
+        self.mangled.insert(mangled.to_owned(), overload);
+    }
+
     // pub fn search_fns
 }
 
@@ -97,20 +529,57 @@ impl std::ops::DerefMut for FnSignWithName {
 
 #[derive(Debug, Clone)]
 pub struct FnSign {
-    /// return type of the function must be cleared (will change in future versions)
+    /// return type of the function
+    ///
+    /// if it's inferred, it's `zi4dong4` until the function is generated, see
+    /// [`Defs::set_return_type`]
     pub ty: TypeDefine,
+    /// if the return type is inferred from values returned by `fan3`
+    pub inferred: bool,
     pub params: Vec<Parameter>,
     pub retty_span: Span,
     pub sign_span: Span,
+    /// the generic function and type arguments, if it's an instance of a generic function
+    pub instance: Option<Instance>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Instance {
+    /// [`Generic::idx`]
+    pub generic: usize,
+    pub args: Vec<TypeDefine>,
 }
 
 impl FnSign {
     pub fn new(ty: TypeDefine, params: Vec<Parameter>, retty_span: Span, sign_span: Span) -> Self {
         Self {
             ty,
+            inferred: false,
+            params,
+            retty_span,
+            sign_span,
+            instance: None,
+        }
+    }
+
+    /// type of the function as a value
+    pub fn fn_type(&self) -> FnType {
+        FnType {
+            ty: Box::new(self.ty.clone()),
+            params: self.params.iter().map(|param| param.ty.clone()).collect(),
+        }
+    }
+
+    /// a function whose return type is inferred
+    pub fn inferred(params: Vec<Parameter>, retty_span: Span, sign_span: Span) -> Self {
+        let placeholder = py_lex::types::TypePlaceholder::Auto.to_string();
+        Self {
+            ty: ComplexType::no_decorators(placeholder).into(),
+            inferred: true,
             params,
             retty_span,
             sign_span,
+            instance: None,
         }
     }
 }
@@ -120,20 +589,29 @@ impl std::fmt::Display for FnSignWithName {
         // NOTO: this may only work with default Chinese Mangler
         let unmangled = self.name.split_ascii_whitespace().next().unwrap();
         f.write_str(unmangled)?;
-        f.write_str("(")?;
-        match self.params.len() {
-            0 => f.write_str(")")?,
-            1 => f.write_fmt(format_args!("{})", self.params[0].ty))?,
-            _ => {
-                f.write_fmt(format_args!("{}", self.params[0].ty))?;
-                for param in &self.params[1..] {
-                    f.write_fmt(format_args!(", {}", param.name))?;
-                }
-                f.write_str(")")?
+        if let Some(instance) = &self.instance {
+            let args = instance.args.iter().map(|arg| arg.to_string());
+            write!(f, "<{}>", args.collect::<Vec<_>>().join(", "))?;
+        }
+        fmt_sign(&self.sign, f)
+    }
+}
+
+/// parameters and the return type
+fn fmt_sign(sign: &FnSign, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str("(")?;
+    match sign.params.len() {
+        0 => f.write_str(")")?,
+        1 => f.write_fmt(format_args!("{})", sign.params[0].ty))?,
+        _ => {
+            f.write_fmt(format_args!("{}", sign.params[0].ty))?;
+            for param in &sign.params[1..] {
+                f.write_fmt(format_args!(", {}", param.ty))?;
             }
+            f.write_str(")")?
         }
-        f.write_fmt(format_args!(" -> {}", self.ty))
     }
+    f.write_fmt(format_args!(" -> {}", sign.ty))
 }
 
 pub type Parameter = py_ir::Parameter<TypeDefine>;
@@ -142,4 +620,6 @@ pub type Parameter = py_ir::Parameter<TypeDefine>;
 pub struct VarDef {
     pub ty: GroupIdx,
     pub mutable: bool,
+    /// span of the name where the variable is defined
+    pub define: Span,
 }
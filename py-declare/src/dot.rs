@@ -0,0 +1,164 @@
+use crate::*;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+use terl::{Span, WithSpan};
+
+/// escape a string to be used in a quoted id of graphviz, tags of messages are removed
+fn escape(str: &str) -> String {
+    py_lex::messages::untag(str)
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn node(branch: Branch) -> String {
+    format!("g{}b{}", branch.belong_to.idx, branch.branch_idx)
+}
+
+/// why the branch was removed, in one line
+fn describe(reason: &DeclareError, locate: &impl Fn(Span) -> String) -> String {
+    let (_, reason) = reason.split_previous();
+    reason
+        .generate()
+        .into_iter()
+        .map(|msg| match msg {
+            terl::Message::Location(at) => locate(at),
+            terl::Message::Text(text) => text,
+            terl::Message::Rich(text, at) => format!("{text} ({})", locate(at)),
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+impl DeclareGraph {
+    /// dump the graph in graphviz's dot language
+    ///
+    /// groups are clusters, and branches are nodes in them. an edge points from a branch
+    /// to a branch which it depends on. removed branches and their edges are dashed, and
+    /// labeled with the reason why they were removed. the type a group is declared as is
+    /// drawn in bold
+    ///
+    /// `locate` is used to show where [`Span`]s are
+    pub fn to_dot(&self, name: &str, locate: impl Fn(Span) -> String) -> String {
+        let mut dot = String::new();
+        _ = writeln!(dot, "digraph \"{}\" {{", escape(name));
+        _ = writeln!(dot, "    label=\"{}\";", escape(name));
+        _ = writeln!(dot, "    node [shape=box];");
+
+        for (idx, group) in self.groups.iter().enumerate() {
+            let gidx = GroupIdx::new(idx);
+            let result = if group.is_declared() {
+                group.result().to_string()
+            } else {
+                py_lex::msg!(CantInferType)
+            };
+            let label = format!(
+                "group {} at {}\n{result}",
+                gidx.idx,
+                locate(group.get_span())
+            );
+
+            _ = writeln!(dot, "    subgraph cluster_{} {{", gidx.idx);
+            _ = writeln!(dot, "        label=\"{}\";", escape(&label));
+
+            let mut alives = group.alives(|alives| alives.collect::<Vec<_>>());
+            alives.sort_by_key(|(branch, _)| branch.branch_idx);
+            let declared = group.is_declared();
+            for (branch, ty) in alives {
+                let style = if declared { ", style=bold" } else { "" };
+                let label = escape(&ty.to_string());
+                _ = writeln!(dot, "        {} [label=\"{label}\"{style}];", node(branch));
+            }
+
+            let mut failds = group.failds().iter().collect::<Vec<_>>();
+            failds.sort_by_key(|(branch, _)| **branch);
+            for (&branch, reason) in failds {
+                let ty = match reason.split_previous() {
+                    (Some(ty), _) => ty.to_string(),
+                    (None, _) => "?".to_owned(),
+                };
+                let label = format!("{ty}\n{}", describe(reason, &locate));
+                _ = writeln!(
+                    dot,
+                    "        {} [label=\"{}\", style=dashed, color=gray];",
+                    node(Branch::new(gidx, branch)),
+                    escape(&label)
+                );
+            }
+            _ = writeln!(dot, "    }}");
+        }
+
+        let edges = |deps: &HashMap<Branch, HashSet<Branch>>| {
+            let mut edges = deps
+                .iter()
+                .flat_map(|(from, to)| to.iter().map(move |to| (node(*from), node(*to))))
+                .collect::<Vec<_>>();
+            edges.sort_unstable();
+            edges
+        };
+        for (from, to) in edges(&self.deps) {
+            _ = writeln!(dot, "    {from} -> {to};");
+        }
+        for (from, to) in edges(&self.removed_deps) {
+            _ = writeln!(dot, "    {from} -> {to} [style=dashed, color=gray];");
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use py_ir::types::{ComplexType, TypeDefine};
+
+    #[test]
+    fn dump_filtered_branches() {
+        let span = |idx: usize| Span::new(idx, idx + 1);
+        let raw_types = (0..3)
+            .map(|idx| TypeDefine::from(ComplexType::no_decorators(format!("t{idx}"))))
+            .collect::<Vec<_>>();
+        let types = raw_types
+            .iter()
+            .cloned()
+            .map(Type::from)
+            .collect::<Vec<_>>();
+
+        let mut map = DeclareGraph::new();
+        let defs = Defs::new();
+
+        // a: t0 | t1, b: t1 if a is t0, t2 if a is t1
+        let a = map.new_static_group(span(0), types[..2].iter().cloned());
+        let filter = |idx: usize| filters::TypeEqual::new(&raw_types[idx], span(1));
+        let branches = vec![
+            BranchesBuilder::new(types[1].clone()).new_depend::<Directly, _>(
+                &mut map,
+                &defs,
+                a,
+                &filter(0),
+            ),
+            BranchesBuilder::new(types[2].clone()).new_depend::<Directly, _>(
+                &mut map,
+                &defs,
+                a,
+                &filter(1),
+            ),
+        ];
+        let b = map.build_group(GroupBuilder::new(span(2), branches));
+        map.declare_type(span(3), b, &raw_types[2]);
+        assert!(map.declare_all().is_ok());
+
+        let dot = map.to_dot("f", |span| format!("{}", span.start));
+        assert!(dot.starts_with("digraph \"f\" {"));
+        assert!(dot.contains("label=\"group 0 at 0\\nt1\""));
+        assert!(dot.contains("g1b1 [label=\"t2\", style=bold];"));
+        // removed branches are labeled with their types and reasons
+        assert!(dot.contains("g1b0 [label=\"t1\\nexpect this to be declared as `t2` (3)\""));
+        assert!(dot.contains("g0b0 [label=\"t0\\n"));
+        assert!(dot.contains("    g1b1 -> g0b1;"));
+        assert!(dot.contains("    g1b0 -> g0b0 [style=dashed, color=gray];"));
+    }
+}
@@ -1,4 +1,5 @@
 use crate::Type;
+use py_ir::types::TypeDefine;
 use std::rc::Rc;
 use terl::{Message, Span, WithSpan};
 
@@ -40,6 +41,20 @@ pub enum DeclareError {
     Shared {
         err: Rc<DeclareError>,
     },
+    /// the literal is out of the range of the type
+    Overflow {
+        literal: String,
+        max: String,
+    },
+    /// the group is declared as the default type of the literal, because no constraint
+    /// decides which type it is
+    Defaulted {
+        ty: TypeDefine,
+    },
+    /// the overload is viable, but another one is a better match for arguments
+    Outranked {
+        better: Type,
+    },
 
     Filtered,
     Empty,
@@ -64,44 +79,66 @@ impl DeclareError {
         }
     }
 
+    /// the type of the removed branch, and the reason why it was removed
+    pub(crate) fn split_previous(&self) -> (Option<&Type>, &DeclareError) {
+        match self {
+            DeclareError::WithPrevious { previous, error } => {
+                let (_, reason) = error.split_previous();
+                (Some(previous), reason)
+            }
+            DeclareError::Shared { err } => err.split_previous(),
+            _ => (None, self),
+        }
+    }
+
     fn generate_inner(&self, msgs: &mut Vec<terl::Message>) {
         match self {
             DeclareError::UniqueDeleted { reason } => {
-                msgs.push(Message::Text(
-                    "this has been declared, but filtered latter".to_owned(),
-                ));
+                msgs.push(Message::Text(py_lex::msg!(FilteredLater)));
                 reason.generate_inner(msgs)
             }
             DeclareError::NonBranchSelected { expect } => {
-                msgs.push(Message::Text(format!("this should be `{expect}`")))
+                msgs.push(Message::Text(py_lex::msg!(ShouldBe, expect = expect)))
             }
             DeclareError::ConflictSelected {
                 conflict_with,
                 this,
-            } => msgs.push(Message::Text(format!(
-                "this is required to been declared as {} and {} together, but its impossiable",
-                this, conflict_with
+            } => msgs.push(Message::Text(py_lex::msg!(
+                ConflictTypes,
+                this = this,
+                other = conflict_with
             ))),
             DeclareError::NeverUsed { reason, in_group } => {
-                msgs.push(in_group.make_message("the branch is never used in group"));
+                msgs.push(in_group.make_message(py_lex::msg!(BranchNeverUsed)));
                 if let Some(reason) = reason.as_ref() {
                     reason.generate_inner(msgs)
                 }
             }
 
             DeclareError::ShouldAlign { left, right } => {
-                msgs.push("those two has been declared to have same type".into());
+                msgs.push(py_lex::msg!(ShouldAlign).into());
                 msgs.push((*left).into());
                 msgs.push((*right).into());
             }
-            DeclareError::Declared { declare_as } => msgs.push(Message::Text(format!(
-                "this has been declared as {declare_as}"
+            DeclareError::Declared { declare_as } => {
+                msgs.push(Message::Text(py_lex::msg!(DeclaredAs, ty = declare_as)))
+            }
+            DeclareError::Unexpect { expect } => msgs.push(Message::Text(py_lex::msg!(
+                ExpectDeclaredAs,
+                expect = expect
             ))),
-            DeclareError::Unexpect { expect } => msgs.push(Message::Text(format!(
-                "expect this to be declared as `{}`",
-                expect.to_owned()
+            DeclareError::Filtered => msgs.push(Message::Text(py_lex::msg!(Filtered))),
+            DeclareError::Overflow { literal, max } => msgs.push(Message::Text(py_lex::msg!(
+                LiteralOutOfRange,
+                literal = literal,
+                max = max
             ))),
-            DeclareError::Filtered => msgs.push(Message::Text("this has been filtered".to_owned())),
+            DeclareError::Defaulted { ty } => {
+                msgs.push(Message::Text(py_lex::msg!(DefaultedAs, ty = ty)))
+            }
+            DeclareError::Outranked { better } => {
+                msgs.push(Message::Text(py_lex::msg!(OutrankedBy, better = better)))
+            }
             DeclareError::Shared { err } => err.generate_inner(msgs),
 
             DeclareError::WithLocation { location, error } => {
@@ -113,8 +150,9 @@ impl DeclareError {
             }
             DeclareError::WithPrevious { previous, error } => {
                 error.generate_inner(msgs);
-                msgs.push(Message::Text(format!(
-                    "note: this used to be guessed as {previous}"
+                msgs.push(Message::Text(py_lex::msg!(
+                    Note,
+                    note = py_lex::msg!(GuessedAs, ty = previous)
                 )))
             }
             DeclareError::Empty => {}
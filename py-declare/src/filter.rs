@@ -63,7 +63,7 @@ where
 pub mod filters {
 
     use super::*;
-    use crate::{Directly, Overload};
+    use crate::{defs::Interface, Directly, Overload};
     use py_ir::types::TypeDefine;
     use std::any::TypeId;
 
@@ -91,15 +91,82 @@ pub mod filters {
 
         fn expect(&self, _: &Defs) -> String {
             if TypeId::of::<T>() == TypeId::of::<Overload>() {
-                format!("a function whose return type is {}", self.expect)
+                py_lex::msg!(FnReturning, ty = self.expect)
             } else if TypeId::of::<T>() == TypeId::of::<Directly>() {
-                format!("a val whose type is {}", self.expect)
+                py_lex::msg!(ValOfType, ty = self.expect)
             } else {
                 unreachable!()
             }
         }
     }
 
+    /// the type is the expected type, or could be implicitly promoted to it
+    pub struct Promotable<'t> {
+        expect: &'t TypeDefine,
+        at: Span,
+    }
+
+    impl<'t> Promotable<'t> {
+        pub fn new(expect: &'t TypeDefine, at: Span) -> Self {
+            Self { expect, at }
+        }
+    }
+
+    impl WithSpan for Promotable<'_> {
+        fn get_span(&self) -> Span {
+            self.at
+        }
+    }
+
+    impl<T: Types> BranchFilter<T> for Promotable<'_> {
+        fn satisfy(&self, ty: &Type) -> bool {
+            let ty = ty.get_type();
+            ty == self.expect
+                || ty
+                    .as_primitive()
+                    .zip(self.expect.as_primitive())
+                    .is_some_and(|(from, to)| from.promotable_to(to))
+        }
+
+        fn expect(&self, _: &Defs) -> String {
+            py_lex::msg!(PromotableTo, ty = self.expect)
+        }
+    }
+
+    /// the type has functions with all signatures which the interface requires, see
+    /// [`Defs::missing`]
+    pub struct Implements<'d> {
+        defs: &'d Defs,
+        interface: &'d Interface,
+        at: Span,
+    }
+
+    impl<'d> Implements<'d> {
+        pub fn new(defs: &'d Defs, interface: &'d Interface, at: Span) -> Self {
+            Self {
+                defs,
+                interface,
+                at,
+            }
+        }
+    }
+
+    impl WithSpan for Implements<'_> {
+        fn get_span(&self) -> Span {
+            self.at
+        }
+    }
+
+    impl<T: Types> BranchFilter<T> for Implements<'_> {
+        fn satisfy(&self, ty: &Type) -> bool {
+            self.defs.missing(ty.get_type(), self.interface).is_empty()
+        }
+
+        fn expect(&self, _: &Defs) -> String {
+            py_lex::msg!(ImplementsInterface, interface = self.interface.name)
+        }
+    }
+
     pub struct FnParamLen<'n> {
         name: Option<&'n str>,
         expect: usize,
@@ -124,10 +191,10 @@ pub mod filters {
         }
 
         fn expect(&self, defs: &Defs) -> String {
-            let mut msg = format!("a funcion with {} parameters", self.expect);
+            let mut msg = py_lex::msg!(FnWithParams, count = self.expect);
 
             if let Some(name) = self.name {
-                msg += "\nexist overloads whose length is expected:\n";
+                msg += &format!("\n{}\n", py_lex::msg!(OverloadsWithLen));
                 let satisfies = defs
                     .get_unmangled(name)
                     .unwrap()
@@ -136,7 +203,7 @@ pub mod filters {
                     .map(|ol| ol.to_string())
                     .collect::<Vec<_>>();
                 if satisfies.is_empty() {
-                    msg += "emm, no-overload a matched :("
+                    msg += &py_lex::msg!(NoOverloadMatched)
                 } else {
                     msg += &satisfies.join("\n");
                 }
@@ -172,10 +239,7 @@ pub mod filters {
         }
 
         fn expect(&self, _defs: &Defs) -> String {
-            format!(
-                "a function whose {}th parameter is {}",
-                self.nth, self.expect
-            )
+            py_lex::msg!(FnWithNthParam, nth = self.nth, ty = self.expect)
         }
     }
 }
@@ -1,6 +1,6 @@
 use crate::*;
 use py_ir::types::TypeDefine;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use terl::{Span, WithSpan};
 
 /// used to declare which overload of function is called, or which possiable type is
@@ -13,6 +13,25 @@ pub struct DeclareGraph {
     /// if any of them is impossible, the [`Branch`] will be removed, too
     pub(crate) deps: HashMap<Branch, HashSet<Branch>>,
     pub(crate) rdeps: HashMap<Branch, HashSet<Branch>>,
+    /// deps of removed branches, kept for [`DeclareGraph::to_dot`]
+    pub(crate) removed_deps: HashMap<Branch, HashSet<Branch>>,
+    /// groups which are merged by [`DeclareGraph::merge_group`]
+    ///
+    /// it only finds the default type of a group and reports merged groups once. removals
+    /// of branches are not shared by merged groups, [`DeclareGraph::merge_group`] removes
+    /// types which are not in both groups when they are merged
+    pub(crate) merged: UnionFind,
+    /// groups which are types of variables whose types are inferred, with names of them
+    pub(crate) inferred_vars: Vec<(GroupIdx, String)>,
+    /// types which groups are declared as if they are still ambiguous at the end
+    pub(crate) defaults: Vec<(GroupIdx, TypeDefine)>,
+}
+
+/// a branch to be removed by [`DeclareGraph::remove_branches`]
+struct Removal {
+    branch: Branch,
+    /// should be wrapped by rc(by calling [`DeclareError::into_shared`])
+    reason: DeclareError,
 }
 
 impl DeclareGraph {
@@ -41,6 +60,7 @@ impl DeclareGraph {
         };
         self.groups
             .push(DeclareGroup::new(span, idx, failds, status));
+        self.merged.push(idx);
         idx
     }
 
@@ -54,7 +74,7 @@ impl DeclareGraph {
     pub fn build_group(&mut self, gb: GroupBuilder) -> GroupIdx {
         let gidx = GroupIdx::new(self.groups.len());
 
-        let mut alives = HashMap::new();
+        let mut alives = vec![];
         let mut failds = HashMap::new();
 
         #[derive(Debug)]
@@ -85,13 +105,13 @@ impl DeclareGraph {
                 // may the branch doesnot depend on any other branches
                 Ok(branch_depends) if branch_depends.is_empty() => {
                     let new_branch = Branch::new(gidx, alives.len() + failds.len());
-                    alives.insert(new_branch.branch_idx, ty.clone());
+                    alives.push((new_branch.branch_idx, ty.clone()));
                 }
                 Ok(branch_depends) => {
                     for branch_depends in branch_depends {
                         let new_branch = Branch::new(gidx, alives.len() + failds.len());
                         self.insert_depends(new_branch, branch_depends);
-                        alives.insert(new_branch.branch_idx, ty.clone());
+                        alives.push((new_branch.branch_idx, ty.clone()));
                     }
                 }
                 Err(group_errors) => {
@@ -107,24 +127,23 @@ impl DeclareGraph {
 
         let new_group = self.new_group_inner(gb.span, failds, alives.into());
 
+        let mut removals = vec![];
         for (group, mut branch_marks) in used_branches {
-            // use hashmap avoiding remove same branch more than once
-            let remove: HashMap<_, _> = self[group].filter_alive(|branch, ty| {
-                match branch_marks.remove(&branch.branch_idx) {
-                    Some(BranchMark::Used) => Ok((branch, ty)),
-                    Some(BranchMark::Error(error)) => Err(error.with_location(gb.span)),
-                    None => Err(DeclareError::NeverUsed {
-                        in_group: gb.span,
-                        reason: None,
-                    }),
+            self[group].alives(|alives| {
+                for (branch, _) in alives {
+                    let reason = match branch_marks.remove(&branch.branch_idx) {
+                        Some(BranchMark::Used) => continue,
+                        Some(BranchMark::Error(error)) => error.with_location(gb.span),
+                        None => DeclareError::NeverUsed {
+                            in_group: gb.span,
+                            reason: None,
+                        },
+                    };
+                    removals.push(Removal::new(branch, reason.into_shared()));
                 }
-                .map_err(|e| e.into_shared())
             });
-            //
-            for (branch, reason) in remove {
-                self.remove_branch(branch, reason);
-            }
         }
+        self.remove_branches(removals);
 
         new_group
     }
@@ -135,64 +154,51 @@ impl DeclareGraph {
         B: BranchFilter<T>,
     {
         let location = self[gidx].get_span();
-        let reason = || {
+        let reason = std::cell::LazyCell::new(|| {
             DeclareError::Unexpect {
                 expect: filter.expect(defs),
             }
             .with_location(location)
             .into_shared()
-        };
-        let removed = self[gidx].remove_branches(|_, ty| !filter.satisfy(ty), reason);
-
-        for (branch, reason) in removed {
-            self.remove_branch(branch, reason);
-        }
-    }
-
-    pub fn merge_group(&mut self, at: terl::Span, base: GroupIdx, from: GroupIdx) {
-        let bases = self[from].alives(|alives| {
-            alives
-                .map(|(branch, ty)| (branch, ty.get_type()))
-                .collect::<Vec<_>>()
         });
-        let exists = self[base].alives(|alives| {
+        let removals = self[gidx].alives(|alives| {
             alives
-                .map(|(branch, ty)| (branch, ty.get_type()))
+                .filter(|(_, ty)| !filter.satisfy(ty))
+                .map(|(branch, _)| Removal::new(branch, (*reason).clone()))
                 .collect::<Vec<_>>()
         });
+        self.remove_branches(removals);
+    }
 
-        // to_branch, from_branch, type
-        let merge = exists
-            .iter()
-            .flat_map(|&(branch, ty)| {
-                bases
-                    .iter()
-                    .filter(move |(.., f_ty)| *f_ty == ty)
-                    .map(move |&(f_branch, ..)| (branch, f_branch, ty))
-            })
-            .collect::<Vec<_>>();
-
-        let (base_kept, from_kept): (HashSet<_>, HashSet<_>) =
-            merge.iter().map(|(base, from, _)| (*base, *from)).unzip();
+    /// merge two groups, then they must be declared as a same type
+    ///
+    /// types which only one of them has are removed
+    pub fn merge_group(&mut self, at: terl::Span, base: GroupIdx, from: GroupIdx) {
+        fn types(group: &DeclareGroup) -> Vec<(Branch, &TypeDefine)> {
+            group.alives(|alives| alives.map(|(branch, ty)| (branch, ty.get_type())).collect())
+        }
+        let (bases, exists) = (types(&self[from]), types(&self[base]));
 
-        let removed = bases
-            .iter()
-            .map(|(branch, ..)| *branch)
-            .filter(|branch| !from_kept.contains(branch))
-            .chain(
-                exists
-                    .iter()
-                    .map(|(branch, ..)| *branch)
-                    .filter(|branch| !base_kept.contains(branch)),
-            )
+        let only_in = |this: &[(Branch, &TypeDefine)], that: &[(Branch, &TypeDefine)]| {
+            this.iter()
+                .filter(|(_, ty)| that.iter().all(|(_, that_ty)| that_ty != ty))
+                .map(|(branch, _)| *branch)
+                .collect::<Vec<_>>()
+        };
+        let removed = only_in(&bases, &exists)
+            .into_iter()
+            .chain(only_in(&exists, &bases))
             .collect::<Vec<_>>();
 
-        // TODO: improve error message here
-        let remove_reason = DeclareError::Filtered.with_location(at).into_shared();
-
-        for remove in removed {
-            self.remove_branch(remove, remove_reason.clone());
+        if !removed.is_empty() {
+            // TODO: improve error message here
+            let remove_reason = DeclareError::Filtered.with_location(at).into_shared();
+            let removals = removed
+                .into_iter()
+                .map(|branch| Removal::new(branch, remove_reason.clone()));
+            self.remove_branches(removals);
         }
+        self.merged.union(base, from);
     }
 
     /// declare a [`DeclareGroup`]'s result is a type
@@ -200,69 +206,164 @@ impl DeclareGraph {
     /// return [`Err`] if the type has be declared and isn't given type,
     /// or non of [`Branch`] match the given type
     pub fn declare_type(&mut self, at: terl::Span, gidx: GroupIdx, expect_ty: &TypeDefine) {
-        let group = &mut self[gidx];
         // TODO: unknown type support
-
-        let reason = || {
-            DeclareError::Unexpect {
-                expect: expect_ty.to_string(),
-            }
-            .with_location(at)
-            .into_shared()
-        };
-        for (branch, remove) in group.remove_branches(|_, ty| ty.get_type() != expect_ty, reason) {
-            self.remove_branch(branch, remove);
+        let reason = DeclareError::Unexpect {
+            expect: expect_ty.to_string(),
         }
+        .with_location(at)
+        .into_shared();
+        let removals = self[gidx].alives(|alives| {
+            alives
+                .filter(|(_, ty)| ty.get_type() != expect_ty)
+                .map(|(branch, _)| Removal::new(branch, reason.clone()))
+                .collect::<Vec<_>>()
+        });
+        self.remove_branches(removals);
     }
 
     /// Zhu double eight: is your Nine Clan([`Branch`]) wholesale?
     ///
-    /// `remove` a node, and all node which must depend on it, and then a generate a error
-    /// with [`Type`] which previous branch stored in
-    ///
-    /// # Note:
+    /// `remove` branches, and all branches which must depend on them, and branches which
+    /// are only depended by them, and then generate errors with [`Type`]s which removed
+    /// branches stored in
     ///
-    /// make sure the reason passed in are wrapped by rc(by calling [`DeclareError::into_shared`])
-    pub(crate) fn remove_branch(&mut self, branch: Branch, reason: DeclareError) {
-        // is it impossiable to be a cycle dep in map?
+    /// removals are queued instead of being propagated by recursion, so long dependency
+    /// chains dont grow the stack. every branch is removed at most once
+    fn remove_branches(&mut self, removals: impl IntoIterator<Item = Removal>) {
+        let mut worklist = removals.into_iter().collect::<VecDeque<_>>();
+        while let Some(removal) = worklist.pop_front() {
+            worklist.extend(self.remove_branch(removal));
+        }
+    }
+
+    /// remove a branch, return branches which should be removed because of it
+    fn remove_branch(&mut self, Removal { branch, reason }: Removal) -> Vec<Removal> {
         let group = &mut self[branch.belong_to];
+        // the branch has been removed
+        let Some(previous) = group.remove_branch(branch.branch_idx) else {
+            return vec![];
+        };
         let group_loc = group.get_span();
-        {
-            let reason = group.remove_branch(branch.branch_idx, reason.clone());
-            group.push_error(branch.branch_idx, reason.clone());
-        }
+        group.push_error(branch.branch_idx, reason.clone().with_previous(previous));
 
         // remove all branches depend on removed branch
-        if let Some(rdeps) = self.rdeps.remove(&branch) {
-            for rdep in rdeps {
-                self.remove_branch(rdep, reason.clone());
-            }
-        }
-        // remove the record of all branch which removed branch depend on
+        let mut removals = match self.rdeps.remove(&branch) {
+            Some(rdeps) => rdeps
+                .into_iter()
+                .map(|rdep| Removal::new(rdep, reason.clone()))
+                .collect(),
+            None => vec![],
+        };
+        // remove the record of all branch which removed branch depend on, and remove
+        // them if they are not depended by others
         if let Some(deps) = self.deps.remove(&branch) {
-            for dep in deps {
-                match self.rdeps.get_mut(&dep) {
-                    Some(rdeps) if rdeps.len() == 1 => {
-                        let reason = DeclareError::NeverUsed {
-                            in_group: group_loc,
-                            reason: Some(reason.clone().into()),
-                        };
-                        self.remove_branch(dep, reason);
-                    }
-                    Some(rdeps) => {
-                        rdeps.remove(&branch);
-                    }
-                    None => {}
+            for &dep in &deps {
+                let Some(rdeps) = self.rdeps.get_mut(&dep) else {
+                    continue;
+                };
+                rdeps.remove(&branch);
+                if rdeps.is_empty() {
+                    self.rdeps.remove(&dep);
+                    let reason = DeclareError::NeverUsed {
+                        in_group: group_loc,
+                        reason: Some(reason.clone().into()),
+                    };
+                    removals.push(Removal::new(dep, reason.into_shared()));
                 }
             }
+            self.removed_deps.insert(branch, deps);
+        }
+        removals
+    }
+
+    /// mark that the group is the type of a variable whose type is inferred, so that a
+    /// dedicated error is generated if the group could be declared as several types
+    pub fn regist_inferred(&mut self, gidx: GroupIdx, var: impl ToString) {
+        self.inferred_vars.push((gidx, var.to_string()));
+    }
+
+    /// set the type which the group is declared as, if it could still be declared as
+    /// more than one type after all constraints are applied, like `i64` for integer literals
+    pub fn set_default(&mut self, gidx: GroupIdx, ty: TypeDefine) {
+        self.defaults.push((gidx, ty));
+    }
+
+    /// the default type of the group, if it's merged with a group whose default type is
+    /// set, and it could still be declared as it
+    pub fn default_of(&mut self, gidx: GroupIdx) -> Option<TypeDefine> {
+        let root = self.merged.find(gidx);
+        let defaults = self.defaults.clone();
+        let (_, default) = defaults
+            .into_iter()
+            .find(|(group, _)| self.merged.find(*group) == root)?;
+        let alive = self[gidx]
+            .alives(|mut alives| Iterator::any(&mut alives, |(_, ty)| ty.get_type() == &default));
+        alive.then_some(default)
+    }
+
+    /// declare groups which are still ambiguous as their default types, in the order
+    /// defaults were set
+    fn apply_defaults(&mut self) {
+        for (gidx, default) in self.defaults.clone() {
+            let group = &self[gidx];
+            let has_default = group.alives(|mut alives| {
+                Iterator::any(&mut alives, |(_, ty)| ty.get_type() == &default)
+            });
+            if !group.is_ambiguous() || !has_default {
+                continue;
+            }
+            let reason = DeclareError::Defaulted {
+                ty: default.clone(),
+            }
+            .with_location(group.get_span())
+            .into_shared();
+            let removals = group.alives(|alives| {
+                alives
+                    .filter(|(_, ty)| ty.get_type() != &default)
+                    .map(|(branch, _)| Removal::new(branch, reason.clone()))
+                    .collect::<Vec<_>>()
+            });
+            self.remove_branches(removals);
         }
     }
 
     pub fn declare_all(&mut self) -> Result<(), Vec<terl::Error>> {
+        self.apply_defaults();
+
         let mut errors = vec![];
+        // groups merged with an ambiguous variable or an overflowed literal are reported
+        // by it only
+        let mut reported = HashSet::new();
+        for (gidx, var) in &self.inferred_vars {
+            let group = &self.groups[gidx.idx];
+            if group.is_ambiguous() && reported.insert(self.merged.find(*gidx)) {
+                errors.push(group.make_ambiguous_error(var));
+            }
+        }
+        for group in &self.groups {
+            if let Some(literal) = group.overflowed() {
+                if reported.insert(self.merged.find(group.idx())) {
+                    errors.push(group.make_overflow_error(literal));
+                }
+            }
+        }
+        let ambiguous_calls = self
+            .groups
+            .iter()
+            .filter(|group| group.is_ambiguous_call())
+            .map(|group| group.idx())
+            .collect::<Vec<_>>();
+        for gidx in ambiguous_calls {
+            if reported.insert(self.merged.find(gidx)) {
+                errors.push(self[gidx].make_ambiguous_call_error());
+                // arguments are ambiguous because of the call
+                let depended = self.depended_groups(gidx);
+                reported.extend(depended);
+            }
+        }
         for group in &self.groups {
             // un-declared group
-            if !group.is_declared() {
+            if !group.is_declared() && !reported.contains(&self.merged.find(group.idx())) {
                 errors.push(group.make_error());
             }
         }
@@ -273,11 +374,35 @@ impl DeclareGraph {
         }
     }
 
+    /// roots of groups which alive branches of the group depend on, directly or not
+    fn depended_groups(&mut self, gidx: GroupIdx) -> HashSet<GroupIdx> {
+        let mut groups = HashSet::new();
+        let mut worklist =
+            self[gidx].alives(|alives| alives.map(|(branch, _)| branch).collect::<Vec<_>>());
+        while let Some(branch) = worklist.pop() {
+            for &dep in self.deps.get(&branch).into_iter().flatten() {
+                if groups.insert(self.merged.find(dep.belong_to)) {
+                    worklist.extend(
+                        self[dep.belong_to]
+                            .alives(|alives| alives.map(|(branch, _)| branch).collect::<Vec<_>>()),
+                    );
+                }
+            }
+        }
+        groups
+    }
+
     pub fn get_type(&self, gidx: GroupIdx) -> &TypeDefine {
         self[gidx].result().get_type()
     }
 }
 
+impl Removal {
+    fn new(branch: Branch, reason: DeclareError) -> Self {
+        Self { branch, reason }
+    }
+}
+
 impl std::ops::Index<GroupIdx> for DeclareGraph {
     type Output = DeclareGroup;
 
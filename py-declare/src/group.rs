@@ -1,5 +1,5 @@
 use super::*;
-use std::{cell::LazyCell, collections::HashMap};
+use std::collections::HashMap;
 use terl::{Span, WithSpan};
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -17,7 +17,8 @@ impl GroupIdx {
 pub enum DeclareState {
     Empty,
     Declared(usize, Type),
-    Declaring(HashMap<usize, Type>),
+    /// alive branches, in the order they were pushed
+    Declaring(Vec<(usize, Type)>),
 }
 
 impl DeclareState {
@@ -44,12 +45,12 @@ impl From<(usize, Type)> for DeclareState {
     }
 }
 
-impl From<HashMap<usize, Type>> for DeclareState {
-    fn from(v: HashMap<usize, Type>) -> Self {
+impl From<Vec<(usize, Type)>> for DeclareState {
+    fn from(mut v: Vec<(usize, Type)>) -> Self {
         match v.len() {
             0 => Self::Empty,
             1 => {
-                let Some((branch, unique)) = v.into_iter().next() else {
+                let Some((branch, unique)) = v.pop() else {
                     unreachable!()
                 };
                 Self::Declared(branch, unique)
@@ -106,89 +107,31 @@ impl DeclareGroup {
         self.failds.insert(at, error);
     }
 
-    fn update_state<U>(&mut self, updater: U)
-    where
-        U: FnOnce(DeclareState) -> DeclareState,
-    {
-        let previous = std::mem::replace(&mut self.status, DeclareState::Empty);
-        self.status = updater(previous);
-    }
-
-    pub fn filter_alive<F, C>(&mut self, mut filter: F) -> C
-    where
-        F: FnMut(Branch, Type) -> Result<(Branch, Type), DeclareError>,
-        C: Default + Extend<(Branch, DeclareError)>,
-    {
-        let mut remove = C::default();
-        let belong_to = self.group;
-        let filter_map = |(branch, ty)| {
-            let branch = Branch::new(belong_to, branch);
-            filter(branch, ty)
-                .map_err(|e| remove.extend(Some((branch, e))))
-                .map(|(branch, ty)| (branch.branch_idx, ty))
-                .ok()
-        };
-
-        self.update_state(|state| match state {
-            DeclareState::Empty => DeclareState::Empty,
-            DeclareState::Declared(branch, ty) => {
-                let iter = std::iter::once((branch, ty)).filter_map(filter_map);
-                DeclareState::from_iter(iter)
-            }
-            DeclareState::Declaring(branches) => {
-                let iter = branches.into_iter().filter_map(filter_map);
-                DeclareState::from_iter(iter)
-            }
-        });
-        remove
-    }
-
-    /// remove only one branch
+    /// remove only one branch, return its type
     ///
-    /// note: this method will do nothing if the branch is not exist(including have been remvoed)
-    pub fn remove_branch(&mut self, branch: usize, reason: DeclareError) -> DeclareError {
-        let mut new_reason = DeclareError::Empty;
-        self.update_state(|state| match state {
-            DeclareState::Declared(unique, previous) => {
-                if unique == branch {
-                    new_reason = reason.with_previous(previous);
-                    DeclareState::Empty
-                } else {
-                    DeclareState::Declared(unique, previous)
-                }
+    /// note: this method will do nothing and return [`None`] if the branch is not
+    /// exist(including have been remvoed)
+    pub fn remove_branch(&mut self, branch: usize) -> Option<Type> {
+        let (previous, status) = match std::mem::replace(&mut self.status, DeclareState::Empty) {
+            DeclareState::Declared(unique, ty) if unique == branch => {
+                (Some(ty), DeclareState::Empty)
             }
             DeclareState::Declaring(mut items) => {
-                let previous = items.remove(&branch);
-                new_reason = reason.with_previous(previous.unwrap_or_else(|| unreachable!()));
-                items.into()
+                let previous = items
+                    .iter()
+                    .position(|(idx, _)| *idx == branch)
+                    .map(|at| items.remove(at).1);
+                (previous, items.into())
             }
-            _ => unreachable!(),
-        });
-        new_reason
+            status => (None, status),
+        };
+        self.status = status;
+        previous
     }
 
-    /// # Note
-    ///
-    /// the [`DeclareError`] generated by `reason` will be clone many times, so, you should
-    /// call [`DeclareError::into_shared`] to wrapped it in [`Rc`]
-    ///
-    /// and, [`DeclareError::with_previous`] will be called on [`DeclareError`]
-    ///
-    /// [`Rc`]: std::rc
-    pub fn remove_branches<F, R>(&mut self, remove_if: F, reason: R) -> Vec<(Branch, DeclareError)>
-    where
-        F: Fn(Branch, &Type) -> bool,
-        R: FnOnce() -> DeclareError,
-    {
-        let reason = LazyCell::new(reason);
-
-        self.filter_alive(move |branch, ty| {
-            if remove_if(branch, &ty) {
-                Err((*reason).clone().with_previous(ty))
-            } else {
-                Ok((branch, ty))
-            }
-        })
+    /// branches which have been removed, and why they were removed
+    pub fn failds(&self) -> &HashMap<usize, DeclareError> {
+        &self.failds
     }
 
     pub fn alives<'t, A, T: 't>(&'t self, active: A) -> T
@@ -198,10 +141,19 @@ impl DeclareGroup {
         self.status.alives(self.group, active)
     }
 
+    pub fn idx(&self) -> GroupIdx {
+        self.group
+    }
+
     pub fn is_declared(&self) -> bool {
         matches!(self.status, DeclareState::Declared(..))
     }
 
+    /// if the group could still be declared as more than one type
+    pub fn is_ambiguous(&self) -> bool {
+        matches!(self.status, DeclareState::Declaring(..))
+    }
+
     /// return declare result
     ///
     /// # Panic
@@ -215,19 +167,102 @@ impl DeclareGroup {
     }
 
     pub fn make_error(&self) -> terl::Error {
-        let mut err = <Self as terl::WithSpan>::make_error(self, "cant infer type");
+        let mut err = <Self as terl::WithSpan>::make_error(self, py_lex::msg!(CantInferType));
         match &self.status {
-            DeclareState::Empty => err += "this cant be declared as any type!",
+            DeclareState::Empty => err += py_lex::msg!(NoPossibleType),
             DeclareState::Declaring(alives) => {
-                err += "this cant be declared as:";
-                for alives in alives.values() {
+                err += py_lex::msg!(PossibleTypes);
+                for (_, alives) in alives {
                     err += format!("\t{alives}")
                 }
             }
             DeclareState::Declared(_, _) => unreachable!(),
         }
 
-        err.extend(self.failds.values().flat_map(|faild| faild.generate()));
+        // in the order of branches
+        let mut failds = self.failds.iter().collect::<Vec<_>>();
+        failds.sort_by_key(|(branch, _)| **branch);
+        err.extend(failds.into_iter().flat_map(|(_, faild)| faild.generate()));
+        err
+    }
+
+    /// the literal, if the group cant be declared as any type, and some of its branches
+    /// were failed because the literal is out of their ranges
+    pub fn overflowed(&self) -> Option<&str> {
+        if !matches!(self.status, DeclareState::Empty) {
+            return None;
+        }
+        self.failds
+            .values()
+            .find_map(|faild| match faild.split_previous() {
+                (_, DeclareError::Overflow { literal, .. }) => Some(literal.as_str()),
+                _ => None,
+            })
+    }
+
+    /// make an error for the group whose literal is [`DeclareGroup::overflowed`]
+    pub fn make_overflow_error(&self, literal: &str) -> terl::Error {
+        let reason = py_lex::msg!(LiteralOverflow, literal = literal);
+        let mut err = <Self as terl::WithSpan>::make_error(self, reason);
+        // only types which are too narrow, in the order of branches
+        let mut overflows = self
+            .failds
+            .iter()
+            .filter(|(_, faild)| matches!(faild.split_previous().1, DeclareError::Overflow { .. }))
+            .collect::<Vec<_>>();
+        overflows.sort_by_key(|(branch, _)| **branch);
+        err.extend(
+            overflows
+                .into_iter()
+                .flat_map(|(_, faild)| faild.generate()),
+        );
+        err
+    }
+
+    /// make an error for the group which is the type of the variable `var`, and could be
+    /// declared as more than one type
+    ///
+    /// # Panic
+    ///
+    /// panic if the group is not ambiguous
+    pub fn make_ambiguous_error(&self, var: &str) -> terl::Error {
+        let DeclareState::Declaring(alives) = &self.status else {
+            panic!("group is not ambiguous")
+        };
+        let reason = py_lex::msg!(AmbiguousVarType, name = var);
+        let mut err = <Self as terl::WithSpan>::make_error(self, reason);
+        for (_, alive) in alives {
+            err += format!("\t{alive}")
+        }
+        err + py_lex::msg!(Note, note = py_lex::msg!(ExplicitVarType, name = var))
+    }
+
+    /// if the group is a function call, and more than one overload could be called
+    pub fn is_ambiguous_call(&self) -> bool {
+        match &self.status {
+            DeclareState::Declaring(alives) => alives
+                .iter()
+                .all(|(_, ty)| matches!(ty, Type::Overload(..))),
+            _ => false,
+        }
+    }
+
+    /// make an error for the group which [`DeclareGroup::is_ambiguous_call`], with each
+    /// overload which could be called and where it's defined
+    ///
+    /// # Panic
+    ///
+    /// panic if the group is not ambiguous
+    pub fn make_ambiguous_call_error(&self) -> terl::Error {
+        let DeclareState::Declaring(alives) = &self.status else {
+            panic!("group is not ambiguous")
+        };
+        let mut err = <Self as terl::WithSpan>::make_error(self, py_lex::msg!(AmbiguousCall));
+        for (_, alive) in alives {
+            let overload = alive.overload();
+            let defined = py_lex::msg!(CandidateDefinedHere, sign = overload);
+            err = err.append(overload.sign_span.make_message(defined));
+        }
         err
     }
 
@@ -243,7 +278,10 @@ impl DeclareGroup {
         let branch = branch.branch_idx;
         match &self.status {
             DeclareState::Declared(idx, ty) if *idx == branch => ty,
-            DeclareState::Declaring(alives) if alives.contains_key(&branch) => &alives[&branch],
+            DeclareState::Declaring(alives) => alives
+                .iter()
+                .find_map(|(idx, ty)| (*idx == branch).then_some(ty))
+                .expect("the branch isnot exist, or faild"),
             _ => panic!("the branch isnot exist, or faild"),
         }
     }
@@ -1,18 +1,22 @@
 mod branch;
+mod dot;
 mod error;
 mod filter;
 mod graph;
 mod group;
 mod res;
+mod union_find;
 pub use branch::*;
 pub use error::*;
 pub use filter::*;
 pub use graph::*;
 pub use group::*;
 pub use res::*;
+use union_find::UnionFind;
 
 pub mod defs;
 pub mod mir;
+pub mod overload;
 pub use defs::Defs;
 
 type Result<T, E = DeclareError> = std::result::Result<T, E>;
@@ -144,4 +148,167 @@ mod tests {
 
         assert!(map.declare_all().is_ok());
     }
+
+    #[test]
+    fn literal_range_and_default() {
+        use py_ir::{
+            types::{PrimitiveType, TypeDefine},
+            value::Literal,
+        };
+
+        let span = terl::Span::new(0, 1);
+        let mut map = DeclareGraph::new();
+        let literal_group = |map: &mut DeclareGraph, literal: u128| {
+            let literal = Literal::Integer(literal);
+            let branches = mir::Undeclared::literal_branches(&literal);
+            let group = map.build_group(GroupBuilder::new(span, branches));
+            let default = mir::Undeclared::literal_default(&literal).unwrap();
+            map.set_default(group, default.into());
+            group
+        };
+
+        // unconstrained literals are declared as i64
+        let small = literal_group(&mut map, 1);
+        // only unsigned types of 64 bits or wider could hold the literal
+        let large = literal_group(&mut map, u64::MAX as u128);
+        let u64 = TypeDefine::from(PrimitiveType::U64);
+        let u64_group = map.new_static_group(span, [u64.clone().into()]);
+        map.merge_group(span, u64_group, large);
+
+        assert!(map.declare_all().is_ok());
+        assert_eq!(map.get_type(small), &TypeDefine::from(PrimitiveType::I64));
+        assert_eq!(map.get_type(large), &u64);
+
+        let overflowed = literal_group(&mut map, u64::MAX as u128 + 1);
+        let u64_group = map.new_static_group(span, [u64.into()]);
+        map.merge_group(span, u64_group, overflowed);
+        assert!(map[overflowed].overflowed().is_some());
+        assert_eq!(map.declare_all().unwrap_err().len(), 1);
+    }
+
+    #[test]
+    fn overload_ranking() {
+        use overload::{Conversion, Rejection, Resolution};
+        use py_ir::types::{PrimitiveType, TypeDefine};
+
+        let span = terl::Span::new(0, 1);
+        let overload = |name: &str, params: &[PrimitiveType]| -> Overload {
+            let params = params
+                .iter()
+                .map(|ty| defs::Parameter {
+                    ty: (*ty).into(),
+                    name: "x".to_owned(),
+                })
+                .collect();
+            let sign = defs::FnSign::new(PrimitiveType::I64.into(), params, span, span);
+            let name = name.to_owned();
+            defs::FnSignWithName { sign, name }.into()
+        };
+        let arg = |ty: PrimitiveType| vec![TypeDefine::from(ty)];
+        let (i64, i128) = (PrimitiveType::I64, PrimitiveType::I128);
+
+        let overloads = [
+            overload("f i64", &[i64]),
+            overload("f i128", &[i128]),
+            overload("f f32", &[PrimitiveType::F32]),
+            overload("f i64 i64", &[i64, i64]),
+        ];
+        let resolution = Resolution::new(&overloads, &[arg(i64)]);
+        let [exact, promoted, mismatch, too_many] = &resolution.candidates[..] else {
+            unreachable!()
+        };
+        assert_eq!(exact.conversions, Ok(vec![Conversion::Exact]));
+        assert_eq!(promoted.conversions, Ok(vec![Conversion::Promotion]));
+        assert_eq!(mismatch.conversions, Err(Rejection::Arg(0)));
+        assert_eq!(too_many.conversions, Err(Rejection::ParamLen));
+        // exact matches are better than promotions
+        assert!(resolution.is_best(exact));
+        assert!(resolution.outranked_by(promoted).is_some());
+        assert!(!resolution.is_best(mismatch));
+
+        // neither is better than the other
+        let overloads = [
+            overload("g i64 i128", &[i64, i128]),
+            overload("g i128 i64", &[i128, i64]),
+        ];
+        let resolution = Resolution::new(&overloads, &[arg(i64), arg(i64)]);
+        assert!(resolution.candidates.iter().all(|c| resolution.is_best(c)));
+
+        let resolution = Resolution::new(&overloads, &[arg(i128), arg(i128)]);
+        assert!(!resolution.is_viable());
+    }
+
+    #[test]
+    fn display_sign() {
+        use py_ir::types::PrimitiveType;
+
+        let span = terl::Span::new(0, 1);
+        let sign = |params: &[(&str, PrimitiveType)]| {
+            let params = params
+                .iter()
+                .map(|(name, ty)| defs::Parameter {
+                    ty: (*ty).into(),
+                    name: (*name).to_owned(),
+                })
+                .collect();
+            let sign = defs::FnSign::new(PrimitiveType::Bool.into(), params, span, span);
+            let name = "f 参 结".to_owned();
+            defs::FnSignWithName { sign, name }.to_string()
+        };
+
+        assert_eq!(sign(&[]), "f() -> bool");
+        assert_eq!(sign(&[("x", PrimitiveType::I64)]), "f(i64) -> bool");
+        // every parameter is shown with its type, not its name
+        let params = [
+            ("x", PrimitiveType::I64),
+            ("y", PrimitiveType::U8),
+            ("z", PrimitiveType::F32),
+        ];
+        assert_eq!(sign(&params), "f(i64, u8, f32) -> bool");
+    }
+
+    #[test]
+    fn remove_branch_with_only_used_deps() {
+        use py_ir::types::{ComplexType, TypeDefine};
+
+        let span = terl::Span::new(0, 1);
+        let raw_types = (0..4)
+            .map(|idx| TypeDefine::from(ComplexType::no_decorators(format!("t{idx}"))))
+            .collect::<Vec<_>>();
+        let defs = Defs::new();
+        let mut map = DeclareGraph::new();
+
+        // m() -> t1 | t2
+        // p(t1) -> t3
+        let m = map.build_group(GroupBuilder::new(
+            span,
+            vec![
+                Type::from(raw_types[1].clone()).into(),
+                Type::from(raw_types[2].clone()).into(),
+            ],
+        ));
+        let filter = filters::TypeEqual::new(&raw_types[1], span);
+        let p = {
+            let branches = BranchesBuilder::new(raw_types[3].clone().into())
+                .new_depend::<Directly, _>(&mut map, &defs, m, &filter);
+            map.build_group(GroupBuilder::new(span, vec![branches]))
+        };
+        // t2 of m is never used by p
+        assert_eq!(map[m].failds().len(), 1);
+
+        // t1 of m is only used by the removed branch of p, so it is removed, too. the
+        // removed branch of p must not be removed again through the reverse dependency
+        map.declare_type(span, p, &raw_types[0]);
+        assert!(map.rdeps.is_empty());
+        assert_eq!(map[m].failds().len(), 2);
+        assert!(matches!(
+            map[m].failds()[&0].split_previous().1,
+            DeclareError::NeverUsed { .. }
+        ));
+        assert!(matches!(
+            map[p].failds()[&0].split_previous().1,
+            DeclareError::WithLocation { .. }
+        ));
+        assert_eq!(map.declare_all().unwrap_err().len(), 2);
+    }
 }
@@ -4,7 +4,7 @@ pub trait IntoIR {
 }
 
 pub mod mir_variable {
-    use crate::{branches, BranchesBuilder, DeclareGraph, GroupIdx};
+    use crate::{branches, BranchesBuilder, DeclareError, DeclareGraph, GroupIdx};
     use py_ir as ir;
     use py_ir::value::Literal;
     use py_lex::ops::Operators;
@@ -15,6 +15,10 @@ pub mod mir_variable {
     pub enum Value {
         Literal(Literal),
         Variable(String),
+        /// a function as a value, with types and mangled names of overloads it could be
+        Fn(Vec<(ir::types::FnType, String)>),
+        /// a reference to the variable, which is captured by a closure by reference
+        Ref(String),
     }
 
     impl From<Literal> for Value {
@@ -36,33 +40,76 @@ pub mod mir_variable {
     }
 
     impl Undeclared<Value> {
+        /// types the literal could be declared as, types which are too narrow for the
+        /// literal are failed branches
         pub fn literal_branches(var: &Literal) -> Vec<BranchesBuilder> {
             use py_ir::types::PrimitiveType;
             match var {
                 Literal::Char(_) => branches! {() =>  PrimitiveType::char()},
                 // String: greatly in processing...
-                Literal::Integer(_) => branches! {
-                    () =>  PrimitiveType::U8, () =>  PrimitiveType::U16,
-                    () =>  PrimitiveType::U32,() =>  PrimitiveType::U64,
-                    () =>  PrimitiveType::U128,() =>  PrimitiveType::Usize,
-                    () =>  PrimitiveType::I8, () =>  PrimitiveType::I16,
-                    () =>  PrimitiveType::I32,() =>  PrimitiveType::I64,
-                    () =>  PrimitiveType::I128,() =>  PrimitiveType::Isize
-
-                },
+                Literal::Integer(int) => [
+                    PrimitiveType::U8,
+                    PrimitiveType::U16,
+                    PrimitiveType::U32,
+                    PrimitiveType::U64,
+                    PrimitiveType::U128,
+                    PrimitiveType::Usize,
+                    PrimitiveType::I8,
+                    PrimitiveType::I16,
+                    PrimitiveType::I32,
+                    PrimitiveType::I64,
+                    PrimitiveType::I128,
+                    PrimitiveType::Isize,
+                ]
+                .into_iter()
+                .map(|ty| match ty.max_integer() {
+                    Some(max) if *int > max => {
+                        let reason = DeclareError::Overflow {
+                            literal: int.to_string(),
+                            max: max.to_string(),
+                        };
+                        BranchesBuilder::new_failed(ty.into(), reason)
+                    }
+                    _ => BranchesBuilder::new(ty.into()),
+                })
+                .collect(),
+                Literal::Float(float) if float.abs() > f32::MAX as f64 => {
+                    let reason = DeclareError::Overflow {
+                        literal: float.to_string(),
+                        max: f32::MAX.to_string(),
+                    };
+                    vec![
+                        BranchesBuilder::new_failed(PrimitiveType::F32.into(), reason),
+                        BranchesBuilder::new(PrimitiveType::F64.into()),
+                    ]
+                }
                 Literal::Float(_) => branches! {
                     () =>  PrimitiveType::F32,
                     () =>  PrimitiveType::F64
                 },
             }
         }
+
+        /// the type the literal is declared as, if it could still be declared as more than
+        /// one type after all constraints are applied
+        pub fn literal_default(var: &Literal) -> Option<py_ir::types::PrimitiveType> {
+            use py_ir::types::PrimitiveType;
+            match var {
+                Literal::Char(_) => None,
+                Literal::Integer(_) => Some(PrimitiveType::I64),
+                Literal::Float(_) => Some(PrimitiveType::F64),
+            }
+        }
     }
 
     #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
     pub enum AssignValue {
         Value(Value),
         FnCall(FnCall),
+        IndirectCall(IndirectCall),
         Operate(Operate),
+        /// a field of the value, whose type is a class
+        Field(Undeclared<Value>, String),
     }
 
     impl From<Value> for AssignValue {
@@ -77,6 +124,12 @@ pub mod mir_variable {
         }
     }
 
+    impl From<IndirectCall> for AssignValue {
+        fn from(v: IndirectCall) -> Self {
+            Self::IndirectCall(v)
+        }
+    }
+
     impl From<Operate> for AssignValue {
         fn from(v: Operate) -> Self {
             Self::Operate(v)
@@ -87,6 +140,9 @@ pub mod mir_variable {
     pub enum Operate {
         Unary(Operators, Undeclared<Value>),
         Binary(Operators, Undeclared<Value>, Undeclared<Value>),
+        /// convert the value to the type of the operation, it's only a copy if they are
+        /// declared as the same type
+        Convert(Undeclared<Value>),
     }
 
     #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
@@ -94,6 +150,13 @@ pub mod mir_variable {
         pub args: Vec<Undeclared<Value>>,
     }
 
+    /// call the function which the value of `callee` points to
+    #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+    pub struct IndirectCall {
+        pub callee: Undeclared<Value>,
+        pub args: Vec<Undeclared<Value>>,
+    }
+
     impl py_ir::IRValue for Undeclared<Value> {
         type AssignValue = Undeclared<AssignValue>;
         type VarDefineType = GroupIdx;
@@ -111,6 +174,15 @@ pub mod mir_variable {
                     ir::value::Value::Literal(literal, ty)
                 }
                 Value::Variable(variable) => ir::value::Value::Variable(variable),
+                Value::Fn(overloads) => {
+                    let ty = map.get_type(self.ty).as_fn().unwrap();
+                    let (_, name) = overloads
+                        .into_iter()
+                        .find(|(fn_ty, _)| fn_ty == ty)
+                        .unwrap();
+                    ir::value::Value::Fn(name)
+                }
+                Value::Ref(variable) => ir::value::Value::Ref(variable),
             }
         }
     }
@@ -129,6 +201,15 @@ pub mod mir_variable {
                     }
                     .into()
                 }
+                AssignValue::IndirectCall(call) => {
+                    let ty = map.get_type(call.callee.ty).as_fn().unwrap().clone();
+                    ir::value::IndirectCall {
+                        callee: call.callee.into_ir(map),
+                        ty,
+                        args: call.args.into_ir(map),
+                    }
+                    .into()
+                }
                 AssignValue::Operate(operate) => {
                     let ty = *map.get_type(self.ty).as_primitive().unwrap();
                     let operate = match operate {
@@ -136,9 +217,19 @@ pub mod mir_variable {
                         Operate::Binary(op, l, r) => {
                             ir::value::Operate::Binary(op, l.into_ir(map), r.into_ir(map))
                         }
+                        Operate::Convert(val) => {
+                            let from = *map.get_type(val.ty).as_primitive().unwrap();
+                            if from == ty {
+                                return val.into_ir(map).into();
+                            }
+                            ir::value::Operate::Convert(from, val.into_ir(map))
+                        }
                     };
                     (operate, ty).into()
                 }
+                AssignValue::Field(object, field) => {
+                    ir::value::AssignValue::Field(object.into_ir(map), field)
+                }
             }
         }
     }
@@ -169,6 +260,8 @@ mod into_ir_impls {
         fn into_ir(self, map: &DeclareGraph) -> Self::Forward {
             match self {
                 Item::FnDefine(fn_define) => fn_define.into_ir(map).into(),
+                Item::Struct(struct_) => struct_.into(),
+                Item::Global(global) => global.into(),
             }
         }
     }
@@ -183,6 +276,7 @@ mod into_ir_impls {
                 name: self.name,
                 params: self.params,
                 body: self.body.into_ir(map),
+                template: self.template,
             }
         }
     }
@@ -213,6 +307,7 @@ mod into_ir_impls {
                 Statement::If(item) => item.into_ir(map).into(),
                 Statement::While(item) => item.into_ir(map).into(),
                 Statement::Return(item) => item.into_ir(map).into(),
+                Statement::Location(item) => item.into(),
             }
         }
     }
@@ -237,6 +332,7 @@ mod into_ir_impls {
         fn into_ir(self, map: &DeclareGraph) -> Self::Forward {
             VarStore {
                 name: self.name,
+                fields: self.fields,
                 val: self.val.into_ir(map),
             }
         }
@@ -0,0 +1,149 @@
+use crate::Overload;
+use py_ir::types::TypeDefine;
+use terl::{Span, WithSpan};
+
+/// how an argument is passed as a parameter, the former is better
+///
+/// promotions([`py_ir::types::PrimitiveType::promotable_to`]) are the only implicit
+/// conversions now
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Conversion {
+    Exact,
+    Promotion,
+}
+
+impl Conversion {
+    /// how the argument, which could be declared as any of `args`, is passed as `param`
+    ///
+    /// return [`None`] if it cant be passed as `param`
+    pub fn of(args: &[TypeDefine], param: &TypeDefine) -> Option<Self> {
+        if args.contains(param) {
+            return Some(Self::Exact);
+        }
+        let to = param.as_primitive()?;
+        args.iter()
+            .filter_map(|arg| arg.as_primitive())
+            .any(|from| from.promotable_to(to))
+            .then_some(Self::Promotion)
+    }
+}
+
+/// why an overload cant be called with the arguments
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    /// the count of parameters is not the count of arguments
+    ParamLen,
+    /// the nth argument cant be passed as the parameter
+    Arg(usize),
+}
+
+/// an overload, and how arguments are passed to it
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub overload: Overload,
+    pub conversions: Result<Vec<Conversion>, Rejection>,
+}
+
+impl Candidate {
+    fn new(overload: &Overload, args: &[Vec<TypeDefine>]) -> Self {
+        let conversions = if overload.params.len() != args.len() {
+            Err(Rejection::ParamLen)
+        } else {
+            overload
+                .params
+                .iter()
+                .zip(args)
+                .enumerate()
+                .map(|(nth, (param, arg))| {
+                    Conversion::of(arg, &param.ty).ok_or(Rejection::Arg(nth))
+                })
+                .collect()
+        };
+        Self {
+            overload: overload.clone(),
+            conversions,
+        }
+    }
+
+    pub fn is_viable(&self) -> bool {
+        self.conversions.is_ok()
+    }
+
+    /// if both are viable, and every argument is passed to this no worse than to the
+    /// other, and at least one is passed better
+    ///
+    /// if all arguments are passed equally, a function is better than an instance of a
+    /// generic function
+    pub fn better_than(&self, other: &Self) -> bool {
+        match (&self.conversions, &other.conversions) {
+            (Ok(this), Ok(that)) if this == that => {
+                self.overload.instance.is_none() && other.overload.instance.is_some()
+            }
+            (Ok(this), Ok(that)) => this.iter().zip(that).all(|(l, r)| l <= r),
+            _ => false,
+        }
+    }
+}
+
+/// overloads of a called function, ranked by how arguments are passed to them
+#[derive(Debug, Clone)]
+pub struct Resolution {
+    pub candidates: Vec<Candidate>,
+}
+
+impl Resolution {
+    /// `args` are types which each argument could be declared as
+    pub fn new(overloads: &[Overload], args: &[Vec<TypeDefine>]) -> Self {
+        let candidates = overloads
+            .iter()
+            .map(|overload| Candidate::new(overload, args))
+            .collect();
+        Self { candidates }
+    }
+
+    pub fn is_viable(&self) -> bool {
+        self.candidates.iter().any(Candidate::is_viable)
+    }
+
+    /// a viable candidate which is better than the candidate
+    pub fn outranked_by(&self, candidate: &Candidate) -> Option<&Candidate> {
+        self.candidates
+            .iter()
+            .find(|other| other.better_than(candidate))
+    }
+
+    /// if the candidate is viable, and no other candidate is better than it
+    pub fn is_best(&self, candidate: &Candidate) -> bool {
+        candidate.is_viable() && self.outranked_by(candidate).is_none()
+    }
+
+    /// make an error which explains why each overload cant be called, used if the
+    /// resolution is not [`Resolution::is_viable`]
+    ///
+    /// `args` are spans of arguments
+    pub fn make_error(&self, name: &str, at: Span, args: &[Span]) -> terl::Error {
+        let mut err = at.make_error(py_lex::msg!(NoMatchingOverload, name = name));
+        for candidate in &self.candidates {
+            let overload = &candidate.overload;
+            let defined = py_lex::msg!(CandidateDefinedHere, sign = overload);
+            err = err.append(overload.sign_span.make_message(defined));
+            match candidate.conversions {
+                Ok(_) => {}
+                Err(Rejection::ParamLen) => {
+                    let reason = py_lex::msg!(
+                        ParamLenMismatch,
+                        expect = overload.params.len(),
+                        got = args.len()
+                    );
+                    err += py_lex::msg!(Note, note = reason);
+                }
+                Err(Rejection::Arg(nth)) => {
+                    let ty = &overload.params[nth].ty;
+                    let reason = py_lex::msg!(ArgMismatch, nth = nth + 1, ty = ty);
+                    err = err.append(args[nth].make_message(reason));
+                }
+            }
+        }
+        err
+    }
+}
@@ -0,0 +1,70 @@
+use crate::GroupIdx;
+
+/// disjoint sets of [`DeclareGroup`]s which are merged, groups in a set must be declared
+/// as a same type
+///
+/// [`DeclareGroup`]: crate::DeclareGroup
+#[derive(Default, Debug)]
+pub(crate) struct UnionFind {
+    parents: Vec<usize>,
+    /// sizes of sets, only the one of the root is valid
+    sizes: Vec<usize>,
+}
+
+impl UnionFind {
+    /// add a group which is in a set of its own
+    pub fn push(&mut self, group: GroupIdx) {
+        debug_assert_eq!(group.idx, self.parents.len());
+        self.parents.push(group.idx);
+        self.sizes.push(1);
+    }
+
+    pub fn find(&mut self, group: GroupIdx) -> GroupIdx {
+        let mut root = group.idx;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+        // path compression
+        let mut idx = group.idx;
+        while self.parents[idx] != root {
+            idx = std::mem::replace(&mut self.parents[idx], root);
+        }
+        GroupIdx::new(root)
+    }
+
+    /// the smaller set is merged into the larger one
+    ///
+    /// return the root of the merged set
+    pub fn union(&mut self, a: GroupIdx, b: GroupIdx) -> GroupIdx {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return a;
+        }
+        let (root, child) = if self.sizes[a.idx] >= self.sizes[b.idx] {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        self.parents[child.idx] = root.idx;
+        self.sizes[root.idx] += self.sizes[child.idx];
+        root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn union_and_find() {
+        let groups = (0..5).map(GroupIdx::new).collect::<Vec<_>>();
+        let mut sets = UnionFind::default();
+        groups.iter().for_each(|group| sets.push(*group));
+
+        sets.union(groups[0], groups[1]);
+        sets.union(groups[2], groups[3]);
+        sets.union(groups[3], groups[0]);
+        assert_eq!(sets.find(groups[1]), sets.find(groups[2]));
+        assert_ne!(sets.find(groups[1]), sets.find(groups[4]));
+    }
+}
@@ -11,6 +11,8 @@ pub trait IRValue {
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 pub enum Item<Var: IRValue = crate::value::Value> {
     FnDefine(FnDefine<Var>),
+    Struct(Struct),
+    Global(Global),
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
@@ -83,6 +85,7 @@ pub enum Statement<Var: IRValue> {
     If(If<Var>),
     While(While<Var>),
     Return(Return<Var>),
+    Location(Location),
 }
 
 impl<Var: IRValue> ControlFlow for Statement<Var> {
@@ -105,6 +108,18 @@ mod from_impls {
         }
     }
 
+    impl<Var: IRValue> From<Struct> for Item<Var> {
+        fn from(v: Struct) -> Self {
+            Self::Struct(v)
+        }
+    }
+
+    impl<Var: IRValue> From<Global> for Item<Var> {
+        fn from(v: Global) -> Self {
+            Self::Global(v)
+        }
+    }
+
     impl<Var: IRValue> From<VarDefine<Var>> for Statement<Var> {
         fn from(v: VarDefine<Var>) -> Self {
             Self::VarDefine(v)
@@ -140,6 +155,12 @@ mod from_impls {
             Self::Return(v)
         }
     }
+
+    impl<Var: IRValue> From<Location> for Statement<Var> {
+        fn from(v: Location) -> Self {
+            Self::Location(v)
+        }
+    }
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
@@ -158,6 +179,37 @@ pub struct FnDefine<Var: IRValue> {
     pub name: String,
     pub params: Vec<Parameter<Var::ParameterType>>,
     pub body: Statements<Var>,
+    /// the generic function and type arguments which the function is an instance of
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<types::Template>,
+}
+
+/// a type with named fields, classes are lowered to it
+///
+/// structs are defined before functions, and a struct is defined after structs its
+/// fields are of
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct Struct {
+    pub name: String,
+    pub fields: Vec<Field>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct Field {
+    #[serde(rename = "type")]
+    pub ty: types::TypeDefine,
+    pub name: String,
+}
+
+/// a variable which lives as long as the program, it's zero-initialized
+///
+/// globals are used as variables in functions, unless a variable in them has the same
+/// name. they are not generated from source code, but by hosts like the repl
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct Global {
+    #[serde(rename = "type")]
+    pub ty: types::TypeDefine,
+    pub name: String,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
@@ -182,6 +234,11 @@ pub struct VarDefine<Var: IRValue> {
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 pub struct VarStore<Var> {
     pub name: String,
+    /// fields of the variable, the value is stored into the last one. the value is
+    /// stored into the variable itself if it's empty
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<String>,
     pub val: Var,
 }
 
@@ -241,6 +298,17 @@ impl<Var: IRValue> ControlFlow for Return<Var> {
     }
 }
 
+/// source location of statements follow it, until the next [`Location`]
+///
+/// this is only a hint for code generation backends to emit debug information, and it
+/// has no effect on semantics
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct Location {
+    pub file: String,
+    /// start from 1
+    pub line: usize,
+}
+
 #[macro_export]
 macro_rules! custom_ir_variable {
     ($vis:vis IR<$variable:ty>) => {
@@ -46,6 +46,32 @@ impl PrimitiveType {
         )
     }
 
+    /// if the value of the type could be implicitly converted to `to` without loss
+    ///
+    /// integers are widened to wider integers of the same signedness, and `f32` to `f64`.
+    /// pointer-sized integers are never widened, because their widths depend on targets
+    pub fn promotable_to(&self, to: &Self) -> bool {
+        let pointer_sized = |ty: &Self| matches!(ty, Self::Usize | Self::Isize);
+        if *self == Self::F32 {
+            return *to == Self::F64;
+        }
+        self.is_integer()
+            && to.is_integer()
+            && !pointer_sized(self)
+            && !pointer_sized(to)
+            && self.is_signed() == to.is_signed()
+            && self.width() < to.width()
+    }
+
+    /// the largest value of an integer type, [`None`] if the type is not an integer type
+    pub fn max_integer(&self) -> Option<u128> {
+        if !self.is_integer() {
+            return None;
+        }
+        let bits = self.width() - self.is_signed() as usize;
+        Some(u128::MAX >> (128 - bits))
+    }
+
     pub fn width(&self) -> usize {
         match self {
             PrimitiveType::Bool => 1,
@@ -175,10 +201,37 @@ impl std::fmt::Display for ComplexType {
     }
 }
 
+/// type of functions, values of it are pointers to functions which take parameters of
+/// `params` and return a value of `ty`
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct FnType {
+    pub ty: Box<TypeDefine>,
+    pub params: Vec<TypeDefine>,
+}
+
+impl std::fmt::Display for FnType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let params = self.params.iter().map(ToString::to_string);
+        write!(
+            f,
+            "fn({}) -> {}",
+            params.collect::<Vec<_>>().join(", "),
+            self.ty
+        )
+    }
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
 pub enum TypeDefine {
     Primitive(PrimitiveType),
     Complex(ComplexType),
+    Fn(FnType),
+    /// a reference to a variable of the type, a variable of it is used as the variable
+    /// it refers to
+    ///
+    /// closures capture variables by reference with it, it's the type of fields of their
+    /// environments, and of variables unpacked from environments
+    Ref(Box<TypeDefine>),
 }
 
 impl TypeDefine {
@@ -205,6 +258,31 @@ impl TypeDefine {
             None
         }
     }
+
+    pub fn as_fn(&self) -> Option<&FnType> {
+        if let Self::Fn(v) = self {
+            Some(v)
+        } else {
+            None
+        }
+    }
+
+    /// the type with types in it replaced, types of parameters and return types of function
+    /// types are replaced too
+    pub fn replaced(&self, replace: &impl Fn(&TypeDefine) -> Option<TypeDefine>) -> TypeDefine {
+        if let Some(replaced) = replace(self) {
+            return replaced;
+        }
+        match self {
+            TypeDefine::Fn(fn_ty) => FnType {
+                ty: Box::new(fn_ty.ty.replaced(replace)),
+                params: fn_ty.params.iter().map(|ty| ty.replaced(replace)).collect(),
+            }
+            .into(),
+            TypeDefine::Ref(ty) => TypeDefine::Ref(Box::new(ty.replaced(replace))),
+            _ => self.clone(),
+        }
+    }
 }
 
 impl std::fmt::Display for TypeDefine {
@@ -212,6 +290,8 @@ impl std::fmt::Display for TypeDefine {
         match self {
             TypeDefine::Primitive(ty) => write!(f, "{}", ty),
             TypeDefine::Complex(ty) => write!(f, "{}", ty),
+            TypeDefine::Fn(ty) => write!(f, "{}", ty),
+            TypeDefine::Ref(ty) => write!(f, "& {}", ty),
         }
     }
 }
@@ -222,6 +302,12 @@ impl From<ComplexType> for TypeDefine {
     }
 }
 
+impl From<FnType> for TypeDefine {
+    fn from(v: FnType) -> Self {
+        Self::Fn(v)
+    }
+}
+
 impl From<PrimitiveType> for TypeDefine {
     fn from(v: PrimitiveType) -> Self {
         Self::Primitive(v)
@@ -234,7 +320,7 @@ impl TryFrom<TypeDefine> for PrimitiveType {
     fn try_from(value: TypeDefine) -> Result<Self, Self::Error> {
         match value {
             TypeDefine::Primitive(p) => Ok(p),
-            TypeDefine::Complex(_) => Err(value),
+            TypeDefine::Complex(_) | TypeDefine::Fn(_) | TypeDefine::Ref(_) => Err(value),
         }
     }
 }
@@ -244,8 +330,8 @@ impl TryFrom<TypeDefine> for ComplexType {
 
     fn try_from(value: TypeDefine) -> Result<Self, Self::Error> {
         match value {
-            TypeDefine::Primitive(_) => Err(value),
             TypeDefine::Complex(c) => Ok(c),
+            TypeDefine::Primitive(_) | TypeDefine::Fn(_) | TypeDefine::Ref(_) => Err(value),
         }
     }
 }
@@ -254,7 +340,7 @@ impl PartialEq<PrimitiveType> for TypeDefine {
     fn eq(&self, other: &PrimitiveType) -> bool {
         match self {
             TypeDefine::Primitive(s) => s == other,
-            TypeDefine::Complex(_) => false,
+            TypeDefine::Complex(_) | TypeDefine::Fn(_) | TypeDefine::Ref(_) => false,
         }
     }
 }
@@ -284,6 +370,16 @@ impl From<String> for Type {
     }
 }
 
+impl From<&TypeDefine> for Type {
+    fn from(v: &TypeDefine) -> Self {
+        match v {
+            TypeDefine::Primitive(ty) => Self::Primitive(*ty),
+            TypeDefine::Ref(ty) => Template::reference(ty.as_ref().into()).into(),
+            TypeDefine::Complex(_) | TypeDefine::Fn(_) => Self::Custom(v.to_string()),
+        }
+    }
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
 pub struct Template {
     pub name: String,
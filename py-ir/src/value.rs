@@ -2,12 +2,16 @@ use std::fmt::Write;
 
 use py_lex::ops::Operators;
 
-use crate::types::{PrimitiveType, TypeDefine};
+use crate::types::{FnType, PrimitiveType, TypeDefine};
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 pub enum Value {
     Variable(String),
     Literal(Literal, PrimitiveType),
+    /// a function as a value, by its mangled name
+    Fn(String),
+    /// a reference to the variable, its type is [`TypeDefine::Ref`]
+    Ref(String),
 }
 
 /// [`Operate::Unary`] and [`Operate::Binary`] are normal operations aroud primitives
@@ -17,13 +21,20 @@ pub enum Value {
 pub enum Operate {
     Unary(Operators, Value),
     Binary(Operators, Value, Value),
+    /// convert the value from the type to the type of the operation
+    ///
+    /// only implicit promotions([`PrimitiveType::promotable_to`]) are generated now
+    Convert(PrimitiveType, Value),
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 pub enum AssignValue {
     Value(Value),
     FnCall(FnCall<Value>),
+    IndirectCall(IndirectCall<Value>),
     Operate(Operate, PrimitiveType),
+    /// a field of the value, whose type is a [`crate::Struct`]
+    Field(Value, String),
 }
 
 impl From<Value> for AssignValue {
@@ -38,6 +49,12 @@ impl From<FnCall<Value>> for AssignValue {
     }
 }
 
+impl From<IndirectCall<Value>> for AssignValue {
+    fn from(v: IndirectCall<Value>) -> Self {
+        Self::IndirectCall(v)
+    }
+}
+
 impl From<(Operate, PrimitiveType)> for AssignValue {
     fn from(value: (Operate, PrimitiveType)) -> Self {
         Self::Operate(value.0, value.1)
@@ -58,6 +75,15 @@ pub struct FnCall<Var> {
     pub args: Vec<Var>,
 }
 
+/// call the function which the value of `callee` points to
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct IndirectCall<Var> {
+    pub callee: Var,
+    /// type of `callee`
+    pub ty: FnType,
+    pub args: Vec<Var>,
+}
+
 /// [`Literal::Char`], [`Literal::Integer`] and [`Literal::Float`]
 /// mean literals
 ///
@@ -68,7 +94,7 @@ pub struct FnCall<Var> {
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 pub enum Literal {
     Char(char),
-    Integer(usize),
+    Integer(u128),
     Float(f64),
 }
 
@@ -1,9 +1,14 @@
 mod macros;
+pub mod messages;
 
 #[cfg(feature = "parse")]
 mod token;
 #[cfg(feature = "parse")]
 pub use token::*;
+#[cfg(feature = "parse")]
+mod source_map;
+#[cfg(feature = "parse")]
+pub use source_map::*;
 
 #[cfg(feature = "ops")]
 pub mod ops;
@@ -40,13 +40,14 @@ macro_rules! reverse_parse_keywords {
             type Left = $enum_name;
             fn reverse_parse(&self, p:&mut terl::Parser<$crate::Token>) -> Result<$enum_name, terl::ParseError> {
                 use terl::WithSpanExt;
+                use $crate::Tokens;
 
-                let Some(next) = p.next() else {
-                    return p.unmatch(format!("expect {}, but non token left", self))
+                let Some(next) = p.next_token() else {
+                    return p.unmatch($crate::msg!(ExpectButEof, expect = self))
                 };
 
                 if &**next != &**self {
-                    let msg = format!("expect {}, but {} was got", self, &**next);
+                    let msg = $crate::msg!(ExpectButGot, expect = self, got = &**next);
                     return p.unmatch(msg)
                 }
 
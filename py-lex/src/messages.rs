@@ -0,0 +1,879 @@
+//! catalog of messages which are shown to users, in all supported languages
+//!
+//! messages are made with [`crate::msg`], placeholders like `{name}` in templates are
+//! replaced with arguments
+//!
+//! a message starts with the tag of its key, which is a char in the private use area, so
+//! that the key is known without parsing the message. tags must be removed by [`untag`]
+//! before messages are shown
+
+use std::{
+    fmt::Display,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum Lang {
+    #[default]
+    En,
+    Zh,
+}
+
+impl Lang {
+    pub const ALL: &'static [Lang] = &[Lang::En, Lang::Zh];
+
+    fn catalog(self) -> &'static [(Key, &'static str)] {
+        match self {
+            Lang::En => EN,
+            Lang::Zh => ZH,
+        }
+    }
+}
+
+static LANG: AtomicU8 = AtomicU8::new(Lang::En as u8);
+
+/// set the language of messages, for all threads
+pub fn set_lang(lang: Lang) {
+    LANG.store(lang as u8, Ordering::Relaxed);
+}
+
+pub fn lang() -> Lang {
+    match LANG.load(Ordering::Relaxed) {
+        1 => Lang::Zh,
+        _ => Lang::En,
+    }
+}
+
+macro_rules! keys {
+    ($($key:ident),* $(,)?) => {
+        /// key of a message in the catalog
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum Key {
+            $($key,)*
+        }
+
+        impl Key {
+            pub const ALL: &'static [Key] = &[$(Key::$key),*];
+        }
+    };
+}
+
+keys! {
+    // tokens
+    ExpectButEof,
+    ExpectKindButEof,
+    ExpectButGot,
+    NoTokenLeft,
+    EmptyToken,
+    MatchesNone,
+    // syntax
+    ExpectFnDefine,
+    ExpectStatement,
+    IdentButEof,
+    IdentStartsWithDigit,
+    KeywordAsIdent,
+    ExpectNumber,
+    UnexpectedToken,
+    InvalidEscape,
+    InvalidChar,
+    UnfinishedEscape,
+    LeftBracketHere,
+    ExpectThis,
+    UnclosedBracket,
+    UnclosedComment,
+    // types
+    ExpectSign,
+    UnsupportedIntWidth,
+    SignedFloat,
+    UnsupportedFloatWidth,
+    UnsupportedSign,
+    UnsupportedWidth,
+    UnsupportedDecorator,
+    // definitions and uses
+    FnDefinedHere,
+    DoubleDefine,
+    OverloadByReturnType,
+    OverloadHint,
+    NeverReturn,
+    UndefinedFn,
+    UndefinedVar,
+    AssignImmutable,
+    Note,
+    // type inference
+    CantInferType,
+    NoPossibleType,
+    PossibleTypes,
+    FilteredLater,
+    ShouldBe,
+    ConflictTypes,
+    BranchNeverUsed,
+    ShouldAlign,
+    DeclaredAs,
+    ExpectDeclaredAs,
+    Filtered,
+    GuessedAs,
+    FnReturning,
+    ValOfType,
+    FnWithParams,
+    OverloadsWithLen,
+    NoOverloadMatched,
+    FnWithNthParam,
+    RecursiveInference,
+    ExplicitReturnType,
+    NoReturnValue,
+    AmbiguousVarType,
+    ExplicitVarType,
+    LiteralTooLarge,
+    LiteralOutOfRange,
+    LiteralOverflow,
+    DefaultedAs,
+    PromotableTo,
+    NoMatchingOverload,
+    CandidateDefinedHere,
+    ParamLenMismatch,
+    ArgMismatch,
+    AmbiguousCall,
+    OutrankedBy,
+    GenericExport,
+    GenericInferredReturn,
+    CantInferTypeArgs,
+    InstanceOf,
+    InstantiatedHere,
+    // classes
+    UndefinedType,
+    DoubleClass,
+    DoubleField,
+    ClassDefinedHere,
+    RecursiveClass,
+    ExpectReceiver,
+    GenericMethod,
+    NotAClass,
+    NoField,
+    NoMethod,
+    OperatorOnClass,
+    // interfaces
+    UndefinedInterface,
+    DoubleInterface,
+    InterfaceDefinedHere,
+    NotImplemented,
+    MissingFn,
+    ImplementsInterface,
+    BoundUnsatisfied,
+    // operators
+    NotOverloadable,
+    OperatorParamLen,
+    OperatorInferredReturn,
+    OperatorHint,
+    // function values
+    GenericFnValue,
+    InferredFnValue,
+    AmbiguousFnValue,
+    IndirectCallArgLen,
+    // closures
+    ClosureNotBound,
+    ClosureAsValue,
+    ClosureArgLen,
+    AmbiguousCapture,
+    // modules
+    DoubleModule,
+    ModuleDefinedHere,
+    NotInModule,
+    NotInModuleHint,
+    UndefinedModule,
+    PrivateItem,
+    PublicHint,
+    // literals
+    StringLiteral,
+    ArrayLiteral,
+}
+
+const EN: &[(Key, &str)] = &[
+    (Key::ExpectButEof, "expect {expect}, but no token left"),
+    (Key::ExpectKindButEof, "expect a `{kind}`, but no token left"),
+    (Key::ExpectButGot, "expect {expect}, but {got} was got"),
+    (Key::NoTokenLeft, "no token left"),
+    (Key::EmptyToken, "empty token"),
+    (Key::MatchesNone, "{got} matches none of {expect}"),
+    (Key::ExpectFnDefine, "expect a function definition"),
+    (Key::ExpectStatement, "expect a statement"),
+    (Key::IdentButEof, "expect an identifier, but no token left"),
+    (
+        Key::IdentStartsWithDigit,
+        "identifiers should not start with a digit",
+    ),
+    (Key::KeywordAsIdent, "keywords cant be used as identifiers"),
+    (
+        Key::ExpectNumber,
+        "expect a number, but got `{error}` while parsing",
+    ),
+    (Key::UnexpectedToken, "unexpected {token}"),
+    (
+        Key::InvalidEscape,
+        "invalid or unsupported escape character: {char}",
+    ),
+    (Key::InvalidChar, "invalid char literal {literal}"),
+    (
+        Key::UnfinishedEscape,
+        "invalid escape, maybe a character is missing",
+    ),
+    (Key::LeftBracketHere, "left bracket here"),
+    (Key::ExpectThis, "expect this to be `{expect}`"),
+    (Key::UnclosedBracket, "unclosed bracket"),
+    (Key::UnclosedComment, "comment without ending"),
+    (Key::ExpectSign, "should be `you3fu2` or `wu2fu2`"),
+    (
+        Key::UnsupportedIntWidth,
+        "`zheng3` with width {width} is not supported now",
+    ),
+    (
+        Key::SignedFloat,
+        "`fu2` type cant be decorated with `you3fu2` or `wu2fu2`",
+    ),
+    (
+        Key::UnsupportedFloatWidth,
+        "`fu2` with width {width} is not supported now",
+    ),
+    (
+        Key::UnsupportedSign,
+        "type `{ty}` with `you3fu2` or `wu2fu2` is not supported now",
+    ),
+    (
+        Key::UnsupportedWidth,
+        "type `{ty}` with `kuan1` is not supported now",
+    ),
+    (
+        Key::UnsupportedDecorator,
+        "type `{ty}` with `she4`, `zu3`, `yin3` or `zhi3` is not supported now",
+    ),
+    (Key::FnDefinedHere, "function {name} has been defined here"),
+    (Key::DoubleDefine, "double define for function {name}"),
+    (
+        Key::OverloadByReturnType,
+        "overloads which only differ in return types are not allowed",
+    ),
+    (
+        Key::OverloadHint,
+        "if you want to overload function {name}, you can define them with different parameters",
+    ),
+    (Key::NeverReturn, "function `{name}` never returns"),
+    (Key::UndefinedFn, "call undefined function {name}"),
+    (Key::UndefinedVar, "use of undefined variable {name}"),
+    (
+        Key::AssignImmutable,
+        "cant assign to an immutable variable {name}",
+    ),
+    (Key::Note, "note: {note}"),
+    (Key::CantInferType, "cant infer type"),
+    (Key::NoPossibleType, "this cant be declared as any type!"),
+    (Key::PossibleTypes, "this could be declared as any of:"),
+    (
+        Key::FilteredLater,
+        "this has been declared, but filtered later",
+    ),
+    (Key::ShouldBe, "this should be `{expect}`"),
+    (
+        Key::ConflictTypes,
+        "this is required to be declared as {this} and {other} together, but it's impossible",
+    ),
+    (Key::BranchNeverUsed, "the branch is never used in group"),
+    (
+        Key::ShouldAlign,
+        "those two are required to have the same type",
+    ),
+    (Key::DeclaredAs, "this has been declared as {ty}"),
+    (
+        Key::ExpectDeclaredAs,
+        "expect this to be declared as `{expect}`",
+    ),
+    (Key::Filtered, "this has been filtered"),
+    (Key::GuessedAs, "this used to be guessed as {ty}"),
+    (Key::FnReturning, "a function whose return type is {ty}"),
+    (Key::ValOfType, "a value whose type is {ty}"),
+    (Key::FnWithParams, "a function with {count} parameters"),
+    (
+        Key::OverloadsWithLen,
+        "overloads with the expected number of parameters:",
+    ),
+    (Key::NoOverloadMatched, "no overload matched"),
+    (
+        Key::FnWithNthParam,
+        "a function whose parameter {nth} is {ty}",
+    ),
+    (
+        Key::RecursiveInference,
+        "cant infer the return type of `{name}`, because it calls itself: {cycle}",
+    ),
+    (
+        Key::ExplicitReturnType,
+        "write the return type of a function in the cycle explicitly",
+    ),
+    (
+        Key::NoReturnValue,
+        "cant infer the return type of `{name}`, because no `fan3` in it returns a value",
+    ),
+    (
+        Key::AmbiguousVarType,
+        "cant infer the type of `{name}`, it could be declared as any of:",
+    ),
+    (
+        Key::ExplicitVarType,
+        "write the type of `{name}` explicitly instead of `zi4dong4`",
+    ),
+    (
+        Key::LiteralTooLarge,
+        "the literal `{literal}` is too large, integers are at most {max}",
+    ),
+    (
+        Key::LiteralOutOfRange,
+        "{literal} is out of the range of this type, which is at most {max}",
+    ),
+    (
+        Key::LiteralOverflow,
+        "cant infer the type of the literal `{literal}`, it's too large for some types",
+    ),
+    (Key::DefaultedAs, "this is declared as {ty} by default"),
+    (
+        Key::PromotableTo,
+        "a value of {ty}, or of a type which could be promoted to it",
+    ),
+    (
+        Key::NoMatchingOverload,
+        "no overload of `{name}` could be called with these arguments",
+    ),
+    (
+        Key::CandidateDefinedHere,
+        "candidate `{sign}` is defined here",
+    ),
+    (
+        Key::ParamLenMismatch,
+        "it takes {expect} arguments, but {got} are given",
+    ),
+    (Key::ArgMismatch, "argument {nth} cant be passed as {ty}"),
+    (
+        Key::AmbiguousCall,
+        "this call is ambiguous, it could call any of these overloads:",
+    ),
+    (Key::OutrankedBy, "`{better}` is a better match"),
+    (
+        Key::GenericExport,
+        "generic function `{name}` cant be exported, because only its instances are generated",
+    ),
+    (
+        Key::GenericInferredReturn,
+        "the return type of generic function `{name}` must be written explicitly",
+    ),
+    (
+        Key::CantInferTypeArgs,
+        "its type arguments cant be inferred from the arguments",
+    ),
+    (
+        Key::InstanceOf,
+        "in `{instance}`, an instance of the generic function defined here",
+    ),
+    (Key::InstantiatedHere, "instantiated here"),
+    (Key::UndefinedType, "use of undefined type `{name}`"),
+    (Key::DoubleClass, "class `{name}` is defined more than once"),
+    (Key::DoubleField, "field `{name}` is defined more than once"),
+    (Key::ClassDefinedHere, "class `{name}` is defined here"),
+    (
+        Key::RecursiveClass,
+        "class `{name}` contains itself through its fields: {cycle}",
+    ),
+    (
+        Key::ExpectReceiver,
+        "the first parameter of method `{name}` must be of `{class}`, it receives the instance",
+    ),
+    (Key::GenericMethod, "method `{name}` cant be generic"),
+    (
+        Key::NotAClass,
+        "a value of `{ty}` has no fields or methods, because it's not a class",
+    ),
+    (Key::NoField, "class `{class}` has no field `{field}`"),
+    (Key::NoMethod, "class `{class}` has no method `{name}`"),
+    (
+        Key::OperatorOnClass,
+        "operator `{op}` cant be applied to a value of class `{ty}`",
+    ),
+    (
+        Key::UndefinedInterface,
+        "use of undefined interface `{name}`",
+    ),
+    (
+        Key::DoubleInterface,
+        "interface `{name}` is defined more than once",
+    ),
+    (
+        Key::InterfaceDefinedHere,
+        "interface `{name}` is defined here",
+    ),
+    (
+        Key::NotImplemented,
+        "class `{class}` doesnt implement interface `{interface}`",
+    ),
+    (Key::MissingFn, "`{sign}` is missing"),
+    (
+        Key::ImplementsInterface,
+        "a type implementing `{interface}`",
+    ),
+    (
+        Key::BoundUnsatisfied,
+        "`{ty}` cant be passed as `{param}`, because it doesnt implement `{interface}`",
+    ),
+    (Key::NotOverloadable, "operator `{op}` cant be overloaded"),
+    (
+        Key::OperatorParamLen,
+        "operator `{op}` takes {expect} operands, but {got} parameters are given",
+    ),
+    (
+        Key::OperatorInferredReturn,
+        "the return type of operator `{op}` must be written explicitly",
+    ),
+    (
+        Key::OperatorHint,
+        "define a function like `yun4suan4 {op}` to apply it to classes",
+    ),
+    (
+        Key::GenericFnValue,
+        "generic function `{name}` cant be used as a value, because only its instances are generated",
+    ),
+    (
+        Key::InferredFnValue,
+        "the return type of `{name}` must be written explicitly to use it as a value",
+    ),
+    (
+        Key::AmbiguousFnValue,
+        "`{name}` could be any of `{types}`, its type must be written explicitly to call it",
+    ),
+    (
+        Key::IndirectCallArgLen,
+        "`{name}` is a `{ty}`, it takes {expect} arguments, but {got} are given",
+    ),
+    (
+        Key::ClosureNotBound,
+        "closures can only initialize variables whose types are inferred, like `zi4dong4 f wei2 bi4bao1 ...`",
+    ),
+    (Key::ClosureAsValue, "`{name}` is a closure, it can only be called"),
+    (
+        Key::ClosureArgLen,
+        "closure `{name}` takes {expect} arguments, but {got} are given",
+    ),
+    (
+        Key::AmbiguousCapture,
+        "`{name}` could be any of `{types}`, its type must be written explicitly to capture it in a closure",
+    ),
+    (Key::DoubleModule, "double define for module `{name}`"),
+    (Key::ModuleDefinedHere, "module `{name}` is defined here"),
+    (
+        Key::NotInModule,
+        "`{name}` cant be defined in the module `{module}`, classes and interfaces must be defined outside modules",
+    ),
+    (
+        Key::NotInModuleHint,
+        "types are not qualified by modules, define it outside modules and functions in modules could still use it",
+    ),
+    (Key::UndefinedModule, "use of undefined module `{name}`"),
+    (
+        Key::PrivateItem,
+        "`{name}` is private, it cant be used outside the module `{module}`",
+    ),
+    (
+        Key::PublicHint,
+        "mark it with `gong1kai1` to use it outside the module",
+    ),
+    (Key::StringLiteral, "string literals are not supported yet"),
+    (Key::ArrayLiteral, "array literals are not supported yet"),
+];
+
+const ZH: &[(Key, &str)] = &[
+    (Key::ExpectButEof, "此处应为 {expect}，但已没有更多的词"),
+    (Key::ExpectKindButEof, "此处应为 `{kind}`，但已没有更多的词"),
+    (Key::ExpectButGot, "此处应为 {expect}，但得到了 {got}"),
+    (Key::NoTokenLeft, "已没有更多的词"),
+    (Key::EmptyToken, "空的词"),
+    (Key::MatchesNone, "{got} 不是任何一种 {expect}"),
+    (Key::ExpectFnDefine, "此处应为函数定义"),
+    (Key::ExpectStatement, "此处应为语句"),
+    (Key::IdentButEof, "此处应为标识符，但已没有更多的词"),
+    (Key::IdentStartsWithDigit, "标识符不能以数字开头"),
+    (Key::KeywordAsIdent, "关键字不能用作标识符"),
+    (Key::ExpectNumber, "此处应为数字，但解析时出错：`{error}`"),
+    (Key::UnexpectedToken, "意外的 {token}"),
+    (Key::InvalidEscape, "无效或不支持的转义字符：{char}"),
+    (Key::InvalidChar, "无效的字符字面量 {literal}"),
+    (Key::UnfinishedEscape, "无效的转义，可能缺少了一个字符"),
+    (Key::LeftBracketHere, "左括号在这里"),
+    (Key::ExpectThis, "此处应为 `{expect}`"),
+    (Key::UnclosedBracket, "括号没有闭合"),
+    (Key::UnclosedComment, "注释没有结束"),
+    (Key::ExpectSign, "此处应为 `you3fu2` 或 `wu2fu2`"),
+    (
+        Key::UnsupportedIntWidth,
+        "暂不支持宽度为 {width} 的 `zheng3`",
+    ),
+    (
+        Key::SignedFloat,
+        "`fu2` 类型不能用 `you3fu2` 或 `wu2fu2` 修饰",
+    ),
+    (
+        Key::UnsupportedFloatWidth,
+        "暂不支持宽度为 {width} 的 `fu2`",
+    ),
+    (
+        Key::UnsupportedSign,
+        "暂不支持用 `you3fu2` 或 `wu2fu2` 修饰类型 `{ty}`",
+    ),
+    (Key::UnsupportedWidth, "暂不支持用 `kuan1` 修饰类型 `{ty}`"),
+    (
+        Key::UnsupportedDecorator,
+        "暂不支持用 `she4`、`zu3`、`yin3` 或 `zhi3` 修饰类型 `{ty}`",
+    ),
+    (Key::FnDefinedHere, "函数 {name} 已在此处定义"),
+    (Key::DoubleDefine, "函数 {name} 被重复定义"),
+    (Key::OverloadByReturnType, "不允许仅有返回类型不同的重载"),
+    (
+        Key::OverloadHint,
+        "如果想要重载函数 {name}，可以用不同的参数定义它们",
+    ),
+    (Key::NeverReturn, "函数 `{name}` 没有返回"),
+    (Key::UndefinedFn, "调用了未定义的函数 {name}"),
+    (Key::UndefinedVar, "使用了未定义的变量 {name}"),
+    (Key::AssignImmutable, "不能给不可变的变量 {name} 赋值"),
+    (Key::Note, "注意：{note}"),
+    (Key::CantInferType, "无法推断类型"),
+    (Key::NoPossibleType, "这里无法被推断为任何类型！"),
+    (Key::PossibleTypes, "这里可以被推断为以下任一类型："),
+    (Key::FilteredLater, "这里已被推断，但随后被过滤掉了"),
+    (Key::ShouldBe, "这里应为 `{expect}`"),
+    (
+        Key::ConflictTypes,
+        "这里需要同时被推断为 {this} 和 {other}，但这是不可能的",
+    ),
+    (Key::BranchNeverUsed, "该分支在组中从未被使用"),
+    (Key::ShouldAlign, "这两处需要具有相同的类型"),
+    (Key::DeclaredAs, "这里已被推断为 {ty}"),
+    (Key::ExpectDeclaredAs, "这里应被推断为 `{expect}`"),
+    (Key::Filtered, "这里已被过滤掉了"),
+    (Key::GuessedAs, "这里曾被推测为 {ty}"),
+    (Key::FnReturning, "返回类型为 {ty} 的函数"),
+    (Key::ValOfType, "类型为 {ty} 的值"),
+    (Key::FnWithParams, "有 {count} 个参数的函数"),
+    (Key::OverloadsWithLen, "参数个数符合的重载："),
+    (Key::NoOverloadMatched, "没有匹配的重载"),
+    (Key::FnWithNthParam, "第 {nth} 个参数为 {ty} 的函数"),
+    (
+        Key::RecursiveInference,
+        "无法推断函数 `{name}` 的返回类型，因为它调用了自身：{cycle}",
+    ),
+    (
+        Key::ExplicitReturnType,
+        "请显式写出循环中某个函数的返回类型",
+    ),
+    (
+        Key::NoReturnValue,
+        "无法推断函数 `{name}` 的返回类型，因为其中没有 `fan3` 返回了值",
+    ),
+    (
+        Key::AmbiguousVarType,
+        "无法推断变量 `{name}` 的类型，它可以被推断为以下任一类型：",
+    ),
+    (
+        Key::ExplicitVarType,
+        "请显式写出 `{name}` 的类型，而不是 `zi4dong4`",
+    ),
+    (
+        Key::LiteralTooLarge,
+        "字面量 `{literal}` 太大了，整数最大为 {max}",
+    ),
+    (
+        Key::LiteralOutOfRange,
+        "{literal} 超出了这个类型的范围，它最大为 {max}",
+    ),
+    (
+        Key::LiteralOverflow,
+        "无法推断字面量 `{literal}` 的类型，它对部分类型来说太大了",
+    ),
+    (Key::DefaultedAs, "这里被默认推断为 {ty}"),
+    (Key::PromotableTo, "类型为 {ty} 或可以被提升为该类型的值"),
+    (
+        Key::NoMatchingOverload,
+        "`{name}` 没有可以用这些参数调用的重载",
+    ),
+    (Key::CandidateDefinedHere, "候选重载 `{sign}` 在此处定义"),
+    (
+        Key::ParamLenMismatch,
+        "它需要 {expect} 个参数，但是给出了 {got} 个",
+    ),
+    (Key::ArgMismatch, "第 {nth} 个参数不能作为 {ty} 传入"),
+    (
+        Key::AmbiguousCall,
+        "这个调用有歧义，它可以调用以下任一重载：",
+    ),
+    (Key::OutrankedBy, "`{better}` 是更好的匹配"),
+    (
+        Key::GenericExport,
+        "泛型函数 `{name}` 不能被导出，因为只有它的实例会被生成",
+    ),
+    (
+        Key::GenericInferredReturn,
+        "必须显式写出泛型函数 `{name}` 的返回类型",
+    ),
+    (Key::CantInferTypeArgs, "无法从参数推断出它的类型参数"),
+    (
+        Key::InstanceOf,
+        "在 `{instance}` 中，它是在此处定义的泛型函数的实例",
+    ),
+    (Key::InstantiatedHere, "在此处被实例化"),
+    (Key::UndefinedType, "使用了未定义的类型 `{name}`"),
+    (Key::DoubleClass, "类 `{name}` 被重复定义"),
+    (Key::DoubleField, "字段 `{name}` 被重复定义"),
+    (Key::ClassDefinedHere, "类 `{name}` 在此处定义"),
+    (
+        Key::RecursiveClass,
+        "类 `{name}` 通过它的字段包含了它自身：{cycle}",
+    ),
+    (
+        Key::ExpectReceiver,
+        "方法 `{name}` 的第一个参数必须是 `{class}` 类型，它用于接收实例",
+    ),
+    (Key::GenericMethod, "方法 `{name}` 不能是泛型的"),
+    (
+        Key::NotAClass,
+        "`{ty}` 类型的值没有字段或方法，因为它不是类",
+    ),
+    (Key::NoField, "类 `{class}` 没有字段 `{field}`"),
+    (Key::NoMethod, "类 `{class}` 没有方法 `{name}`"),
+    (Key::OperatorOnClass, "运算符 `{op}` 不能用于类 `{ty}` 的值"),
+    (Key::UndefinedInterface, "使用了未定义的接口 `{name}`"),
+    (Key::DoubleInterface, "接口 `{name}` 被重复定义"),
+    (Key::InterfaceDefinedHere, "接口 `{name}` 在此处定义"),
+    (
+        Key::NotImplemented,
+        "类 `{class}` 没有实现接口 `{interface}`",
+    ),
+    (Key::MissingFn, "缺少 `{sign}`"),
+    (Key::ImplementsInterface, "实现了 `{interface}` 的类型"),
+    (
+        Key::BoundUnsatisfied,
+        "`{ty}` 不能作为 `{param}` 传入，因为它没有实现 `{interface}`",
+    ),
+    (Key::NotOverloadable, "运算符 `{op}` 不能被重载"),
+    (
+        Key::OperatorParamLen,
+        "运算符 `{op}` 需要 {expect} 个操作数，但给出了 {got} 个参数",
+    ),
+    (
+        Key::OperatorInferredReturn,
+        "运算符 `{op}` 的返回类型必须显式写出",
+    ),
+    (
+        Key::OperatorHint,
+        "定义形如 `yun4suan4 {op}` 的函数以将它用于类",
+    ),
+    (
+        Key::GenericFnValue,
+        "泛型函数 `{name}` 不能用作值，因为只有它的实例会被生成",
+    ),
+    (
+        Key::InferredFnValue,
+        "要将 `{name}` 用作值，它的返回类型必须显式写出",
+    ),
+    (
+        Key::AmbiguousFnValue,
+        "`{name}` 可能是 `{types}` 中的任何一个，必须显式写出它的类型才能调用它",
+    ),
+    (
+        Key::IndirectCallArgLen,
+        "`{name}` 是 `{ty}`，它需要 {expect} 个参数，但给出了 {got} 个",
+    ),
+    (
+        Key::ClosureNotBound,
+        "闭包只能用于初始化类型被推断的变量，如 `zi4dong4 f wei2 bi4bao1 ...`",
+    ),
+    (Key::ClosureAsValue, "`{name}` 是闭包，它只能被调用"),
+    (
+        Key::ClosureArgLen,
+        "闭包 `{name}` 需要 {expect} 个参数，但给出了 {got} 个",
+    ),
+    (
+        Key::AmbiguousCapture,
+        "`{name}` 可能是 `{types}` 中的任何一个，必须显式写出它的类型才能在闭包中捕获它",
+    ),
+    (Key::DoubleModule, "模块 `{name}` 被重复定义"),
+    (Key::ModuleDefinedHere, "模块 `{name}` 在此定义"),
+    (
+        Key::NotInModule,
+        "`{name}` 不能定义在模块 `{module}` 中，类和接口必须定义在模块之外",
+    ),
+    (
+        Key::NotInModuleHint,
+        "类型不会被模块限定，在模块之外定义它，模块中的函数仍然可以使用它",
+    ),
+    (Key::UndefinedModule, "使用了未定义的模块 `{name}`"),
+    (
+        Key::PrivateItem,
+        "`{name}` 是私有的，它不能在模块 `{module}` 之外使用",
+    ),
+    (Key::PublicHint, "用 `gong1kai1` 标记它以在模块之外使用它"),
+    (Key::StringLiteral, "暂不支持字符串字面量"),
+    (Key::ArrayLiteral, "暂不支持数组字面量"),
+];
+
+/// split the template into literal parts and names of placeholders
+fn split(template: &str) -> (Vec<&str>, Vec<&str>) {
+    let mut literals = vec![];
+    let mut placeholders = vec![];
+    let mut rest = template;
+    while let Some((literal, placeholder)) = rest.split_once('{') {
+        let (placeholder, after) = placeholder.split_once('}').unwrap();
+        literals.push(literal);
+        placeholders.push(placeholder);
+        rest = after;
+    }
+    literals.push(rest);
+    (literals, placeholders)
+}
+
+impl Key {
+    /// template of the message in the language
+    ///
+    /// falls back to english if the message is not translated
+    pub fn template(self, lang: Lang) -> &'static str {
+        [lang, Lang::En]
+            .iter()
+            .find_map(|lang| lang.catalog().iter().find(|(key, _)| *key == self))
+            .map(|(_, template)| *template)
+            .unwrap()
+    }
+
+    /// make the message in the current language, use [`crate::msg`] instead
+    pub fn format(self, args: &[(&str, &dyn Display)]) -> String {
+        self.format_in(lang(), args)
+    }
+
+    /// make the message in the language, it starts with the tag of the key
+    pub fn format_in(self, lang: Lang, args: &[(&str, &dyn Display)]) -> String {
+        let mut message = self.template(lang).to_owned();
+        for (name, value) in args {
+            message = message.replace(&format!("{{{name}}}"), &value.to_string());
+        }
+        message.insert(0, self.tag());
+        message
+    }
+
+    fn tag(self) -> char {
+        char::from_u32(TAGS.start + self as u32).unwrap()
+    }
+
+    /// if the message is made with this key in the current language, return values
+    /// of placeholders in order
+    ///
+    /// the message should be untagged by [`untag`]
+    pub fn parse(self, message: &str) -> Option<Vec<&str>> {
+        let (literals, _) = split(self.template(lang()));
+        let (first, rest) = literals.split_first().unwrap();
+        let mut message = message.strip_prefix(first)?;
+        let Some((last, middles)) = rest.split_last() else {
+            return message.is_empty().then(Vec::new);
+        };
+
+        let mut values = vec![];
+        for literal in middles {
+            let (value, after) = message.split_once(literal)?;
+            values.push(value);
+            message = after;
+        }
+        values.push(message.strip_suffix(last)?);
+        Some(values)
+    }
+
+    /// the key which the message is made with, taken from the tag of the message
+    pub fn of(message: &str) -> Option<Key> {
+        let tag = message.chars().next()? as u32;
+        let idx = tag.checked_sub(TAGS.start)?;
+        Self::ALL.get(idx as usize).copied()
+    }
+}
+
+/// tags of keys, [`Key`]s are mapped to chars in the private use area in order
+const TAGS: std::ops::Range<u32> = 0xE000..0xF900;
+
+/// remove tags from the message, including ones of messages which are arguments of it
+pub fn untag(message: &str) -> String {
+    let is_tag = |c: &char| TAGS.contains(&(*c as u32));
+    message.chars().filter(|c| !is_tag(c)).collect()
+}
+
+/// make a message in the current language
+///
+/// ``` ignore
+/// msg!(UndefinedVar, name = "a")
+/// ```
+#[macro_export]
+macro_rules! msg {
+    ($key:ident $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::messages::Key::$key.format(&[
+            $((stringify!($name), &$value as &dyn std::fmt::Display),)*
+        ])
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_key_in_every_lang() {
+        for lang in Lang::ALL {
+            for key in Key::ALL {
+                let templates = lang.catalog().iter().filter(|(k, _)| k == key).count();
+                assert_eq!(templates, 1, "{key:?} should be in {lang:?} exactly once");
+
+                let (_, mut placeholders) = split(key.template(*lang));
+                let (_, mut expect) = split(key.template(Lang::En));
+                placeholders.sort_unstable();
+                expect.sort_unstable();
+                assert_eq!(placeholders, expect, "placeholders of {key:?} in {lang:?}");
+            }
+            assert_eq!(lang.catalog().len(), Key::ALL.len());
+        }
+    }
+
+    #[test]
+    fn format_and_parse() {
+        let message = msg!(ExpectButGot, expect = "fen1", got = "wei2");
+        assert_eq!(Key::of(&message), Some(Key::ExpectButGot));
+        let message = untag(&message);
+        assert_eq!(message, "expect fen1, but wei2 was got");
+        assert_eq!(
+            Key::ExpectButGot.parse(&message),
+            Some(vec!["fen1", "wei2"])
+        );
+        assert_eq!(Key::of(&msg!(ExpectStatement)), Some(Key::ExpectStatement));
+        assert_eq!(Key::of("not a message"), None);
+        assert_eq!(Key::of(&message), None);
+    }
+
+    #[test]
+    fn keys_of_same_messages() {
+        assert!(Key::ALL.len() <= TAGS.len());
+
+        // the message is same as the one of `ExpectThis` in chinese
+        let sign = Key::ExpectSign.format_in(Lang::Zh, &[]);
+        let expect = "you3fu2` 或 `wu2fu2";
+        let this = Key::ExpectThis.format_in(Lang::Zh, &[("expect", &expect)]);
+        assert_eq!(untag(&sign), untag(&this));
+        assert_eq!(Key::of(&sign), Some(Key::ExpectSign));
+        assert_eq!(Key::of(&this), Some(Key::ExpectThis));
+
+        // the message matches the template of `ExpectButEof` too
+        let eof = Key::IdentButEof.format_in(Lang::En, &[]);
+        assert!(Key::ExpectButEof.parse(&untag(&eof)).is_some());
+        assert_eq!(Key::of(&eof), Some(Key::IdentButEof));
+
+        // tags of arguments are removed too
+        let note = msg!(Note, note = msg!(ExpectStatement));
+        assert_eq!(Key::of(&note), Some(Key::Note));
+        assert_eq!(untag(&note), "note: expect a statement");
+    }
+}
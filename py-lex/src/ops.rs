@@ -27,8 +27,8 @@ macro_rules! parse_unit_impl {
                 }
 
                 // use peek here to avoid mutable borrow
-                let Some(next) = p.peek() else {
-                    let msg = format!("expect a `{}`, but there are no token left", stringify!($enum_name));
+                let Some(next) = $crate::Tokens::peek_token(p) else {
+                    let msg = $crate::msg!(ExpectKindButEof, kind = stringify!($enum_name));
                     return p.unmatch(msg)
                 };
 
@@ -36,12 +36,12 @@ macro_rules! parse_unit_impl {
                 match MAP.with(|map| map.get(&**next).copied()) {
                     Some(item) => {
                         // and use next here to actually use a token
-                        p.next();
+                        $crate::Tokens::next_token(p);
                         Ok(item)
                     },
                     None => {
                         p.unmatch(
-                            format!("{} matched non of {}", &**next , stringify!($enum_name)),
+                            $crate::msg!(MatchesNone, got = &**next, expect = stringify!($enum_name)),
                         )
                     }
                 }
@@ -122,6 +122,16 @@ macro_rules! operators {
                 self.associativity().cost()
             }
 
+            /// the operator whose keyword is the word
+            pub fn from_keyword(word: &str) -> Option<Self> {
+                match word {
+                    $(
+                        $($string => Some(Self::$var),)*
+                    )*
+                    _ => None,
+                }
+            }
+
             /// return the priority of the symbol
             ///
             /// samller number means higher priority
@@ -230,3 +240,13 @@ operators! {
         "he2"       -> BracketR   : None 0
     }
 }
+
+impl Operators {
+    /// whether the operator could be defined for custom types, by a function like `yun4suan4 jia1`
+    pub fn overloadable(&self) -> bool {
+        !matches!(
+            self.op_ty(),
+            OperatorTypes::SpecialOperator | OperatorTypes::StructOperator
+        )
+    }
+}
@@ -0,0 +1,113 @@
+use crate::Token;
+use terl::{Buffer, Span, WithSpan};
+
+/// lookup table from spans in [`Buffer<Token>`] to lines and columns in source file
+///
+/// spans of parse units are spans in [`Buffer<Token>`], so [`Buffer<char>`] is needed
+/// to know where they actually are
+///
+/// a map could be of many source files, see [`SourceMap::append`]
+#[derive(Debug, Clone)]
+pub struct SourceMap {
+    /// names of source files, with indexes of their first tokens
+    files: Vec<(usize, String)>,
+    /// (line, column) of the first char of each token, both of them start from 1
+    tokens: Vec<(usize, usize)>,
+}
+
+impl SourceMap {
+    pub fn new(chars: &Buffer<char>, tokens: &Buffer<Token>) -> Self {
+        let mut line = 1;
+        let mut line_start = 0;
+        let mut idx = 0;
+
+        let tokens = tokens
+            .iter()
+            .map(|token| {
+                let start = token.get_span().start;
+                while idx < start {
+                    if chars[idx] == '\n' {
+                        line += 1;
+                        line_start = idx + 1;
+                    }
+                    idx += 1;
+                }
+                (line, start - line_start + 1)
+            })
+            .collect();
+
+        Self {
+            files: vec![(0, chars.name().to_owned())],
+            tokens,
+        }
+    }
+
+    /// append the map of other source files, whose tokens are after tokens in this map
+    pub fn append(&mut self, map: SourceMap) {
+        let offset = self.tokens.len();
+        let files = map.files.into_iter();
+        self.files
+            .extend(files.map(|(start, name)| (start + offset, name)));
+        self.tokens.extend(map.tokens);
+    }
+
+    /// name of the source file where the [`Span`] starts at
+    pub fn name(&self, span: Span) -> &str {
+        let idx = self
+            .files
+            .partition_point(|(start, _)| *start <= span.start);
+        &self.files[idx.saturating_sub(1)].1
+    }
+
+    /// return the line and the column where the [`Span`] starts at
+    ///
+    /// spans out of range(like the span of `EOF`) are treated as at the last token
+    pub fn line_col(&self, span: Span) -> (usize, usize) {
+        match self.tokens.get(span.start) {
+            Some(loc) => *loc,
+            None => self.tokens.last().copied().unwrap_or((1, 1)),
+        }
+    }
+
+    /// return the line where the [`Span`] starts at
+    #[inline]
+    pub fn line(&self, span: Span) -> usize {
+        self.line_col(span).0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use terl::*;
+
+    #[test]
+    fn lines() {
+        let src = "han2\n  fan3 1 fen1\n\njie2";
+        let buffer = Buffer::new("lines.py1".to_owned(), src.chars().collect());
+        let (chars, parser) = Parser::new(buffer)
+            .process(|p| {
+                let mut tokens = vec![];
+                while let Some(token) = p.parse::<Token>().apply(mapper::Try)? {
+                    tokens.push(token);
+                }
+                Ok(tokens)
+            })
+            .unwrap();
+        let map = SourceMap::new(&chars, parser.buffer());
+
+        assert_eq!(map.name(Span::new(0, 1)), "lines.py1");
+        assert_eq!(map.line_col(Span::new(0, 1)), (1, 1));
+        assert_eq!(map.line_col(Span::new(1, 2)), (2, 3));
+        assert_eq!(map.line_col(Span::new(3, 4)), (2, 10));
+        assert_eq!(map.line(Span::new(4, 5)), 4);
+
+        let mut files = map.clone();
+        let mut other = map;
+        other.files[0].1 = "other.py1".to_owned();
+        files.append(other);
+        assert_eq!(files.name(Span::new(4, 5)), "lines.py1");
+        assert_eq!(files.name(Span::new(6, 7)), "other.py1");
+        assert_eq!(files.line_col(Span::new(6, 7)), (2, 3));
+    }
+}
@@ -4,9 +4,16 @@ crate::reverse_parse_keywords! {
         "mei2"     -> Enum,
         "lian2"    -> Union,
         "jie2gou4" -> Struct,
+        "jie1kou3" -> Interface,
     }
     keywords Symbol {
         "dao3chu1" -> Export,
+        "gong1kai1" -> Public,
+        "fan4xing2" -> Generic,
+        "shi2xian4" -> Implements,
+        "yun4suan4" -> Operator,
+        "bi4bao1"   -> Closure,
+        "mo2kuai4"  -> Module,
 
         "ya1"      -> FnCallL,
         "ru4"      -> FnCallR,
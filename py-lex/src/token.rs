@@ -13,6 +13,39 @@ impl Token {
             span,
         }
     }
+
+    /// the empty token after tokens of a source file, so that tokens of several files
+    /// could be parsed by one parser, see [`Tokens`]
+    ///
+    /// `span` is the end of the file
+    pub fn end_of_file(span: Span) -> Self {
+        Self::new("", span)
+    }
+
+    /// tokens of source code are never empty
+    pub fn is_end_of_file(&self) -> bool {
+        self.string.is_empty()
+    }
+}
+
+/// [`Parser::peek`] and [`Parser::next`] which stop at [`Token::end_of_file`], like at
+/// the end of tokens
+pub trait Tokens {
+    fn peek_token(&self) -> Option<&Token>;
+
+    fn next_token(&mut self) -> Option<&Token>;
+}
+
+impl Tokens for Parser<Token> {
+    #[inline]
+    fn peek_token(&self) -> Option<&Token> {
+        self.peek().filter(|token| !token.is_end_of_file())
+    }
+
+    #[inline]
+    fn next_token(&mut self) -> Option<&Token> {
+        self.next_if(|token| !token.is_end_of_file())
+    }
 }
 
 impl std::ops::Deref for Token {
@@ -56,7 +89,7 @@ impl ParseUnit<char> for Token {
 
         // return unmatch if string is empty
         if string.is_empty() {
-            return p.unmatch("empty string");
+            return p.unmatch(crate::msg!(EmptyToken));
         }
 
         Ok(Token {
@@ -71,9 +104,9 @@ impl ParseUnit<Token> for Token {
 
     #[inline]
     fn parse(p: &mut Parser<Token>) -> Result<Self::Target, ParseError> {
-        match p.next().cloned() {
+        match p.next_token().cloned() {
             Some(token) => Ok(token),
-            None => p.unmatch("no token left"),
+            None => p.unmatch(crate::msg!(NoTokenLeft)),
         }
     }
 }
@@ -81,6 +114,21 @@ impl ParseUnit<Token> for Token {
 impl Source for Token {
     type HandleErrorWith<'b> = (&'b Buffer<char>, &'b Buffer<Token>);
 
+    fn handle_message<S>(
+        with: &Self::HandleErrorWith<'_>,
+        buffer: &mut S,
+        message: Message,
+    ) -> std::fmt::Result
+    where
+        S: std::fmt::Write,
+    {
+        match message {
+            Message::Location(span) => Self::handle_location(with, buffer, span, ""),
+            Message::Text(reason) => writeln!(buffer, "{}", crate::messages::untag(&reason)),
+            Message::Rich(reason, span) => Self::handle_location(with, buffer, span, &reason),
+        }
+    }
+
     #[inline]
     fn handle_location<S>(
         with: &Self::HandleErrorWith<'_>,
@@ -93,7 +141,7 @@ impl Source for Token {
     {
         let (chars, tokens) = with;
         let loc = tokens[loc.start].get_span() + tokens[loc.end - 1].get_span();
-        char::handle_location(chars, buffer, loc, msg)
+        char::handle_location(chars, buffer, loc, &crate::messages::untag(msg))
     }
 }
 
@@ -14,5 +14,10 @@ crate::front_parse_keywords! {
         "yin3"     -> Reference,
         "she4"     -> Const,
         "zhi3"     -> Pointer,
+        "han2zhi3" -> Function,
+    }
+    // used in place of a type, the type will be inferred
+    keywords TypePlaceholder {
+        "zi4dong4" -> Auto,
     }
 }
@@ -0,0 +1,78 @@
+//! benchmarks of type inference over generated programs, run them with `cargo bench`
+extern crate test;
+
+use py_ast::semantic::{Defines, Generate};
+use test::Bencher;
+
+/// a literal-headed sum, every literal has candidates of all integer types until `x` is
+/// added at the end
+fn long_expression(terms: usize) -> String {
+    let sum = (0..terms)
+        .map(|term| term.to_string())
+        .collect::<Vec<_>>()
+        .join(" jia1 ");
+    format!("zheng3 zhu3 can1 zheng3 x jie2 han2 fan3 {sum} jia1 x fen1 jie2")
+}
+
+/// calls of an overloaded function nested in each other, each argument is a sum with a
+/// literal
+fn nested_calls(depth: usize) -> String {
+    let mut call = "x".to_owned();
+    for idx in 0..depth {
+        call = format!("ya1 {idx} jia1 {call} ru4 f");
+    }
+    format!(
+        "zheng3 f can1 zheng3 x jie2 han2 fan3 x fen1 jie2
+         fu2 f can1 fu2 x jie2 han2 fan3 x fen1 jie2
+         zheng3 zhu3 can1 zheng3 x jie2 han2 fan3 {call} fen1 jie2"
+    )
+}
+
+/// a chain of variables, each of them is defined with the previous one and literals
+fn variable_chain(vars: usize) -> String {
+    let mut body = "zheng3 v0 wei2 x fen1".to_owned();
+    for idx in 1..vars {
+        body += &format!(" zheng3 v{idx} wei2 {idx} cheng2 2 jia1 v{} fen1", idx - 1);
+    }
+    format!(
+        "zheng3 zhu3 can1 zheng3 x jie2 han2 {body} fan3 v{} fen1 jie2",
+        vars - 1
+    )
+}
+
+fn bench_declare(b: &mut Bencher, src: String) {
+    let (_, mut parser) = crate::tokenize("bench.py1".to_owned(), src);
+    let (items, errors) = py_ast::parse::parse_items(&mut parser);
+    assert!(errors.is_empty());
+
+    let generate = || {
+        let mut defines: Defines = Default::default();
+        defines.generate(items.as_slice()).is_ok()
+    };
+    assert!(
+        generate(),
+        "types in the generated program should be inferred"
+    );
+    b.iter(generate);
+}
+
+macro_rules! benches {
+    ($($name:ident: $program:ident($size:literal);)*) => {$(
+        #[bench]
+        fn $name(b: &mut Bencher) {
+            bench_declare(b, $program($size));
+        }
+    )*};
+}
+
+benches! {
+    long_expression_50: long_expression(50);
+    long_expression_200: long_expression(200);
+    long_expression_800: long_expression(800);
+    nested_calls_10: nested_calls(10);
+    nested_calls_50: nested_calls(50);
+    nested_calls_100: nested_calls(100);
+    variable_chain_50: variable_chain(50);
+    variable_chain_200: variable_chain(200);
+    variable_chain_800: variable_chain(800);
+}
@@ -0,0 +1,337 @@
+use std::cell::RefCell;
+
+use clap::ValueEnum;
+use py_lex::messages::{self, Key, Lang};
+use serde::Serialize;
+use terl::{Buffer, Source, Span};
+
+use crate::sources::Sources;
+
+/// stable codes of errors, they are chosen by the catalog key of the first message of errors,
+/// so they are same in all languages
+///
+/// codes must never be changed or reused, new codes should be appended
+const CODES: &[(&str, Key)] = &[
+    // syntax errors
+    ("E0001", Key::ExpectFnDefine),
+    ("E0002", Key::ExpectStatement),
+    ("E0003", Key::UnclosedBracket),
+    ("E0004", Key::ExpectButEof),
+    ("E0004", Key::ExpectKindButEof),
+    ("E0004", Key::NoTokenLeft),
+    ("E0004", Key::IdentButEof),
+    ("E0005", Key::IdentStartsWithDigit),
+    ("E0005", Key::KeywordAsIdent),
+    ("E0006", Key::InvalidEscape),
+    ("E0006", Key::InvalidChar),
+    ("E0006", Key::UnfinishedEscape),
+    ("E0007", Key::ExpectButGot),
+    ("E0007", Key::ExpectNumber),
+    ("E0007", Key::UnexpectedToken),
+    ("E0007", Key::ExpectThis),
+    ("E0008", Key::LiteralTooLarge),
+    // types
+    ("E0101", Key::ExpectSign),
+    ("E0101", Key::SignedFloat),
+    ("E0101", Key::UnsupportedSign),
+    ("E0101", Key::UnsupportedWidth),
+    ("E0101", Key::UnsupportedDecorator),
+    ("E0101", Key::UnsupportedIntWidth),
+    ("E0101", Key::UnsupportedFloatWidth),
+    // definitions and uses
+    ("E0201", Key::DoubleDefine),
+    ("E0202", Key::UndefinedFn),
+    ("E0203", Key::UndefinedVar),
+    ("E0204", Key::AssignImmutable),
+    ("E0205", Key::NeverReturn),
+    ("E0206", Key::GenericExport),
+    ("E0207", Key::GenericInferredReturn),
+    ("E0208", Key::UndefinedType),
+    ("E0209", Key::DoubleClass),
+    ("E0210", Key::DoubleField),
+    ("E0211", Key::RecursiveClass),
+    ("E0212", Key::ExpectReceiver),
+    ("E0213", Key::GenericMethod),
+    ("E0214", Key::NotAClass),
+    ("E0215", Key::NoField),
+    ("E0216", Key::NoMethod),
+    ("E0217", Key::OperatorOnClass),
+    ("E0218", Key::UndefinedInterface),
+    ("E0219", Key::DoubleInterface),
+    ("E0220", Key::NotImplemented),
+    ("E0221", Key::NotOverloadable),
+    ("E0222", Key::OperatorParamLen),
+    ("E0223", Key::OperatorInferredReturn),
+    ("E0224", Key::GenericFnValue),
+    ("E0225", Key::InferredFnValue),
+    ("E0226", Key::AmbiguousFnValue),
+    ("E0227", Key::IndirectCallArgLen),
+    ("E0228", Key::ClosureNotBound),
+    ("E0229", Key::ClosureAsValue),
+    ("E0230", Key::ClosureArgLen),
+    ("E0231", Key::AmbiguousCapture),
+    ("E0232", Key::DoubleModule),
+    ("E0233", Key::NotInModule),
+    ("E0234", Key::UndefinedModule),
+    ("E0235", Key::PrivateItem),
+    ("E0236", Key::StringLiteral),
+    ("E0237", Key::ArrayLiteral),
+    // type inference
+    ("E0301", Key::CantInferType),
+    ("E0302", Key::RecursiveInference),
+    ("E0303", Key::NoReturnValue),
+    ("E0304", Key::AmbiguousVarType),
+    ("E0305", Key::LiteralOverflow),
+    ("E0306", Key::NoMatchingOverload),
+    ("E0307", Key::AmbiguousCall),
+];
+
+/// code of errors which are not in [`CODES`]
+const UNKNOWN_CODE: &str = "E0000";
+
+pub(crate) fn code(key: Option<Key>) -> &'static str {
+    key.and_then(|key| CODES.iter().find(|(_, k)| *k == key))
+        .map(|(code, _)| *code)
+        .unwrap_or(UNKNOWN_CODE)
+}
+
+/// language of messages
+#[derive(ValueEnum, Clone, Copy, Default)]
+pub enum Language {
+    #[default]
+    En,
+    /// chinese
+    Zh,
+}
+
+impl From<Language> for Lang {
+    fn from(language: Language) -> Self {
+        match language {
+            Language::En => Lang::En,
+            Language::Zh => Lang::Zh,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Default)]
+pub enum ErrorFormat {
+    /// rendered text with source code
+    #[default]
+    Text,
+    /// a json object per line, see [`Diagnostic`]
+    Json,
+}
+
+/// a message collected by [`Collector`], with its location and the key it's made with
+pub(crate) type Collected = (Option<Span>, Option<Key>, String);
+
+/// collect messages of [`terl::Error`] with their locations, instead of formatting them
+pub(crate) struct Collector;
+
+impl Collector {
+    /// messages of the error in order, locations are spans of tokens
+    pub(crate) fn collect(error: terl::Error) -> Vec<Collected> {
+        let messages = RefCell::default();
+        Self::handle_error(&messages, error);
+        messages.into_inner()
+    }
+}
+
+impl Source for Collector {
+    type HandleErrorWith<'b> = RefCell<Vec<Collected>>;
+
+    fn handle_message<S>(
+        with: &Self::HandleErrorWith<'_>,
+        _: &mut S,
+        message: terl::Message,
+    ) -> std::fmt::Result
+    where
+        S: std::fmt::Write,
+    {
+        let (at, text) = match message {
+            terl::Message::Location(at) => (Some(at), String::new()),
+            terl::Message::Text(text) => (None, text),
+            terl::Message::Rich(text, at) => (Some(at), text),
+        };
+        let key = Key::of(&text);
+        with.borrow_mut().push((at, key, messages::untag(&text)));
+        Ok(())
+    }
+
+    fn handle_location<S>(
+        with: &Self::HandleErrorWith<'_>,
+        buffer: &mut S,
+        loc: Span,
+        msg: &str,
+    ) -> std::fmt::Result
+    where
+        S: std::fmt::Write,
+    {
+        Self::handle_message(with, buffer, terl::Message::Rich(msg.to_owned(), loc))
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+}
+
+/// a location in source file, lines and columns start from 1
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct DiagnosticSpan {
+    pub file: String,
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub line_start: usize,
+    pub column_start: usize,
+    pub line_end: usize,
+    pub column_end: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// an error in machine-readable form
+#[derive(Serialize, Debug)]
+pub struct Diagnostic {
+    pub code: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    pub primary: Option<DiagnosticSpan>,
+    /// other locations which are related to the error
+    pub secondary: Vec<DiagnosticSpan>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(sources: &Sources, error: terl::Error) -> Self {
+        let mut messages = Collector::collect(error).into_iter();
+        let (primary, key, message) = messages.next().unwrap_or_default();
+
+        let mut secondary = vec![];
+        let mut notes = vec![];
+        for (at, _, message) in messages {
+            match at {
+                Some(at) => {
+                    let label = Some(message).filter(|message| !message.is_empty());
+                    secondary.push(DiagnosticSpan::new(sources, at, label));
+                }
+                None => {
+                    let note = Key::Note.parse(&message).map_or(&*message, |args| args[0]);
+                    notes.push(note.trim().to_owned());
+                }
+            }
+        }
+
+        Self {
+            code: code(key),
+            severity: Severity::Error,
+            primary: primary.map(|at| DiagnosticSpan::new(sources, at, None)),
+            message,
+            secondary,
+            notes,
+        }
+    }
+}
+
+impl DiagnosticSpan {
+    /// spans at the end of a file (like the span of `EOF`) are treated as at the end of it
+    fn new(sources: &Sources, at: Span, label: Option<String>) -> Self {
+        let (chars, at) = sources.locate(at);
+        let (byte_start, line_start, column_start) = Self::locate(chars, at.start);
+        let (byte_end, line_end, column_end) = Self::locate(chars, at.end);
+        Self {
+            file: chars.name().to_owned(),
+            byte_start,
+            byte_end,
+            line_start,
+            column_start,
+            line_end,
+            column_end,
+            label,
+        }
+    }
+
+    /// byte offset, line and column of the char
+    fn locate(chars: &Buffer<char>, at: usize) -> (usize, usize, usize) {
+        let (mut byte, mut line, mut column) = (0, 1, 1);
+        for c in chars.iter().take(at) {
+            byte += c.len_utf8();
+            if *c == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+        (byte, line, column)
+    }
+}
+
+/// print the error to stderr in the format
+pub fn report(format: ErrorFormat, sources: &Sources, error: terl::Error) {
+    match format {
+        ErrorFormat::Text => eprintln!("{}", Sources::handle_error(sources, error)),
+        ErrorFormat::Json => {
+            let diagnostic = Diagnostic::new(sources, error);
+            eprintln!("{}", serde_json::to_string(&diagnostic).unwrap());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_diagnostic() {
+        let src =
+            "zheng3 f can1 jie2 han2 fan3 0 fen1 jie2\nzheng3 f can1 jie2 han2 fan3 1 fen1 jie2";
+        let (sources, items) = parse("diagnostic.py1", src);
+
+        use py_ast::semantic::{Defines, Generate};
+        let mut defines: Defines = Default::default();
+        let errors = crate::semantic_errors(defines.generate(items.as_slice()).unwrap_err());
+        let error = errors.into_iter().next().unwrap();
+        let diagnostic = Diagnostic::new(&sources, error);
+
+        assert_eq!(diagnostic.code, "E0201");
+        assert_eq!(diagnostic.message, "double define for function f");
+        let primary = diagnostic.primary.unwrap();
+        assert_eq!((primary.line_start, primary.column_start), (2, 1));
+        assert_eq!(primary.byte_start, 41);
+        assert_eq!(diagnostic.secondary.len(), 1);
+        assert_eq!(diagnostic.secondary[0].line_start, 1);
+        assert_eq!(diagnostic.notes.len(), 1);
+    }
+
+    #[test]
+    fn codes_of_same_messages() {
+        let code_of = |message: String| {
+            let error = terl::Error::new(Span::new(0, 1), message);
+            code(Collector::collect(error)[0].1)
+        };
+        // both messages are `此处应为 `you3fu2` 或 `wu2fu2``
+        let expect = "you3fu2` 或 `wu2fu2";
+        let this = Key::ExpectThis.format_in(Lang::Zh, &[("expect", &expect)]);
+        assert_eq!(code_of(Key::ExpectSign.format_in(Lang::Zh, &[])), "E0101");
+        assert_eq!(code_of(this), "E0007");
+        assert_eq!(code_of("not a message".to_owned()), UNKNOWN_CODE);
+    }
+
+    /// parse the source, it must be parsed cleanly
+    fn parse(name: &str, src: &str) -> (Sources, Vec<py_ast::parse::Item>) {
+        let (chars, parser) = crate::tokenize(name.to_owned(), src.to_owned());
+        let sources = Sources::new(vec![(chars, parser.take_buffer())]);
+        let (items, errors) = sources.parse(py_ast::parse::parse_items).remove(0);
+        assert!(errors.is_empty());
+        (sources, items)
+    }
+
+    #[test]
+    fn keys_are_unique() {
+        for (idx, (_, key)) in CODES.iter().enumerate() {
+            assert!(CODES[..idx].iter().all(|(_, k)| k != key), "{key:?}");
+        }
+    }
+}
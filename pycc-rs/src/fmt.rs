@@ -0,0 +1,448 @@
+use std::{error::Error, path::PathBuf, process::exit};
+
+use clap::Args;
+use py_ast::parse::{
+    Class, ClassItem, CodeBlock, Comment, Interface, InterfaceItem, Item, Module, Statement,
+};
+use py_lex::{Token, PU};
+use terl::{Buffer, ResultMapperExt, Source, Span, WithSpan};
+
+/// format source files in place
+#[derive(Args)]
+pub struct Fmt {
+    #[arg(required = true)]
+    srcs: Vec<PathBuf>,
+    #[arg(
+        long,
+        help = "only check if files are formatted, exit with 1 if any of them is not"
+    )]
+    check: bool,
+}
+
+impl Fmt {
+    pub fn fmt(self) -> Result<(), Box<dyn Error>> {
+        let mut failed = false;
+        let mut unformatted = false;
+        for path in &self.srcs {
+            let src = std::fs::read_to_string(path)?;
+            let formatted = match format(path.to_string_lossy().to_string(), src.clone()) {
+                Ok(formatted) => formatted,
+                Err(error) => {
+                    eprintln!("{error}");
+                    failed = true;
+                    continue;
+                }
+            };
+            if formatted == src {
+                continue;
+            }
+            if self.check {
+                println!("{}", path.display());
+                unformatted = true;
+            } else {
+                std::fs::write(path, formatted)?;
+            }
+        }
+
+        if failed {
+            exit(-1);
+        }
+        if unformatted {
+            exit(1);
+        }
+        Ok(())
+    }
+}
+
+/// format the source code
+///
+/// source code which cant be parsed will not be formatted
+pub(crate) fn format(path: String, src: String) -> Result<String, String> {
+    let (chars, mut parser) = crate::tokenize(path.clone(), src);
+    let mut items = vec![];
+    while parser.peek().is_some() {
+        match parser.parse::<PU<Item>>().apply(terl::mapper::MustMatch) {
+            Ok(item) => items.push(item),
+            Err(error) => {
+                return Err(Token::handle_error(
+                    &(&chars, parser.buffer()),
+                    error.error(),
+                ))
+            }
+        }
+    }
+    let errors = items
+        .iter()
+        .flat_map(|item| item.errors())
+        .map(|error| Token::handle_error(&(&chars, parser.buffer()), error.clone()))
+        .collect::<Vec<_>>();
+    if !errors.is_empty() {
+        return Err(errors.join("\n"));
+    }
+
+    let mut formatter = Formatter {
+        chars: &chars,
+        tokens: parser.buffer(),
+        output: String::new(),
+        indent: 0,
+        line_start: true,
+    };
+    formatter.items(&items);
+    let formatted = formatter.output;
+
+    // only whitespace and symbols between tokens can be changed
+    let (_, reparsed) = crate::tokenize(path, formatted.clone());
+    let words = |tokens: &Buffer<Token>| -> Vec<String> {
+        tokens.iter().map(|token| token.string.clone()).collect()
+    };
+    if words(reparsed.buffer()) != words(parser.buffer()) {
+        return Err(
+            "the program is changed after formatting, this is a bug of formatter".to_owned(),
+        );
+    }
+    Ok(formatted)
+}
+
+struct Formatter<'s> {
+    chars: &'s Buffer<char>,
+    tokens: &'s Buffer<Token>,
+    output: String,
+    /// depth of blocks
+    indent: usize,
+    /// if nothing has been written to the current line
+    line_start: bool,
+}
+
+impl Formatter<'_> {
+    const INDENT: &'static str = "    ";
+
+    fn word(&mut self, word: &str) {
+        if self.line_start {
+            self.output += &Self::INDENT.repeat(self.indent);
+            self.line_start = false;
+        } else {
+            self.output.push(' ');
+        }
+        self.output += word;
+    }
+
+    fn newline(&mut self) {
+        self.output.push('\n');
+        self.line_start = true;
+    }
+
+    /// if there are blank lines before the token in source code
+    fn blank_before(&self, token: usize) -> bool {
+        if token == 0 {
+            return false;
+        }
+        let between = self.tokens[token - 1].get_span().end..self.tokens[token].get_span().start;
+        self.chars[between].iter().filter(|c| **c == '\n').count() > 1
+    }
+
+    fn tokens(&mut self, span: std::ops::Range<usize>) {
+        let tokens = self.tokens;
+        span.for_each(|idx| self.word(&tokens[idx]));
+    }
+
+    /// write tokens in the span, and blocks in it are formatted
+    fn tokens_with_blocks<'b>(
+        &mut self,
+        span: Span,
+        blocks: impl IntoIterator<Item = &'b CodeBlock>,
+    ) {
+        let mut written = span.start;
+        for block in blocks {
+            self.tokens(written..block.get_span().start);
+            self.block(block);
+            written = block.get_span().end;
+        }
+        self.tokens(written..span.end);
+    }
+
+    fn comment(&mut self, comment: &Comment) {
+        let text = self.chars[comment.text.start..comment.text.end]
+            .iter()
+            .collect::<String>();
+        self.word("shi4");
+        if !text.trim().is_empty() {
+            self.word(text.trim());
+        }
+        self.word("jie2");
+    }
+
+    /// `han2` starts a new line, except the one after `ze2`
+    fn block(&mut self, block: &CodeBlock) {
+        if !self.line_start && !self.output.ends_with(" ze2") {
+            self.newline();
+        }
+        let span = block.get_span();
+        self.tokens(span.start..span.start + 1);
+        self.newline();
+
+        self.indent += 1;
+        for (idx, stmt) in block.stmts.iter().enumerate() {
+            if idx != 0 && self.blank_before(stmt.get_span().start) {
+                self.newline();
+            }
+            self.statement(stmt);
+            self.newline();
+        }
+        self.indent -= 1;
+
+        self.tokens(span.end - 1..span.end);
+    }
+
+    fn statement(&mut self, stmt: &PU<Statement>) {
+        match &**stmt {
+            Statement::Comment(comment) => self.comment(comment),
+            Statement::CodeBlock(block) => self.block(block),
+            Statement::If(if_) => {
+                let blocks = if_.branches.iter().map(|branch| &branch.body);
+                let else_ = if_.else_.iter().map(|else_| &else_.block);
+                self.tokens_with_blocks(stmt.get_span(), blocks.chain(else_))
+            }
+            Statement::While(while_) => self.tokens_with_blocks(stmt.get_span(), [&while_.block]),
+            Statement::FnCallStmt(..)
+            | Statement::VarStoreStmt(..)
+            | Statement::VarDefineStmt(..)
+            | Statement::Return(..)
+            | Statement::Invalid(..) => {
+                let span = stmt.get_span();
+                self.tokens(span.start..span.end)
+            }
+        }
+    }
+
+    /// the head, like `lei4` and the name, is on its own line like signatures of functions,
+    /// and items in the body are like items in a file
+    ///
+    /// `separated` tells if the item should be followed by a blank line
+    fn body<I>(
+        &mut self,
+        span: Span,
+        head_end: usize,
+        items: &[PU<I>],
+        separated: impl Fn(&I) -> bool,
+        mut item: impl FnMut(&mut Self, &PU<I>),
+    ) {
+        self.tokens(span.start..head_end);
+        self.newline();
+        self.tokens(head_end..head_end + 1);
+        self.newline();
+
+        self.indent += 1;
+        for (idx, pu) in items.iter().enumerate() {
+            if idx != 0 && (separated(&items[idx - 1]) || self.blank_before(pu.get_span().start)) {
+                self.newline();
+            }
+            item(self, pu);
+            self.newline();
+        }
+        self.indent -= 1;
+
+        self.tokens(span.end - 1..span.end);
+    }
+
+    fn class(&mut self, span: Span, class: &Class) {
+        let head = class.implements.last().unwrap_or(&class.name);
+        let separated = |item: &ClassItem| matches!(item, ClassItem::FnDefine(..));
+        let head_end = head.get_span().end;
+        self.body(
+            span,
+            head_end,
+            &class.items,
+            separated,
+            |s, item| match &**item {
+                ClassItem::FnDefine(define) => {
+                    s.tokens_with_blocks(item.get_span(), [&define.codes])
+                }
+                ClassItem::FieldDefine(..) => {
+                    let span = item.get_span();
+                    s.tokens(span.start..span.end)
+                }
+                ClassItem::Comment(comment) => s.comment(comment),
+            },
+        );
+    }
+
+    fn interface(&mut self, span: Span, interface: &Interface) {
+        // the head ends with `jie2` after the name of the type implementing it
+        let head_end = interface.this.get_span().end + 1;
+        let items = &interface.items;
+        self.body(
+            span,
+            head_end,
+            items,
+            |_| false,
+            |s, item| match &**item {
+                InterfaceItem::FnSignDefine(..) => {
+                    let span = item.get_span();
+                    s.tokens(span.start..span.end)
+                }
+                InterfaceItem::Comment(comment) => s.comment(comment),
+            },
+        );
+    }
+
+    fn module(&mut self, span: Span, module: &Module) {
+        let head_end = module.name.get_span().end;
+        self.body(span, head_end, &module.items, Self::separated, Self::item);
+    }
+
+    /// items are separated by blank lines after function definitions, classes, interfaces
+    /// and modules
+    fn separated(item: &Item) -> bool {
+        !matches!(item, Item::Comment(..))
+    }
+
+    fn item(&mut self, item: &PU<Item>) {
+        match &**item {
+            Item::FnDefine(define) => self.tokens_with_blocks(item.get_span(), [&define.codes]),
+            Item::Class(class) => self.class(item.get_span(), class),
+            Item::Interface(interface) => self.interface(item.get_span(), interface),
+            Item::Module(module) => self.module(item.get_span(), module),
+            Item::Comment(comment) => self.comment(comment),
+        }
+    }
+
+    fn items(&mut self, items: &[PU<Item>]) {
+        for (idx, item) in items.iter().enumerate() {
+            if idx != 0
+                && (Self::separated(&items[idx - 1]) || self.blank_before(item.get_span().start))
+            {
+                self.newline();
+            }
+            self.item(item);
+            self.newline();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    const FORMATTED: &str = "\
+shi4 fibonacci, (with recursion) jie2
+zheng3 fib can1 zheng3 n jie2
+han2
+    ruo4 can1 n tong2 0 huo4 n tong2 1 jie2
+    han2
+        fan3 1 fen1
+    jie2 ze2 han2
+        shi4 jie2
+
+        fan3 ya1 n jian3 1 ru4 fib jia1 ya1 n jian3 2 ru4 fib fen1
+    jie2
+jie2
+
+zheng3 zhu3 can1 jie2
+han2
+    zheng3 i wei2 0 fen1
+    chong2 can1 i xiao3 10 jie2
+    han2
+        i wei2 i jia1 1 fen1
+    jie2
+    han2
+        fan3 ya1 i ru4 fib fen1
+    jie2
+jie2
+";
+
+    fn format(src: &str) -> String {
+        super::format("fmt-test.py1".to_owned(), src.to_owned()).unwrap()
+    }
+
+    #[test]
+    fn layout() {
+        let src = "shi4   fibonacci, (with recursion)
+          jie2 zheng3 fib can1 zheng3 n jie2 han2 ruo4 can1 n tong2 0 huo4 n tong2 1 jie2 han2
+        fan3 1 fen1 jie2 ze2 han2 shi4 jie2
+
+
+          fan3 ya1 n jian3 1 ru4 fib jia1 ya1 n jian3 2 ru4 fib fen1 jie2 jie2
+        zheng3 zhu3 can1 jie2 han2 zheng3 i wei2 0 fen1 chong2 can1 i xiao3 10 jie2
+        han2 i wei2 i jia1 1 fen1 jie2 han2 fan3 ya1 i ru4 fib fen1 jie2 jie2";
+        assert_eq!(format(src), FORMATTED);
+    }
+
+    #[test]
+    fn class() {
+        let src = "lei4 Dian3 han2 zheng3 x fen1 zheng3 y fen1
+        zheng3 he can1 Dian3 p jie2 han2 fan3 p de1 x jia1 p de1 y fen1 jie2 jie2";
+        let formatted = "\
+lei4 Dian3
+han2
+    zheng3 x fen1
+    zheng3 y fen1
+    zheng3 he can1 Dian3 p jie2
+    han2
+        fan3 p de1 x jia1 p de1 y fen1
+    jie2
+jie2
+";
+        assert_eq!(format(src), formatted);
+        assert_eq!(format(formatted), formatted);
+    }
+
+    #[test]
+    fn interface() {
+        let src = "jie1kou3 Ke3jia1 can1 T jie2 han2 T jia can1 T a fen1 T b jie2 fen1
+        T jian can1 T a fen1 T b jie2 fen1 jie2
+        lei4 Dian3 shi2xian4 Ke3jia1 han2 zheng3 x fen1 jie2";
+        let formatted = "\
+jie1kou3 Ke3jia1 can1 T jie2
+han2
+    T jia can1 T a fen1 T b jie2 fen1
+    T jian can1 T a fen1 T b jie2 fen1
+jie2
+
+lei4 Dian3 shi2xian4 Ke3jia1
+han2
+    zheng3 x fen1
+jie2
+";
+        assert_eq!(format(src), formatted);
+        assert_eq!(format(formatted), formatted);
+    }
+
+    #[test]
+    fn module() {
+        let src = "gong1kai1 mo2kuai4 shu4 han2 mo2kuai4 nei4 han2 jie2
+        gong1kai1 dao3chu1 zheng3 f can1 jie2 han2 fan3 1 fen1 jie2 jie2";
+        let formatted = "\
+gong1kai1 mo2kuai4 shu4
+han2
+    mo2kuai4 nei4
+    han2
+    jie2
+
+    gong1kai1 dao3chu1 zheng3 f can1 jie2
+    han2
+        fan3 1 fen1
+    jie2
+jie2
+";
+        assert_eq!(format(src), formatted);
+        assert_eq!(format(formatted), formatted);
+    }
+
+    #[test]
+    fn idempotent() {
+        assert_eq!(format(FORMATTED), FORMATTED);
+    }
+
+    #[test]
+    fn ast_unchanged() {
+        let src = FORMATTED.replace('\n', " ").replace("    ", "");
+        // without source map, so that lines of statements are not in ir
+        let ir = |src: String| {
+            use py_ast::semantic::{Defines, Generate};
+            let files = vec![("fmt-test.py1".to_owned(), src)];
+            let (_, ast) = crate::generate_ast(files, Default::default());
+            let mut defines: Defines = Default::default();
+            let ir = defines.generate(ast.as_slice()).ok().unwrap();
+            serde_json::to_string(&ir).unwrap()
+        };
+        assert_eq!(ir(src.clone()), ir(format(&src)));
+    }
+}
@@ -0,0 +1,227 @@
+use py_ast::semantic::Defines;
+use py_codegen_llvm::inkwell::{
+    execution_engine::{ExecutionEngine, FunctionLookupError, JitFunction},
+    targets::TargetData,
+};
+use py_ir::{
+    types::{PrimitiveType, TypeDefine},
+    value::{AssignValue, FnCall, Literal, Value},
+    FnDefine, Parameter, Return, Statements, VarDefine,
+};
+
+/// name of the function generated by [`entry`]
+pub const ENTRY: &str = "__pycc_entry";
+
+/// find the function by its name before mangling, and the count of its parameters
+///
+/// names of functions in modules are qualified, like `mo2kuai4 de1 han2shu4`
+pub fn find_fn<'i>(
+    defines: &Defines,
+    items: &'i [py_ir::Item],
+    name: &str,
+    args: usize,
+) -> Result<&'i FnDefine<Value>, String> {
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    let overloads = defines.defs.get_unmangled(&name).unwrap_or_default();
+    let mut candidates = overloads
+        .iter()
+        .filter(|overload| overload.params.len() == args);
+
+    let sign = match (candidates.next(), candidates.next()) {
+        (Some(overload), None) => &overload.sign,
+        (None, _) => return Err(format!("no function `{name}` takes {args} arguments")),
+        (Some(..), Some(..)) => {
+            return Err(format!("call of overloaded function `{name}` is ambiguous"))
+        }
+    };
+    let mangled = defines.mangler.mangle_fn(&name, sign);
+    items
+        .iter()
+        .find_map(|item| match item {
+            py_ir::Item::FnDefine(define) if define.name == mangled => Some(define),
+            _ => None,
+        })
+        .ok_or_else(|| format!("function `{name}` is not generated"))
+}
+
+fn primitive(ty: &TypeDefine) -> Result<PrimitiveType, String> {
+    ty.as_primitive()
+        .copied()
+        .ok_or_else(|| format!("values of type `{ty}` cant be passed between jit and host"))
+}
+
+/// width of the type in bits, pointer-sized integers are as wide as pointers of the target
+fn width(ty: PrimitiveType, target_data: &TargetData) -> usize {
+    match ty {
+        PrimitiveType::Isize | PrimitiveType::Usize => {
+            target_data.get_pointer_byte_size(None) as usize * 8
+        }
+        ty => ty.width(),
+    }
+}
+
+/// parse a command-line argument into a literal of the parameter's type
+fn argument(
+    param: &Parameter<TypeDefine>,
+    arg: &str,
+    target_data: &TargetData,
+) -> Result<Value, String> {
+    let ty = primitive(&param.ty)?;
+    let width = width(ty, target_data);
+    let invalid = || format!("`{arg}` is not a valid value of `{}`: {ty}", param.name);
+
+    let literal = match ty {
+        PrimitiveType::Bool => match arg {
+            "true" | "1" => Literal::Integer(1),
+            "false" | "0" => Literal::Integer(0),
+            _ => return Err(invalid()),
+        },
+        ty if ty.is_float() => Literal::Float(arg.parse().map_err(|_| invalid())?),
+        ty if ty.is_signed() => {
+            let int = arg.parse::<i128>().map_err(|_| invalid())?;
+            let half = 1i128.checked_shl(width as u32 - 1).unwrap_or(i128::MAX);
+            if width < 128 && !(-half..half).contains(&int) {
+                return Err(invalid());
+            }
+            // two's complement
+            Literal::Integer(int as u128)
+        }
+        _ => {
+            let int = arg.parse::<u128>().map_err(|_| invalid())?;
+            if width < 128 && int >> width != 0 {
+                return Err(invalid());
+            }
+            Literal::Integer(int)
+        }
+    };
+    Ok(Value::Literal(literal, ty))
+}
+
+/// generate a function named [`ENTRY`] which calls `callee` with `args`, and returns what
+/// the callee returned
+///
+/// so the callee can be called without knowing its signature when compiling pycc-rs. `args`
+/// are checked with the data layout of the target which the callee is compiled for
+pub fn entry(
+    callee: &FnDefine<Value>,
+    args: &[String],
+    target_data: &TargetData,
+) -> Result<FnDefine<Value>, String> {
+    primitive(&callee.ty)?;
+    if callee.params.len() != args.len() {
+        return Err(format!(
+            "{} arguments are expected, but {} were given",
+            callee.params.len(),
+            args.len()
+        ));
+    }
+    let args = callee
+        .params
+        .iter()
+        .zip(args)
+        .map(|(param, arg)| argument(param, arg, target_data))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let returned = "returned".to_owned();
+    let call = VarDefine {
+        ty: callee.ty.clone(),
+        name: returned.clone(),
+        init: Some(AssignValue::FnCall(FnCall {
+            fn_name: callee.name.clone(),
+            args,
+        })),
+        is_temp: false,
+    };
+    let ret = Return {
+        val: Some(Value::Variable(returned)),
+    };
+
+    Ok(FnDefine {
+        export: false,
+        ty: callee.ty.clone(),
+        name: ENTRY.to_owned(),
+        params: vec![],
+        body: Statements::from(vec![call.into(), ret.into()]),
+        template: None,
+    })
+}
+
+/// value returned from jit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Returned {
+    Bool(bool),
+    Signed(i128),
+    Unsigned(u128),
+    Float(f64),
+}
+
+impl Returned {
+    /// the value as an exit code of process
+    pub fn exit_code(&self) -> i32 {
+        match *self {
+            Returned::Bool(b) => b as i32,
+            Returned::Signed(i) => i as i32,
+            Returned::Unsigned(u) => u as i32,
+            Returned::Float(..) => 0,
+        }
+    }
+}
+
+impl std::fmt::Display for Returned {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Returned::Bool(v) => write!(f, "{v}"),
+            Returned::Signed(v) => write!(f, "{v}"),
+            Returned::Unsigned(v) => write!(f, "{v}"),
+            Returned::Float(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+/// call a function without parameters, like the one generated by [`entry`]
+///
+/// # Safety
+///
+/// the function must not have any parameter, and `ty` must be its return type
+pub unsafe fn call(
+    ee: &ExecutionEngine,
+    name: &str,
+    ty: PrimitiveType,
+) -> Result<Returned, FunctionLookupError> {
+    macro_rules! call {
+        ($ret:ty) => {{
+            let function: JitFunction<unsafe extern "C" fn() -> $ret> = ee.get_function(name)?;
+            function.call()
+        }};
+    }
+
+    let returned = match ty {
+        // high bits of i1 are undefined
+        PrimitiveType::Bool => Returned::Bool(call!(u8) & 1 != 0),
+        PrimitiveType::I8 => Returned::Signed(call!(i8) as _),
+        PrimitiveType::U8 => Returned::Unsigned(call!(u8) as _),
+        PrimitiveType::I16 => Returned::Signed(call!(i16) as _),
+        PrimitiveType::U16 => Returned::Unsigned(call!(u16) as _),
+        PrimitiveType::I32 => Returned::Signed(call!(i32) as _),
+        PrimitiveType::U32 => Returned::Unsigned(call!(u32) as _),
+        PrimitiveType::I64 => Returned::Signed(call!(i64) as _),
+        PrimitiveType::U64 => Returned::Unsigned(call!(u64) as _),
+        PrimitiveType::Isize | PrimitiveType::Usize => {
+            let signed = ty.is_signed();
+            match width(ty, ee.get_target_data()) {
+                16 if signed => Returned::Signed(call!(i16) as _),
+                16 => Returned::Unsigned(call!(u16) as _),
+                32 if signed => Returned::Signed(call!(i32) as _),
+                32 => Returned::Unsigned(call!(u32) as _),
+                64 if signed => Returned::Signed(call!(i64) as _),
+                64 => Returned::Unsigned(call!(u64) as _),
+                width => unreachable!("pointers are never {width} bits wide"),
+            }
+        }
+        PrimitiveType::I128 => Returned::Signed(call!(i128)),
+        PrimitiveType::U128 => Returned::Unsigned(call!(u128)),
+        PrimitiveType::F32 => Returned::Float(call!(f32) as _),
+        PrimitiveType::F64 => Returned::Float(call!(f64)),
+    };
+    Ok(returned)
+}
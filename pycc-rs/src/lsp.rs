@@ -0,0 +1,472 @@
+use std::{collections::HashMap, error::Error};
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+        Notification as NotificationTrait, PublishDiagnostics,
+    },
+    request::{Completion, GotoDefinition, HoverRequest, Request as RequestTrait},
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionResponse, Diagnostic,
+    DiagnosticSeverity, GotoDefinitionResponse, Hover, HoverContents, HoverProviderCapability,
+    Location, MarkupContent, MarkupKind, NumberOrString, OneOf, Position, PublishDiagnosticsParams,
+    Range, ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind, Uri,
+};
+use py_ast::semantic::{Defines, Generate, Index, Symbol, VarSymbol};
+use py_lex::Token;
+use terl::{Buffer, Span, WithSpan};
+
+use crate::diagnostic::{self, Collector};
+
+/// names of functions, classes and methods defined in the item, functions in modules are
+/// with their names in the module
+fn callables(item: &py_ast::parse::Item) -> Vec<String> {
+    match item {
+        py_ast::parse::Item::FnDefine(define) => vec![define.name.to_string()],
+        // methods are called with their names too
+        py_ast::parse::Item::Class(class) => {
+            let methods = class.methods().map(|method| method.name.to_string());
+            std::iter::once(class.name.to_string())
+                .chain(methods)
+                .collect()
+        }
+        py_ast::parse::Item::Module(module) => module
+            .items
+            .iter()
+            .flat_map(|item| callables(item))
+            .collect(),
+        py_ast::parse::Item::Interface(..) | py_ast::parse::Item::Comment(..) => vec![],
+    }
+}
+
+/// a source file opened in the editor, and the result of analyzing it
+struct Document {
+    chars: Buffer<char>,
+    tokens: Buffer<Token>,
+    /// index of the first char of each line
+    lines: Vec<usize>,
+    diagnostics: Vec<Diagnostic>,
+    index: Index,
+    /// names of functions, classes and methods defined in the file, which can be called
+    functions: Vec<String>,
+}
+
+impl Document {
+    fn new(name: String, text: String) -> Self {
+        let (chars, mut parser) = crate::tokenize(name, text);
+        let (items, mut errors) = py_ast::parse::parse_items(&mut parser);
+
+        let mut functions = items.iter().flat_map(callables).collect::<Vec<_>>();
+        // functions defined for operators are called by operators, not by their names
+        functions.retain(|name| py_lex::ops::Operators::from_keyword(name).is_none());
+        functions.sort_unstable();
+        functions.dedup();
+
+        let defines: Defines = Default::default();
+        let mut defines = defines.with_index();
+        if let Err(semantic_errors) = defines.generate(items.as_slice()) {
+            errors.extend(crate::semantic_errors(semantic_errors));
+        }
+        let index = defines.take_index().unwrap_or_default();
+
+        let lines = std::iter::once(0)
+            .chain(
+                (0..chars.len())
+                    .filter(|idx| chars[*idx] == '\n')
+                    .map(|idx| idx + 1),
+            )
+            .collect();
+
+        let mut document = Self {
+            chars,
+            tokens: parser.take_buffer(),
+            lines,
+            diagnostics: vec![],
+            index,
+            functions,
+        };
+        document.diagnostics = errors
+            .into_iter()
+            .map(|error| document.diagnostic(error))
+            .collect();
+        document
+    }
+
+    fn position(&self, offset: usize) -> Position {
+        let line = self.lines.partition_point(|start| *start <= offset) - 1;
+        let character: usize = (self.lines[line]..offset)
+            .map(|idx| self.chars[idx].len_utf16())
+            .sum();
+        Position::new(line as u32, character as u32)
+    }
+
+    fn offset(&self, position: Position) -> usize {
+        let Some(mut offset) = self.lines.get(position.line as usize).copied() else {
+            return self.chars.len();
+        };
+        let mut character = 0;
+        while offset < self.chars.len()
+            && self.chars[offset] != '\n'
+            && character < position.character as usize
+        {
+            character += self.chars[offset].len_utf16();
+            offset += 1;
+        }
+        offset
+    }
+
+    /// index of the token at the offset, or the token after it
+    fn token(&self, offset: usize) -> usize {
+        self.tokens
+            .partition_point(|token| token.get_span().end < offset)
+    }
+
+    /// range of the span in [`Buffer<Token>`]
+    ///
+    /// spans out of range(like the span of `EOF`) are treated as at the end of the last token
+    fn range(&self, span: Span) -> Range {
+        let Some(last) = self.tokens.len().checked_sub(1) else {
+            return Range::default();
+        };
+        if span.start > last {
+            let end = self.position(self.tokens[last].get_span().end);
+            return Range::new(end, end);
+        }
+        let start = self.tokens[span.start].get_span().start;
+        let end = self.tokens[(span.end.max(span.start + 1) - 1).min(last)]
+            .get_span()
+            .end;
+        Range::new(self.position(start), self.position(end))
+    }
+
+    fn diagnostic(&self, error: terl::Error) -> Diagnostic {
+        let messages = Collector::collect(error);
+        let code = messages.first().map(|(_, key, _)| diagnostic::code(*key));
+
+        let range = messages
+            .iter()
+            .find_map(|(at, ..)| at.map(|at| self.range(at)))
+            .unwrap_or_default();
+        let message = messages
+            .into_iter()
+            .map(|(.., message)| message)
+            .filter(|message| !message.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Diagnostic {
+            range,
+            severity: Some(DiagnosticSeverity::ERROR),
+            code: code.map(|code| NumberOrString::String(code.to_owned())),
+            source: Some("pycc-rs".to_owned()),
+            message,
+            ..Default::default()
+        }
+    }
+
+    /// the token under the cursor, and the symbol it refers to
+    fn symbol(&self, position: Position) -> Option<(usize, &Symbol)> {
+        let offset = self.offset(position);
+        let token = self.token(offset);
+        if self.tokens.get(token)?.get_span().start > offset {
+            return None;
+        }
+        Some((token, self.index.reference(token)?))
+    }
+
+    fn hover(&self, position: Position) -> Option<Hover> {
+        let (token, symbol) = self.symbol(position)?;
+        let value = match symbol {
+            Symbol::Fn(overload) => overload.to_string(),
+            Symbol::Var(var) => var_detail(var),
+        };
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```\n{value}\n```"),
+            }),
+            range: Some(self.range(Span::new(token, token + 1))),
+        })
+    }
+
+    fn definition(&self, position: Position) -> Option<Range> {
+        let define = match self.symbol(position)?.1 {
+            Symbol::Fn(overload) => overload.sign_span,
+            Symbol::Var(var) => var.define,
+        };
+        Some(self.range(define))
+    }
+
+    fn completion(&self, position: Position) -> Vec<CompletionItem> {
+        let token = self.token(self.offset(position));
+        let item = |label: &str, kind| CompletionItem {
+            label: label.to_owned(),
+            kind: Some(kind),
+            ..Default::default()
+        };
+
+        let variables = self.index.visibles(token).map(|var| CompletionItem {
+            detail: Some(var_detail(var)),
+            ..item(&var.name, CompletionItemKind::VARIABLE)
+        });
+        let functions = self
+            .functions
+            .iter()
+            .map(|name| item(name, CompletionItemKind::FUNCTION));
+        let keywords = py_ast::parse::keywords()
+            .into_iter()
+            .map(|keyword| item(keyword, CompletionItemKind::KEYWORD));
+
+        variables.chain(functions).chain(keywords).collect()
+    }
+}
+
+fn var_detail(var: &VarSymbol) -> String {
+    match &var.ty {
+        Some(ty) => format!("{}: {ty}", var.name),
+        None => format!("{}: {{unknown}}", var.name),
+    }
+}
+
+#[derive(Default)]
+struct Server {
+    documents: HashMap<Uri, Document>,
+}
+
+impl Server {
+    fn handle<R, H>(&self, request: Request, handler: H) -> Response
+    where
+        R: RequestTrait,
+        H: FnOnce(&Self, R::Params) -> R::Result,
+    {
+        let id = request.id.clone();
+        match request.extract::<R::Params>(R::METHOD) {
+            Ok((id, params)) => Response::new_ok(id, handler(self, params)),
+            Err(error) => Response::new_err(id, ErrorCode::InvalidParams as _, error.to_string()),
+        }
+    }
+
+    fn request(&self, request: Request) -> Response {
+        match request.method.as_str() {
+            HoverRequest::METHOD => self.handle::<HoverRequest, _>(request, |server, params| {
+                let params = params.text_document_position_params;
+                let document = server.documents.get(&params.text_document.uri)?;
+                document.hover(params.position)
+            }),
+            GotoDefinition::METHOD => {
+                self.handle::<GotoDefinition, _>(request, |server, params| {
+                    let params = params.text_document_position_params;
+                    let uri = params.text_document.uri;
+                    let range = server.documents.get(&uri)?.definition(params.position)?;
+                    Some(GotoDefinitionResponse::Scalar(Location::new(uri, range)))
+                })
+            }
+            Completion::METHOD => self.handle::<Completion, _>(request, |server, params| {
+                let params = params.text_document_position;
+                let document = server.documents.get(&params.text_document.uri)?;
+                Some(CompletionResponse::Array(
+                    document.completion(params.position),
+                ))
+            }),
+            method => {
+                let reason = format!("unsupported request `{method}`");
+                Response::new_err(request.id, ErrorCode::MethodNotFound as _, reason)
+            }
+        }
+    }
+
+    /// update documents, and return diagnostics of the changed document
+    fn notify(&mut self, notification: Notification) -> Option<Notification> {
+        let (uri, text) = match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params = notification
+                    .extract::<<DidOpenTextDocument as NotificationTrait>::Params>(
+                        DidOpenTextDocument::METHOD,
+                    )
+                    .ok()?;
+                (params.text_document.uri, Some(params.text_document.text))
+            }
+            DidChangeTextDocument::METHOD => {
+                let params = notification
+                    .extract::<<DidChangeTextDocument as NotificationTrait>::Params>(
+                        DidChangeTextDocument::METHOD,
+                    )
+                    .ok()?;
+                // documents are synchronized fully, so the last change is the whole text
+                let text = params.content_changes.into_iter().last()?.text;
+                (params.text_document.uri, Some(text))
+            }
+            DidCloseTextDocument::METHOD => {
+                let params = notification
+                    .extract::<<DidCloseTextDocument as NotificationTrait>::Params>(
+                        DidCloseTextDocument::METHOD,
+                    )
+                    .ok()?;
+                (params.text_document.uri, None)
+            }
+            _ => return None,
+        };
+
+        let diagnostics = match text {
+            Some(text) => {
+                let document = Document::new(uri.as_str().to_owned(), text);
+                let diagnostics = document.diagnostics.clone();
+                self.documents.insert(uri.clone(), document);
+                diagnostics
+            }
+            None => {
+                self.documents.remove(&uri);
+                vec![]
+            }
+        };
+        let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+        Some(Notification::new(
+            PublishDiagnostics::METHOD.to_owned(),
+            params,
+        ))
+    }
+
+    fn serve(mut self, connection: Connection) -> Result<(), Box<dyn Error>> {
+        for message in &connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if connection.handle_shutdown(&request)? {
+                        break;
+                    }
+                    connection.sender.send(self.request(request).into())?;
+                }
+                Message::Notification(notification) => {
+                    if let Some(diagnostics) = self.notify(notification) {
+                        connection.sender.send(diagnostics.into())?;
+                    }
+                }
+                Message::Response(..) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn lsp() -> Result<(), Box<dyn Error>> {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions::default()),
+        ..Default::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    Server::default().serve(connection)?;
+    io_threads.join()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC: &str = "
+zheng3 jia can1 zheng3 x jie2
+han2
+    zheng3 y wei2 x jia1 1 fen1
+    fan3 y fen1
+jie2
+
+zheng3 zhu3 can1 jie2
+han2
+    fan3 ya1 41 ru4 jia fen1
+jie2
+";
+
+    fn document(src: &str) -> Document {
+        Document::new("lsp.py1".to_owned(), src.to_owned())
+    }
+
+    fn hover(document: &Document, line: u32, character: u32) -> Option<String> {
+        match document.hover(Position::new(line, character))?.contents {
+            HoverContents::Markup(markup) => Some(markup.value),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn hover_and_definition() {
+        let document = document(SRC);
+        assert!(document.diagnostics.is_empty());
+
+        // `y` in `fan3 y fen1`
+        assert_eq!(hover(&document, 4, 9).unwrap(), "```\ny: i64\n```");
+        let define = document.definition(Position::new(4, 9)).unwrap();
+        assert_eq!(
+            define,
+            Range::new(Position::new(3, 11), Position::new(3, 12))
+        );
+
+        // `jia` in `ya1 41 ru4 jia`
+        assert_eq!(
+            hover(&document, 9, 21).unwrap(),
+            "```\njia(i64) -> i64\n```"
+        );
+        let define = document.definition(Position::new(9, 21)).unwrap();
+        assert_eq!(
+            define,
+            Range::new(Position::new(1, 0), Position::new(1, 29))
+        );
+
+        // whitespace
+        assert!(hover(&document, 4, 5).is_none());
+    }
+
+    #[test]
+    fn completion() {
+        let document = document(SRC);
+        let labels = |line, character| {
+            document
+                .completion(Position::new(line, character))
+                .into_iter()
+                .map(|item| item.label)
+                .collect::<Vec<_>>()
+        };
+
+        let in_jia = labels(4, 9);
+        assert!(in_jia.contains(&"x".to_owned()));
+        assert!(in_jia.contains(&"y".to_owned()));
+        assert!(in_jia.contains(&"zhu3".to_owned()));
+        assert!(in_jia.contains(&"fan3".to_owned()));
+
+        let in_zhu3 = labels(9, 4);
+        assert!(!in_zhu3.contains(&"x".to_owned()));
+        assert!(in_zhu3.contains(&"jia".to_owned()));
+    }
+
+    #[test]
+    fn diagnostics() {
+        let undefined = document("zheng3 f can1 jie2 han2\n    fan3 a fen1\njie2\n");
+        assert_eq!(undefined.diagnostics.len(), 1);
+        let range = undefined.diagnostics[0].range;
+        assert_eq!(range, Range::new(Position::new(1, 9), Position::new(1, 10)));
+        let code = undefined.diagnostics[0].code.clone();
+        assert_eq!(code, Some(NumberOrString::String("E0203".to_owned())));
+
+        let unclosed = document("zheng3 f can1 jie2 han2\n    fan3 1\n");
+        assert_eq!(unclosed.diagnostics.len(), 1);
+
+        // only the body of `f` is broken, `g` can still call it
+        let broken = document(
+            "zheng3 f can1 jie2 han2\n    fan3 1 1 fen1\njie2\n\
+             zheng3 g can1 jie2 han2\n    fan3 ya1 ru4 f fen1\njie2\n",
+        );
+        assert_eq!(broken.diagnostics.len(), 1);
+        let range = broken.diagnostics[0].range;
+        assert_eq!(range.start.line, 1);
+
+        let string = document("zheng3 f can1 jie2 han2\n    fan3 chuan4 abc fen1\njie2\n");
+        assert_eq!(string.diagnostics.len(), 1);
+        let range = string.diagnostics[0].range;
+        assert_eq!(range, Range::new(Position::new(1, 9), Position::new(1, 19)));
+        let code = string.diagnostics[0].code.clone();
+        assert_eq!(code, Some(NumberOrString::String("E0236".to_owned())));
+    }
+}
@@ -2,13 +2,28 @@
 
 use std::{error::Error, path::PathBuf, process::exit};
 
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand, ValueEnum};
+use diagnostic::{ErrorFormat, Language};
 use py_ast::semantic::Generate;
 use py_codegen::Backend;
 use py_ir::Item;
 use py_lex::Token;
-use terl::{Buffer, ResultMapperExt, Source};
+use sources::Sources;
+use terl::{Buffer, ResultMapperExt};
 
+#[cfg(test)]
+mod bench;
+mod diagnostic;
+mod fmt;
+#[cfg(feature = "backend-llvm")]
+mod jit;
+#[cfg(feature = "lsp")]
+mod lsp;
+#[cfg(feature = "backend-llvm")]
+mod repl;
+#[cfg(feature = "backend-llvm")]
+mod run;
+mod sources;
 #[cfg(all(test, feature = "backend-llvm"))]
 mod tests;
 
@@ -29,6 +44,12 @@ enum LLVMOutputMode {
     Bitcode,
 }
 
+#[derive(ValueEnum, Clone, Copy)]
+enum GraphFormat {
+    /// graphviz's dot language
+    Dot,
+}
+
 // #[cfg(feature = "backend-llvm")]
 // #[derive(ValueEnum, Clone, Copy)]
 // enum LLVMOptimizeLevel {
@@ -39,15 +60,36 @@ enum LLVMOutputMode {
 // }
 
 #[derive(Parser)]
-#[command(version, about)]
+#[command(
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Cli {
-    src: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[arg(
+        required = true,
+        help = "source files, and directories whose .py1 files are compiled together"
+    )]
+    srcs: Vec<PathBuf>,
     #[arg(short, long, help = "path for output file, default to be a.out")]
     output: Option<PathBuf>,
     #[arg(long, help = "path for ast output file")]
     output_ast: Option<PathBuf>,
     #[arg(long, help = "path for py-ir output file")]
     output_ir: Option<PathBuf>,
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Text, help = "format of errors")]
+    error_format: ErrorFormat,
+    #[arg(
+        long,
+        value_enum,
+        help = "print how types in each function are inferred, even if the inference failed"
+    )]
+    dump_declare_graph: Option<GraphFormat>,
+    #[arg(long, value_enum, global = true, default_value_t = Language::En, help = "language of messages")]
+    lang: Language,
     #[cfg(feature = "backend-llvm")]
     #[arg(short = 'm', long, value_enum, default_value_t = LLVMOutputMode::Bitcode, help = "llvm ir output mode",)]
     output_mode: LLVMOutputMode,
@@ -64,24 +106,53 @@ struct Cli {
         arg(short = 'b', long, value_enum, default_value_t = CodeGenBackend::C, help = "code generation backend")
     )]
     backend: CodeGenBackend,
+    #[cfg(feature = "backend-c")]
+    #[arg(
+        long,
+        help = "emit `#line` directives and readable names in c backend output, and write a symbol map"
+    )]
+    c_debug: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[cfg(feature = "backend-llvm")]
+    Run(run::Run),
+    /// compile and execute function definitions, statements and expressions interactively
+    #[cfg(feature = "backend-llvm")]
+    Repl,
+    Fmt(fmt::Fmt),
+    /// start a language server, which communicates over stdin and stdout
+    #[cfg(feature = "lsp")]
+    Lsp,
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let cli = Cli::parse();
+    py_lex::messages::set_lang(cli.lang.into());
+
+    match cli.command {
+        #[cfg(feature = "backend-llvm")]
+        Some(Command::Run(run)) => return run.run(),
+        #[cfg(feature = "backend-llvm")]
+        Some(Command::Repl) => return repl::repl(),
+        Some(Command::Fmt(fmt)) => return fmt.fmt(),
+        #[cfg(feature = "lsp")]
+        Some(Command::Lsp) => return lsp::lsp(),
+        None => {}
+    }
 
     // generate ast
-    let path = &cli.src;
-    let src = std::fs::read_to_string(path)?;
-    let path = path.to_string_lossy().to_string();
-    let (error_handler, ast) = generate_ast(path.clone(), src);
-    let error_handler = (&error_handler.0, &error_handler.1);
+    let files = read_srcs(&cli.srcs)?;
+    let path = files[0].0.clone();
+    let (sources, ast) = generate_ast(files, cli.error_format);
 
     if let Some(ast_path) = cli.output_ast {
         std::fs::write(ast_path, format!("{ast:#?}"))?;
     }
 
     // generate ir
-    let ir = generate_ir(error_handler, &ast);
+    let ir = generate_ir(&sources, &ast, cli.error_format, cli.dump_declare_graph);
     if let Some(ast_path) = cli.output_ir {
         let mut file = std::fs::File::create(ast_path)?;
         serde_json::to_writer(&mut file, &ir)?;
@@ -110,7 +181,12 @@ fn main() -> Result<(), Box<dyn Error>> {
         #[cfg(feature = "backend-c")]
         CodeGenBackend::C => {
             use py_codegen_c::CBackend;
-            let backend = CBackend::init(());
+            let config = if cli.c_debug {
+                py_codegen_c::Config::debug()
+            } else {
+                Default::default()
+            };
+            let backend = CBackend::init(config);
             let module = backend.module(&path, &ir)?;
             let mut output = output;
 
@@ -119,36 +195,104 @@ fn main() -> Result<(), Box<dyn Error>> {
 
             output.set_extension("h");
             std::fs::write(&output, module.h_file())?;
+
+            if cli.c_debug {
+                output.set_extension("symbols.json");
+                let file = std::fs::File::create(&output)?;
+                serde_json::to_writer_pretty(file, module.symbols().mangled())?;
+            }
         }
     };
 
     Ok(())
 }
 
-fn generate_ir(error_handler: (&Buffer, &Buffer<Token>), ast: &[py_ast::parse::Item]) -> Vec<Item> {
-    let mut scope: py_ast::semantic::Defines = Default::default();
-
-    match scope.generate(ast) {
-        Ok(mir) => return mir,
-        Err(err) => match err {
-            either::Either::Left(errs) => errs
-                .into_iter()
-                .for_each(|e| eprintln!("{}", py_lex::Token::handle_error(&error_handler, e))),
-            either::Either::Right(errss) => errss
-                .into_iter()
-                .flatten()
-                .for_each(|e| eprintln!("{}", py_lex::Token::handle_error(&error_handler, e))),
-        },
+/// read source files, files in directories are read in the order of their names
+///
+/// return paths and source code of files
+fn read_srcs(paths: &[PathBuf]) -> std::io::Result<Vec<(String, String)>> {
+    let mut files = vec![];
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+        let mut srcs = std::fs::read_dir(path)?
+            .map(|entry| Ok(entry?.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        srcs.retain(|src| src.is_file() && src.extension().is_some_and(|ext| ext == "py1"));
+        srcs.sort();
+        if srcs.is_empty() {
+            let reason = format!("no source file in {}", path.display());
+            return Err(std::io::Error::new(std::io::ErrorKind::NotFound, reason));
+        }
+        files.extend(srcs);
+    }
+    files
+        .into_iter()
+        .map(|path| {
+            let src = std::fs::read_to_string(&path)?;
+            Ok((path.to_string_lossy().to_string(), src))
+        })
+        .collect()
+}
+
+fn generate_ir(
+    sources: &Sources,
+    ast: &[py_ast::parse::Item],
+    error_format: ErrorFormat,
+    dump_declare_graph: Option<GraphFormat>,
+) -> Vec<Item> {
+    generate_defines(sources, ast, error_format, dump_declare_graph).1
+}
+
+/// like [`generate_ir`], but definitions are returned too
+fn generate_defines(
+    sources: &Sources,
+    ast: &[py_ast::parse::Item],
+    error_format: ErrorFormat,
+    dump_declare_graph: Option<GraphFormat>,
+) -> (py_ast::semantic::Defines, Vec<Item>) {
+    let source_map = sources.source_map();
+    let scope: py_ast::semantic::Defines = Default::default();
+    let mut scope = scope.with_source_map(source_map);
+    if dump_declare_graph.is_some() {
+        scope = scope.with_declare_graphs();
+    }
+
+    let ir = scope.generate(ast);
+    if let Some(GraphFormat::Dot) = dump_declare_graph {
+        scope
+            .take_declare_graphs()
+            .unwrap()
+            .iter()
+            .for_each(|graph| print!("{graph}"));
+    }
+
+    match ir {
+        Ok(mir) => return (scope, mir),
+        Err(err) => semantic_errors(err)
+            .into_iter()
+            .for_each(|e| diagnostic::report(error_format, sources, e)),
     }
     exit(-1);
 }
 
-type GenAstResult = ((Buffer, Buffer<Token>), Vec<py_ast::parse::Item>);
+fn semantic_errors(
+    errors: either::Either<Vec<terl::Error>, Vec<Vec<terl::Error>>>,
+) -> Vec<terl::Error> {
+    match errors {
+        either::Either::Left(errs) => errs,
+        either::Either::Right(errss) => errss.into_iter().flatten().collect(),
+    }
+}
 
-fn generate_ast(path: String, src: String) -> GenAstResult {
+type GenAstResult = (Sources, Vec<py_ast::parse::Item>);
+
+fn tokenize(path: String, src: String) -> (Buffer, terl::Parser<Token>) {
     let source = Buffer::new(path, src.chars().collect());
     let parser = terl::Parser::<char>::new(source);
-    let (char_buffer, mut parser) = parser
+    parser
         .process(|p| {
             let mut tokens = vec![];
 
@@ -157,30 +301,31 @@ fn generate_ast(path: String, src: String) -> GenAstResult {
             }
             Ok(tokens)
         })
-        .unwrap_or_else(|_| unreachable!());
-    let parse_result = (|| -> terl::Result<_, terl::ParseError> {
-        let mut ast = vec![];
-        while parser.peek().is_some() {
-            ast.push(
-                parser
-                    .parse::<py_ast::parse::Item>()
-                    .apply(terl::mapper::MustMatch)?,
-            )
-        }
-        Ok(ast)
-    })();
-    let error_handler = (&char_buffer, parser.buffer());
-    let ast = match parse_result {
-        Ok(ast) => ast,
-        Err(error) => {
-            eprintln!("{}", parser.calling_tree());
-            eprintln!(
-                "{}",
-                py_lex::Token::handle_error(&error_handler, error.error())
-            );
-
-            exit(-1);
+        .unwrap_or_else(|_| unreachable!())
+}
+
+/// parse source files, `files` are paths and source code of them
+///
+/// items of all files are returned together, so that they can call each other
+fn generate_ast(files: Vec<(String, String)>, error_format: ErrorFormat) -> GenAstResult {
+    let files = files.into_iter().map(|(path, src)| {
+        let (chars, parser) = tokenize(path, src);
+        (chars, parser.take_buffer())
+    });
+    let sources = Sources::new(files.collect());
+    let mut ast = vec![];
+    let mut errors = vec![];
+    for (items, file_errors) in sources.parse(py_ast::parse::parse_items) {
+        ast.extend(items);
+        errors.extend(file_errors);
+    }
+    if !errors.is_empty() {
+        for error in errors {
+            diagnostic::report(error_format, &sources, error);
         }
-    };
-    ((char_buffer, parser.take_buffer()), ast)
+        // semantic errors in functions whose bodies are parsed cleanly are reported too
+        generate_ir(&sources, &ast, error_format, None);
+        exit(-1);
+    }
+    (sources, ast)
 }
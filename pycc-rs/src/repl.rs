@@ -0,0 +1,377 @@
+use std::{
+    error::Error,
+    io::{BufRead, Write},
+};
+
+use py_ast::{
+    parse::Item,
+    semantic::{
+        mangle::{DefaultMangler, Mangler},
+        Defines, Generate,
+    },
+};
+use py_codegen_llvm::{
+    inkwell::{execution_engine::ExecutionEngine, module::Module, OptimizationLevel},
+    LLVMBackend,
+};
+use py_declare::{defs::FnSign, Defs};
+use py_ir::{
+    types::{PrimitiveType, TypeDefine},
+    value::{AssignValue, Value},
+    FnDefine, Global, Return, Statement, VarStore,
+};
+use py_lex::Token;
+use terl::{ParseErrorKind, Source, Span};
+
+use crate::jit;
+
+#[derive(Debug)]
+pub(crate) enum Eval {
+    /// the input may be completed by following lines
+    Incomplete(String),
+    Failed(String),
+}
+
+/// a variable defined by top-level statements, which is kept in a [`Global`] between
+/// inputs
+struct Var {
+    name: String,
+    ty: TypeDefine,
+    /// index of the global, it's the suffix of names of the global and the accessor
+    global: usize,
+    /// mangled name of the accessor, which returns the value of the global
+    ///
+    /// variables are defined by calling accessors at the beginning of snippets, and they
+    /// are stored back into globals at the end of snippets, see [`Session::keep_vars`]
+    accessor: String,
+}
+
+pub(crate) struct Session<'ctx> {
+    backend: &'ctx LLVMBackend,
+    ee: ExecutionEngine<'ctx>,
+    modules: Vec<Module<'ctx>>,
+    defs: Defs,
+    /// mangler of accessors, which is the one [`Defines`] are created with
+    mangler: Mangler<DefaultMangler>,
+    /// all functions and globals which have been compiled, they are declared in later
+    /// modules
+    compiled: Vec<py_ir::Item>,
+    vars: Vec<Var>,
+    /// number of globals of variables, including ones of shadowed variables
+    globals: usize,
+    snippets: usize,
+}
+
+impl<'ctx> Session<'ctx> {
+    pub(crate) fn new(backend: &'ctx LLVMBackend) -> Result<Self, Box<dyn Error>> {
+        let module = backend.linked_module("repl", &[], &[])?;
+        let ee = module
+            .create_jit_execution_engine(OptimizationLevel::Default)
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            backend,
+            ee,
+            modules: vec![module],
+            defs: Defs::new(),
+            mangler: Default::default(),
+            compiled: vec![],
+            vars: vec![],
+            globals: 0,
+            snippets: 0,
+        })
+    }
+
+    /// parse and generate ir for the source code, with a copy of [`Defs`]
+    fn generate(&self, src: String) -> Result<(Defines, Vec<py_ir::Item>), String> {
+        let (chars, mut parser) = crate::tokenize("<repl>".to_owned(), src);
+        let mut ast = vec![];
+        while parser.peek().is_some() {
+            match parser.parse::<Item>() {
+                Ok(item) => ast.push(item),
+                Err(e) => {
+                    let error_handler = (&chars, parser.buffer());
+                    return Err(Token::handle_error(&error_handler, e.error()));
+                }
+            }
+        }
+        let error_handler = (&chars, parser.buffer());
+
+        let mut defines = Defines::new(self.defs.clone(), Default::default());
+        match defines.generate(ast.as_slice()) {
+            Ok(ir) => Ok((defines, ir)),
+            Err(errors) => Err(crate::semantic_errors(errors)
+                .into_iter()
+                .map(|e| Token::handle_error(&error_handler, e))
+                .collect::<Vec<_>>()
+                .join("\n")),
+        }
+    }
+
+    /// wrap statements into a function, so that they can be compiled. variables are defined
+    /// before statements, see [`Var::accessor`]
+    ///
+    /// the return type is inferred if `ty` is empty
+    fn wrap(&self, ty: &str, stmts: &str, ret: &str) -> String {
+        let vars = self.vars.iter().map(|var| {
+            let accessor = accessor(var.global);
+            format!("zi4dong4 {} wei2 ya1 ru4 {accessor} fen1\n", var.name)
+        });
+        format!(
+            "{ty} _repl{} can1 jie2 han2\n{}{stmts}\nfan3 {ret} fen1 jie2",
+            self.snippets,
+            vars.collect::<String>()
+        )
+    }
+
+    /// variables defined by top-level statements are stored into globals before the
+    /// function returns, and new variables are kept in new globals
+    ///
+    /// return new variables, and their globals and accessors which are compiled with the
+    /// function. the variables are kept after the function is executed
+    fn keep_vars(
+        &mut self,
+        define: &mut FnDefine<Value>,
+    ) -> Result<(Vec<Var>, Vec<py_ir::Item>), String> {
+        // variables, and globals of kept variables they are defined from
+        let mut defined: Vec<(String, TypeDefine, Option<usize>)> = vec![];
+        for stmt in define.body.iter() {
+            let Statement::VarDefine(var_define) = stmt else {
+                continue;
+            };
+            if var_define.is_temp {
+                continue;
+            }
+            let kept = match &var_define.init {
+                Some(AssignValue::FnCall(call)) => {
+                    self.vars.iter().find(|var| var.accessor == call.fn_name)
+                }
+                _ => None,
+            };
+            defined.retain(|(name, ..)| name != &var_define.name);
+            let global = kept.map(|var| var.global);
+            defined.push((var_define.name.clone(), var_define.ty.clone(), global));
+        }
+
+        let mut vars = vec![];
+        let mut items = vec![];
+        let mut stores = vec![];
+        for (name, ty, kept) in defined {
+            let idx = match kept {
+                Some(idx) => idx,
+                None if !matches!(ty, TypeDefine::Primitive(..) | TypeDefine::Fn(..)) => {
+                    return Err(format!("values of type `{ty}` cant be kept between inputs"));
+                }
+                None => {
+                    let idx = self.globals;
+                    self.globals += 1;
+                    let accessor = self.mangler.mangle_fn(&accessor(idx), &accessor_sign(&ty));
+                    items.push(
+                        Global {
+                            ty: ty.clone(),
+                            name: global(idx),
+                        }
+                        .into(),
+                    );
+                    let ret = Return {
+                        val: Some(Value::Variable(global(idx))),
+                    };
+                    items.push(
+                        FnDefine {
+                            export: false,
+                            ty: ty.clone(),
+                            name: accessor.clone(),
+                            params: vec![],
+                            body: vec![ret.into()].into(),
+                            template: None,
+                        }
+                        .into(),
+                    );
+                    vars.push(Var {
+                        name: name.clone(),
+                        ty,
+                        global: idx,
+                        accessor,
+                    });
+                    idx
+                }
+            };
+            stores.push(Statement::VarStore(VarStore {
+                name: global(idx),
+                fields: vec![],
+                val: Value::Variable(name),
+            }));
+        }
+
+        // the function is ended with `fan3`
+        let ret = define.body.len() - 1;
+        define.body.splice(ret..ret, stores);
+        Ok((vars, items))
+    }
+
+    fn compile(&mut self, ir: &[py_ir::Item]) -> Result<(), String> {
+        let name = format!("repl{}", self.snippets);
+        let module = self
+            .backend
+            .linked_module(&name, ir, &self.compiled)
+            .map_err(|e| e.to_string())?;
+        self.ee
+            .add_module(&module)
+            .map_err(|_| "module has been added")?;
+        self.modules.push(module);
+        Ok(())
+    }
+
+    /// evaluate the input, return the formatted value if the input is an expression
+    pub(crate) fn eval(&mut self, src: &str) -> Result<Option<String>, Eval> {
+        let is_item = {
+            let (_, mut parser) = crate::tokenize("<repl>".to_owned(), src.to_owned());
+            parser.parse::<Item>()
+        };
+        let result = match is_item {
+            Ok(..) => self.eval_items(src),
+            // unmatch at the beginning, so it must not be function definitions
+            Err(e) if e.kind() == ParseErrorKind::Unmatch => self.eval_expr(src),
+            Err(..) => {
+                let error = self.generate(src.to_owned()).err();
+                Err(Eval::Incomplete(error.unwrap_or_default()))
+            }
+        };
+        self.snippets += 1;
+        result
+    }
+
+    fn eval_items(&mut self, src: &str) -> Result<Option<String>, Eval> {
+        let (defines, ir) = self.generate(src.to_owned()).map_err(Eval::Incomplete)?;
+        self.compile(&ir).map_err(Eval::Failed)?;
+        self.defs = defines.defs;
+        self.compiled.extend(ir);
+        Ok(None)
+    }
+
+    /// generate the function wrapping the source code, see [`Session::wrap`]
+    fn generate_wrapped(&self, src: String) -> Result<(Vec<py_ir::Item>, FnDefine<Value>), String> {
+        let (defines, mut ir) = self.generate(src)?;
+        let name = format!("_repl{}", self.snippets);
+        let define = jit::find_fn(&defines, &ir, &name, 0)?;
+        let idx = ir
+            .iter()
+            .position(|item| matches!(item, py_ir::Item::FnDefine(d) if d.name == define.name))
+            .unwrap();
+        let py_ir::Item::FnDefine(define) = ir.remove(idx) else {
+            unreachable!()
+        };
+        Ok((ir, define))
+    }
+
+    fn eval_expr(&mut self, src: &str) -> Result<Option<String>, Eval> {
+        // the expression may be ended with `fen1`
+        let expr = src.trim_end();
+        let expr = expr
+            .strip_suffix("fen1")
+            .filter(|expr| expr.ends_with(char::is_whitespace))
+            .unwrap_or(expr);
+
+        // the return type is inferred from the expression
+        let error = match self.generate_wrapped(self.wrap("", "", expr)) {
+            Ok((mut ir, define)) => {
+                let Some(ty) = define.ty.as_primitive().copied() else {
+                    let ty = &define.ty;
+                    return Err(Eval::Failed(format!(
+                        "values of type `{ty}` cant be printed"
+                    )));
+                };
+                let name = define.name.clone();
+                ir.push(define.into());
+                self.compile(&ir).map_err(Eval::Failed)?;
+
+                let returned = unsafe { jit::call(&self.ee, &name, ty) };
+                let returned = returned.map_err(|e| Eval::Failed(e.to_string()))?;
+                return Ok(Some(format!("{returned}: {ty}")));
+            }
+            Err(error) => error,
+        };
+
+        // not an expression, try statements then
+        if src.trim_start().starts_with("fan3") {
+            return Err(Eval::Failed(
+                "`fan3` is not allowed out of functions".to_owned(),
+            ));
+        }
+        match self.generate_wrapped(self.wrap("zheng3", src, "0")) {
+            Ok((mut ir, mut define)) => {
+                let (vars, kept) = self.keep_vars(&mut define).map_err(Eval::Failed)?;
+                let name = define.name.clone();
+                ir.push(define.into());
+                ir.extend(kept.iter().cloned());
+                self.compile(&ir).map_err(Eval::Failed)?;
+                self.compiled.extend(kept);
+
+                // statements are executed only once, their side effects are kept
+                let returned = unsafe { jit::call(&self.ee, &name, PrimitiveType::I64) };
+                returned.map_err(|e| Eval::Failed(e.to_string()))?;
+                for var in vars {
+                    let sign = accessor_sign(&var.ty);
+                    self.defs.new_fn(&accessor(var.global), &var.accessor, sign);
+                    self.vars.retain(|kept| kept.name != var.name);
+                    self.vars.push(var);
+                }
+                Ok(None)
+            }
+            // errors of expression are more useful if the input is not statements
+            Err(e) if src_is_statements(src) => Err(Eval::Incomplete(e)),
+            Err(..) => Err(Eval::Incomplete(error)),
+        }
+    }
+}
+
+/// name of the accessor of the variable in the global, see [`Var::accessor`]
+fn accessor(global: usize) -> String {
+    format!("_repl_var{global}")
+}
+
+/// name of the global which keeps the variable
+fn global(global: usize) -> String {
+    format!("_repl_global{global}")
+}
+
+fn accessor_sign(ty: &TypeDefine) -> FnSign {
+    FnSign::new(ty.clone(), vec![], Span::new(0, 0), Span::new(0, 0))
+}
+
+/// statements always end with `fen1` or `jie2`
+fn src_is_statements(src: &str) -> bool {
+    let src = src.trim_end();
+    src.ends_with("fen1") || src.ends_with("jie2")
+}
+
+pub fn repl() -> Result<(), Box<dyn Error>> {
+    let backend = LLVMBackend::native()?;
+    let mut session = Session::new(&backend)?;
+
+    let mut stdin = std::io::stdin().lock();
+    let mut stdout = std::io::stdout();
+    let mut src = String::new();
+    loop {
+        write!(stdout, "{}", if src.is_empty() { ">> " } else { ".. " })?;
+        stdout.flush()?;
+
+        let mut line = String::new();
+        if stdin.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        // an empty line ends the input
+        let ended = line.trim().is_empty();
+        if ended && src.is_empty() {
+            continue;
+        }
+        src += &line;
+
+        match session.eval(&src) {
+            Ok(Some(output)) => println!("{output}"),
+            Ok(None) => {}
+            Err(Eval::Incomplete(..)) if !ended => continue,
+            Err(Eval::Incomplete(e) | Eval::Failed(e)) => eprintln!("{e}"),
+        }
+        src.clear();
+    }
+}
@@ -0,0 +1,62 @@
+use std::{error::Error, path::PathBuf, process::exit};
+
+use clap::Args;
+use py_codegen::Backend;
+use py_codegen_llvm::{inkwell::OptimizationLevel, LLVMBackend};
+
+use crate::{diagnostic::ErrorFormat, jit};
+
+/// compile the program in memory, and execute it with jit
+///
+/// arguments are parsed as the parameters of the called function, so `zhu3` takes typed
+/// parameters instead of argc and argv, and arguments of other types can't be passed
+#[derive(Args)]
+pub struct Run {
+    src: PathBuf,
+    #[arg(
+        long = "fn",
+        help = "call the function instead of `zhu3`, and print the returned value"
+    )]
+    function: Option<String>,
+    #[arg(
+        trailing_var_arg = true,
+        allow_hyphen_values = true,
+        help = "arguments passed to the function, parsed as its parameters' types (there is no argc or argv)"
+    )]
+    args: Vec<String>,
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Text, help = "format of errors")]
+    error_format: ErrorFormat,
+}
+
+impl Run {
+    pub fn run(self) -> Result<(), Box<dyn Error>> {
+        let src = std::fs::read_to_string(&self.src)?;
+        let path = self.src.to_string_lossy().to_string();
+        let files = vec![(path.clone(), src)];
+        let (sources, ast) = crate::generate_ast(files, self.error_format);
+        let (defines, ir) = crate::generate_defines(&sources, &ast, self.error_format, None);
+
+        let name = self.function.as_deref().unwrap_or("zhu3");
+        let callee = jit::find_fn(&defines, &ir, name, self.args.len())?;
+
+        let backend = LLVMBackend::native()?;
+        let module = backend.module(&path, &ir)?;
+        let ee = module
+            .create_jit_execution_engine(OptimizationLevel::Default)
+            .map_err(|e| e.to_string())?;
+
+        // arguments are checked with the data layout, so the entry is compiled later
+        let entry = jit::entry(callee, &self.args, ee.get_target_data())?;
+        let ty = *entry.ty.as_primitive().unwrap();
+        let entry = backend.linked_module(jit::ENTRY, &[entry.into()], &ir)?;
+        ee.add_module(&entry).map_err(|_| "module has been added")?;
+
+        // stdin and stdout are shared with the program, because it runs in this process
+        let returned = unsafe { jit::call(&ee, jit::ENTRY, ty)? };
+        if self.function.is_some() {
+            println!("{returned}");
+            return Ok(());
+        }
+        exit(returned.exit_code());
+    }
+}
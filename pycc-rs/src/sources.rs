@@ -0,0 +1,153 @@
+use std::ops::Range;
+
+use py_lex::{messages, Token};
+use terl::{Buffer, Parser, Source, Span, WithSpan};
+
+/// source files which are compiled together
+///
+/// tokens of all files are put one after another in a [`Buffer<Token>`], so spans of
+/// items in different files never overlap, and items can be generated together. after
+/// tokens of each file there is a [`Token::end_of_file`], where parsing of the file stops
+/// and errors like "no token left" are
+pub struct Sources {
+    /// chars of each file, with the range of its tokens, including the one at its end
+    files: Vec<(Range<usize>, Buffer<char>)>,
+    tokens: Buffer<Token>,
+}
+
+impl Sources {
+    /// `files` are chars and tokens of files, in the order they are compiled
+    pub fn new(files: Vec<(Buffer<char>, Buffer<Token>)>) -> Self {
+        let mut tokens = vec![];
+        let files = files
+            .into_iter()
+            .map(|(chars, file_tokens)| {
+                let start = tokens.len();
+                tokens.extend(file_tokens.iter().cloned());
+                let end = Span::new(chars.len(), chars.len());
+                tokens.push(Token::end_of_file(end));
+                (start..tokens.len(), chars)
+            })
+            .collect();
+        let tokens = Buffer::new(String::new(), tokens);
+        Self { files, tokens }
+    }
+
+    /// parse files in order by one parser, `parse` is called once for each file and it
+    /// stops at the end of the file
+    ///
+    /// spans of parse units are spans of tokens in all files
+    pub fn parse<T>(&self, mut parse: impl FnMut(&mut Parser<Token>) -> T) -> Vec<T> {
+        let tokens = Buffer::new(String::new(), self.tokens.to_vec());
+        let mut parser = Parser::new(tokens);
+        self.files
+            .iter()
+            .map(|_| {
+                let parsed = parse(&mut parser);
+                // tokens which are not parsed, and the end of the file
+                while parser.next().is_some_and(|token| !token.is_end_of_file()) {}
+                parsed
+            })
+            .collect()
+    }
+
+    /// the file where the span starts at, spans out of range are in the last file
+    fn file(&self, span: Span) -> &(Range<usize>, Buffer<char>) {
+        let idx = self
+            .files
+            .partition_point(|(range, _)| range.end <= span.start);
+        &self.files[idx.min(self.files.len() - 1)]
+    }
+
+    /// chars of the file where the span starts at, and the span of chars which the span
+    /// of tokens is
+    pub fn locate(&self, span: Span) -> (&Buffer<char>, Span) {
+        let (range, chars) = self.file(span);
+        let token = |idx: usize| self.tokens[idx.clamp(range.start, range.end - 1)].get_span();
+        let (start, end) = (token(span.start), token(span.end.max(span.start + 1) - 1));
+        (chars, start + end)
+    }
+
+    /// the map of lines and columns of tokens in all files
+    pub fn source_map(&self) -> py_lex::SourceMap {
+        let mut files = self.files.iter().map(|(range, chars)| {
+            let tokens = Buffer::new(String::new(), self.tokens[range.clone()].to_vec());
+            py_lex::SourceMap::new(chars, &tokens)
+        });
+        let mut source_map = files.next().unwrap();
+        files.for_each(|file| source_map.append(file));
+        source_map
+    }
+}
+
+impl Source for Sources {
+    type HandleErrorWith<'b> = Sources;
+
+    fn handle_message<S>(
+        with: &Self::HandleErrorWith<'_>,
+        buffer: &mut S,
+        message: terl::Message,
+    ) -> std::fmt::Result
+    where
+        S: std::fmt::Write,
+    {
+        match message {
+            terl::Message::Location(at) => Self::handle_location(with, buffer, at, ""),
+            terl::Message::Text(text) => writeln!(buffer, "{}", messages::untag(&text)),
+            terl::Message::Rich(text, at) => Self::handle_location(with, buffer, at, &text),
+        }
+    }
+
+    fn handle_location<S>(
+        with: &Self::HandleErrorWith<'_>,
+        buffer: &mut S,
+        loc: Span,
+        msg: &str,
+    ) -> std::fmt::Result
+    where
+        S: std::fmt::Write,
+    {
+        let (chars, loc) = with.locate(loc);
+        char::handle_location(chars, buffer, loc, &messages::untag(msg))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sources(files: &[(&str, &str)]) -> Sources {
+        let files = files.iter().map(|(name, src)| {
+            let (chars, parser) = crate::tokenize(name.to_string(), src.to_string());
+            (chars, parser.take_buffer())
+        });
+        Sources::new(files.collect())
+    }
+
+    #[test]
+    fn errors_in_files() {
+        let sources = sources(&[
+            ("a.py1", "zheng3 f can1 jie2 han2 fan3 1 fen1 jie2"),
+            ("b.py1", "zheng3 g can1 jie2\nhan2 fan3 1"),
+        ]);
+
+        let mut parsed = sources.parse(py_ast::parse::parse_items);
+        let (items, errors) = parsed.pop().unwrap();
+        assert!(items.is_empty());
+        assert_eq!(errors.len(), 1);
+        let error = Sources::handle_error(&sources, errors[0].clone());
+        assert!(error.starts_with("[b.py1:2:12]"), "{error}");
+
+        // an item of `a.py1` is parsed before the end of its file
+        let (items, errors) = parsed.pop().unwrap();
+        assert_eq!(items.len(), 1);
+        assert!(errors.is_empty());
+
+        // the first token of `b.py1` is after the token at the end of `a.py1`
+        let (chars, at) = sources.locate(Span::new(10, 11));
+        assert_eq!((chars.name(), at), ("b.py1", Span::new(0, 6)));
+        let source_map = sources.source_map();
+        assert_eq!(source_map.name(Span::new(9, 10)), "a.py1");
+        assert_eq!(source_map.line_col(Span::new(14, 15)), (2, 1));
+    }
+}
@@ -8,10 +8,14 @@ use py_codegen_llvm::{
 };
 use py_ir as ir;
 
+fn test_generate_defines(src: &str) -> (py_ast::semantic::Defines, Vec<ir::Item>) {
+    let files = vec![("compiler-test.py1".to_owned(), src.to_owned())];
+    let (sources, ast) = crate::generate_ast(files, Default::default());
+    crate::generate_defines(&sources, &ast, Default::default(), None)
+}
+
 fn test_generate_ir(src: &str) -> Vec<ir::Item> {
-    let (error_handler, ast) = crate::generate_ast("compiler-test.py1".to_owned(), src.to_owned());
-    let error_handler = (&error_handler.0, &error_handler.1);
-    crate::generate_ir(error_handler, &ast)
+    test_generate_defines(src).1
 }
 
 fn compile_tester(src: &str, tester: impl FnOnce(&ExecutionEngine)) {
@@ -175,3 +179,639 @@ fn control_flow_test() {
         }
     })
 }
+
+// `shuang` calls functions whose return types are inferred, before they are defined
+const INFERRED_RETURN: &str = "
+zheng3 shuang can1 zheng3 x jie2
+han2
+    fan3 ya1 x ru4 jia jia1 ya1 x ru4 fei1fu4 fen1
+jie2
+
+jia can1 zheng3 x jie2
+han2
+    fan3 x jia1 1 fen1
+jie2
+
+zi4dong4 fei1fu4 can1 zheng3 x jie2
+han2
+    ruo4 can1 x xiao3 0 jie2
+    han2
+        fan3 ya1 0 ru4 jia jian3 1 jian3 x fen1
+    jie2
+    fan3 ya1 x ru4 jia jian3 1 fen1
+jie2
+";
+
+#[test]
+fn inferred_return_types() {
+    compile_tester(INFERRED_RETURN, |ee| unsafe {
+        type TestFn = unsafe extern "C" fn(i64) -> i64;
+
+        let shuang: JitFunction<TestFn> = ee.get_function("shuang 参 i64 结").unwrap();
+        for n in -100..100 {
+            assert_eq!(shuang.call(n), n + 1 + n.abs());
+        }
+    })
+}
+
+const PROMOTIONS: &str = "
+kuan1 128 zheng3 kuan can1 kuan1 128 zheng3 x jie2
+han2
+    fan3 x jia1 1 fen1
+jie2
+
+zheng3 geng4da4 can1 zheng3 x jie2
+han2
+    kuan1 128 zheng3 z wei2 x fen1
+    ruo4 can1 ya1 x ru4 kuan da4 x jia1 z jie2 han2
+        fan3 1 fen1
+    jie2
+    fan3 0 fen1
+jie2
+
+kuan1 64 fu2 jia1ban4 can1 fu2 x fen1 kuan1 64 fu2 y jie2
+han2
+    fan3 x jia1 y chu2 2f0 fen1
+jie2
+";
+
+#[test]
+fn implicit_promotions() {
+    compile_tester(PROMOTIONS, |ee| unsafe {
+        type GengDa = unsafe extern "C" fn(i64) -> i64;
+        type JiaBan = unsafe extern "C" fn(f32, f64) -> f64;
+
+        // `x jia1 1 da4 x jia1 x` without overflows
+        let geng4da4: JitFunction<GengDa> = ee.get_function("geng4da4 参 i64 结").unwrap();
+        for (x, expect) in [(-5, 1), (0, 1), (1, 0), (i64::MAX, 0)] {
+            assert_eq!(geng4da4.call(x), expect);
+        }
+
+        let jia1ban4: JitFunction<JiaBan> = ee.get_function("jia1ban4 参 f32 f64 结").unwrap();
+        assert_eq!(jia1ban4.call(1.5, 2.5), 2.75);
+    })
+}
+
+const GENERICS: &str = "
+fan4xing2 can1 T jie2 T xuan3 can1 T x fen1 T y jie2
+han2
+    ruo4 can1 x da4 y jie2 han2
+        fan3 x fen1
+    jie2
+    fan3 y fen1
+jie2
+
+zheng3 zheng3shu4 can1 zheng3 x jie2
+han2
+    fan3 ya1 x fen1 0 ru4 xuan3 fen1
+jie2
+
+kuan1 64 fu2 fu2dian3 can1 kuan1 64 fu2 x jie2
+han2
+    fan3 ya1 x fen1 0f0 ru4 xuan3 fen1
+jie2
+";
+
+#[test]
+fn generic_instances() {
+    compile_tester(GENERICS, |ee| unsafe {
+        type Int = unsafe extern "C" fn(i64) -> i64;
+        type Float = unsafe extern "C" fn(f64) -> f64;
+
+        // an instance is generated for each type argument
+        let instance: JitFunction<Int> = ee.get_function("xuan3 型 i64 参 i64 i64 结").unwrap();
+        assert_eq!(instance.call(3), 3);
+
+        let zheng3shu4: JitFunction<Int> = ee.get_function("zheng3shu4 参 i64 结").unwrap();
+        assert_eq!(zheng3shu4.call(-3), 0);
+        assert_eq!(zheng3shu4.call(5), 5);
+
+        let fu2dian3: JitFunction<Float> = ee.get_function("fu2dian3 参 f64 结").unwrap();
+        assert_eq!(fu2dian3.call(-1.5), 0.0);
+        assert_eq!(fu2dian3.call(2.5), 2.5);
+    })
+}
+
+/// names of functions in the ir, with the generic functions they are instances of
+fn instances(ir: &[ir::Item]) -> Vec<(String, Option<ir::types::Template>)> {
+    let fns = ir.iter().filter_map(|item| match item {
+        ir::Item::FnDefine(fn_define) => Some(fn_define),
+        _ => None,
+    });
+    fns.map(|fn_define| (fn_define.name.clone(), fn_define.template.clone()))
+        .collect()
+}
+
+#[test]
+fn generic_instances_of_solved_types() {
+    let src = format!(
+        "{GENERICS}
+zheng3 liang3 can1 jie2
+han2
+    fan3 ya1 1 fen1 2 ru4 xuan3 fen1
+jie2
+"
+    );
+    let ir = test_generate_ir(&src);
+    let instances = instances(&ir);
+    let instances = instances
+        .iter()
+        .filter_map(|(name, template)| Some((name.as_str(), template.as_ref()?)))
+        .collect::<Vec<_>>();
+
+    // literals are passed as their default types, instead of every type they could be
+    let int = ir::types::PrimitiveType::I64.into();
+    let float = ir::types::PrimitiveType::F64.into();
+    let template = |ty| ir::types::Template::new("xuan3", [("T".to_owned(), ty)].into());
+    assert_eq!(
+        instances,
+        [
+            ("xuan3 型 i64 参 i64 i64 结", &template(int)),
+            ("xuan3 型 f64 参 f64 f64 结", &template(float)),
+        ]
+    );
+}
+
+#[test]
+fn generic_instances_of_single_item() {
+    use py_ast::semantic::Generate;
+
+    let files = vec![("compiler-test.py1".to_owned(), GENERICS.to_owned())];
+    let (_, ast) = crate::generate_ast(files, Default::default());
+    let mut defines: py_ast::semantic::Defines = Default::default();
+    let mut ir = vec![];
+    for item in &ast {
+        ir.extend(defines.generate(item).unwrap());
+    }
+
+    // an instance is generated after the first function which calls it
+    let names = instances(&ir).into_iter().map(|(name, _)| name);
+    assert_eq!(
+        names.collect::<Vec<_>>(),
+        [
+            "zheng3shu4 参 i64 结",
+            "xuan3 型 i64 参 i64 i64 结",
+            "fu2dian3 参 f64 结",
+            "xuan3 型 f64 参 f64 f64 结",
+        ]
+    );
+}
+
+const CLASSES: &str = "
+lei4 Dian3 han2
+    zheng3 x fen1
+    zheng3 y fen1
+
+    zheng3 he can1 Dian3 p jie2 han2 fan3 p de1 x jia1 p de1 y fen1 jie2
+    zheng3 jia can1 Dian3 p fen1 zheng3 k jie2 han2 fan3 ya1 ru4 p de1 he jia1 k fen1 jie2
+jie2
+
+lei4 Xian4 han2
+    Dian3 a fen1
+    Dian3 b fen1
+jie2
+
+zheng3 ce4 can1 zheng3 k jie2
+han2
+    Dian3 p wei2 ya1 1 fen1 2 ru4 Dian3 fen1
+    p de1 x wei2 k fen1
+    Xian4 l wei2 ya1 p fen1 ya1 3 fen1 4 ru4 Dian3 ru4 Xian4 fen1
+    l de1 b de1 y wei2 l de1 a de1 x fen1
+    fan3 ya1 5 ru4 l de1 b de1 jia jia1 p de1 y fen1
+jie2
+";
+
+#[test]
+fn classes() {
+    compile_tester(CLASSES, |ee| unsafe {
+        type Ce4 = unsafe extern "C" fn(i64) -> i64;
+
+        // 3 + k + 5 + 2
+        let ce4: JitFunction<Ce4> = ee.get_function("ce4 参 i64 结").unwrap();
+        assert_eq!(ce4.call(10), 20);
+        assert_eq!(ce4.call(0), 10);
+    })
+}
+
+#[test]
+fn classes_of_single_item() {
+    use py_ast::semantic::Generate;
+
+    let files = vec![("compiler-test.py1".to_owned(), CLASSES.to_owned())];
+    let (_, ast) = crate::generate_ast(files, Default::default());
+    let mut defines: py_ast::semantic::Defines = Default::default();
+    let mut ir = vec![];
+    for item in &ast {
+        ir.extend(defines.generate(item).unwrap());
+    }
+
+    // the constructor and methods are generated with the struct
+    let names = ir.iter().map(|item| match item {
+        ir::Item::FnDefine(fn_define) => &fn_define.name,
+        ir::Item::Struct(struct_) => &struct_.name,
+        ir::Item::Global(global) => &global.name,
+    });
+    assert_eq!(
+        names.collect::<Vec<_>>(),
+        [
+            "Dian3",
+            "Dian3的Dian3 参 i64 i64 结",
+            "Dian3的he 参 Dian3 结",
+            "Dian3的jia 参 Dian3 i64 结",
+            "Xian4",
+            "Xian4的Xian4 参 Dian3 Dian3 结",
+            "ce4 参 i64 结",
+        ]
+    );
+}
+
+const INTERFACES: &str = "
+jie1kou3 Ke3jia1 can1 T jie2
+han2
+    T jia can1 T a fen1 T b jie2 fen1
+jie2
+
+lei4 Dian3 shi2xian4 Ke3jia1
+han2
+    zheng3 x fen1
+    zheng3 y fen1
+jie2
+
+Dian3 jia can1 Dian3 a fen1 Dian3 b jie2
+han2
+    fan3 ya1 a de1 x jia1 b de1 x fen1 a de1 y jia1 b de1 y ru4 Dian3 fen1
+jie2
+
+zheng3 jia can1 zheng3 a fen1 zheng3 b jie2
+han2
+    fan3 a jia1 b fen1
+jie2
+
+fan4xing2 can1 T shi2xian4 Ke3jia1 jie2 T shuang1 can1 T x jie2
+han2
+    fan3 ya1 x fen1 x ru4 jia fen1
+jie2
+
+zheng3 ce4 can1 zheng3 k jie2
+han2
+    Dian3 p wei2 ya1 ya1 k fen1 1 ru4 Dian3 ru4 shuang1 fen1
+    fan3 ya1 p de1 x fen1 ya1 k ru4 shuang1 ru4 jia fen1
+jie2
+";
+
+#[test]
+fn constrained_generics() {
+    compile_tester(INTERFACES, |ee| unsafe {
+        type Ce4 = unsafe extern "C" fn(i64) -> i64;
+
+        // both `Dian3` and `zheng3` implement `Ke3jia1`
+        let ce4: JitFunction<Ce4> = ee.get_function("ce4 参 i64 结").unwrap();
+        assert_eq!(ce4.call(3), 12);
+        assert_eq!(ce4.call(-1), -4);
+    })
+}
+
+const OPERATORS: &str = "
+lei4 Dian3
+han2
+    zheng3 x fen1
+    zheng3 y fen1
+
+    Dian3 yun4suan4 jian3 can1 Dian3 a fen1 Dian3 b jie2
+    han2
+        fan3 ya1 a de1 x jian3 b de1 x fen1 a de1 y jian3 b de1 y ru4 Dian3 fen1
+    jie2
+jie2
+
+Dian3 yun4suan4 jia1 can1 Dian3 a fen1 Dian3 b jie2
+han2
+    fan3 ya1 a de1 x jia1 b de1 x fen1 a de1 y jia1 b de1 y ru4 Dian3 fen1
+jie2
+
+Dian3 yun4suan4 cheng2 can1 Dian3 a fen1 zheng3 k jie2
+han2
+    fan3 ya1 a de1 x cheng2 k fen1 a de1 y cheng2 k ru4 Dian3 fen1
+jie2
+
+zheng3 yun4suan4 xiao3 can1 Dian3 a fen1 Dian3 b jie2
+han2
+    ruo4 can1 a de1 x jia1 a de1 y xiao3 b de1 x jia1 b de1 y jie2
+    han2
+        fan3 1 fen1
+    jie2
+    fan3 0 fen1
+jie2
+
+zheng3 ce4 can1 zheng3 k jie2
+han2
+    Dian3 p wei2 ya1 k fen1 1 ru4 Dian3 fen1
+    Dian3 q wei2 p jia1 p cheng2 k fen1
+    Dian3 r wei2 q jian3 p fen1
+    fan3 r de1 x jia1 r de1 y jia1 jie2 p xiao3 q he2 fen1
+jie2
+";
+
+#[test]
+fn operator_overloading() {
+    compile_tester(OPERATORS, |ee| unsafe {
+        type Ce4 = unsafe extern "C" fn(i64) -> i64;
+
+        let ce4: JitFunction<Ce4> = ee.get_function("ce4 参 i64 结").unwrap();
+        assert_eq!(ce4.call(2), 7);
+        assert_eq!(ce4.call(-1), 0);
+    })
+}
+
+const FN_VALUES: &str = "
+zheng3 jia1fa3 can1 zheng3 a fen1 zheng3 b jie2
+han2
+    fan3 a jia1 b fen1
+jie2
+
+fu2 jia1fa3 can1 fu2 a fen1 fu2 b jie2
+han2
+    fan3 a jia1 b fen1
+jie2
+
+zheng3 cheng2fa3 can1 zheng3 a fen1 zheng3 b jie2
+han2
+    fan3 a cheng2 b fen1
+jie2
+
+zheng3 zhe2die2 can1 han2zhi3 zheng3 can1 zheng3 fen1 zheng3 jie2 f fen1 zheng3 n jie2
+han2
+    zheng3 s wei2 0 fen1
+    zheng3 i wei2 1 fen1
+    chong2 can1 i xiao3 n jia1 1 jie2
+    han2
+        s wei2 ya1 s fen1 i ru4 f fen1
+        i wei2 i jia1 1 fen1
+    jie2
+    fan3 s fen1
+jie2
+
+zheng3 ce4 can1 zheng3 n jie2
+han2
+    han2zhi3 zheng3 can1 zheng3 fen1 zheng3 jie2 f wei2 jia1fa3 fen1
+    ruo4 can1 n xiao3 0 jie2
+    han2
+        f wei2 cheng2fa3 fen1
+    jie2
+    zi4dong4 g wei2 cheng2fa3 fen1
+    fan3 ya1 f fen1 n ru4 zhe2die2 jia1 ya1 2 fen1 3 ru4 g jia1 ya1 jia1fa3 fen1 1 ru4 zhe2die2 fen1
+jie2
+
+zheng3 yong4 can1 han2zhi3 zheng3 can1 zheng3 jie2 g fen1 zheng3 x jie2
+han2
+    fan3 ya1 x ru4 g fen1
+jie2
+";
+
+#[test]
+fn fn_values() {
+    compile_tester(FN_VALUES, |ee| unsafe {
+        type Ce4 = unsafe extern "C" fn(i64) -> i64;
+        type Yong4 = unsafe extern "C" fn(extern "C" fn(i64) -> i64, i64) -> i64;
+
+        let ce4: JitFunction<Ce4> = ee.get_function("ce4 参 i64 结").unwrap();
+        assert_eq!(ce4.call(4), 17);
+        assert_eq!(ce4.call(-1), 7);
+
+        // callbacks from the host
+        extern "C" fn twice(x: i64) -> i64 {
+            x * 2
+        }
+        let yong4: JitFunction<Yong4> =
+            ee.get_function("yong4 参 函 i64 参 i64 结 i64 结").unwrap();
+        assert_eq!(yong4.call(twice, 21), 42);
+    })
+}
+
+const CLOSURES: &str = "
+zheng3 ce4 can1 zheng3 k jie2
+han2
+    zheng3 n wei2 0 fen1
+    zi4dong4 lei3jia1 wei2 bi4bao1 zheng3 can1 zheng3 x jie2
+    han2
+        n wei2 n jia1 x fen1
+        fan3 n fen1
+    jie2 fen1
+    zi4dong4 du2 wei2 bi4bao1 can1 jie2
+    han2
+        zi4dong4 nei4 wei2 bi4bao1 can1 jie2
+        han2
+            fan3 k cheng2 ya1 1 ru4 lei3jia1 fen1
+        jie2 fen1
+        fan3 ya1 ru4 nei4 fen1
+    jie2 fen1
+    ya1 k ru4 lei3jia1 fen1
+    ya1 k ru4 lei3jia1 fen1
+    fan3 ya1 ru4 du2 jia1 n fen1
+jie2
+
+zheng3 kuai4zhao4 can1 zheng3 k jie2
+han2
+    zheng3 n wei2 k fen1
+    zi4dong4 du2 wei2 bi4bao1 can1 jie2
+    han2
+        fan3 n fen1
+    jie2 fen1
+    n wei2 n jia1 1 fen1
+    fan3 ya1 ru4 du2 jia1 n fen1
+jie2
+";
+
+#[test]
+fn closures() {
+    compile_tester(CLOSURES, |ee| unsafe {
+        type Ce4 = unsafe extern "C" fn(i64) -> i64;
+
+        // `n` is captured by reference, so it's changed by `lei3jia1`
+        let ce4: JitFunction<Ce4> = ee.get_function("ce4 参 i64 结").unwrap();
+        assert_eq!(ce4.call(3), 28);
+        assert_eq!(ce4.call(-2), 3);
+
+        // `n` is captured by value, so `du2` returns the value before it's changed
+        let kuai4zhao4: JitFunction<Ce4> = ee.get_function("kuai4zhao4 参 i64 结").unwrap();
+        assert_eq!(kuai4zhao4.call(3), 3 + 4);
+    })
+}
+
+const MODULES: &str = "
+gong1kai1 mo2kuai4 shu4
+han2
+    mo2kuai4 nei4
+    han2
+        gong1kai1 zheng3 bei4 can1 zheng3 x jie2
+        han2
+            fan3 x cheng2 2 fen1
+        jie2
+    jie2
+
+    zheng3 jia1yi1 can1 zheng3 x jie2
+    han2
+        fan3 x jia1 1 fen1
+    jie2
+
+    gong1kai1 zheng3 f can1 zheng3 x jie2
+    han2
+        fan3 ya1 ya1 x ru4 jia1yi1 ru4 nei4 de1 bei4 fen1
+    jie2
+
+    gong1kai1 g can1 zheng3 x jie2
+    han2
+        fan3 ya1 x ru4 f fen1
+    jie2
+jie2
+
+zheng3 f can1 zheng3 x jie2
+han2
+    fan3 x fen1
+jie2
+
+dao3chu1 zheng3 ce4 can1 zheng3 x jie2
+han2
+    zi4dong4 h wei2 shu4 de1 f fen1
+    fan3 ya1 x ru4 shu4 de1 g jia1 ya1 x ru4 f jia1 ya1 x ru4 h fen1
+jie2
+";
+
+#[test]
+fn modules() {
+    compile_tester(MODULES, |ee| unsafe {
+        type Ce4 = unsafe extern "C" fn(i64) -> i64;
+
+        // `f` in `shu4` calls the function in the module, not the one outside it
+        let ce4: JitFunction<Ce4> = ee.get_function("ce4 参 i64 结").unwrap();
+        assert_eq!(ce4.call(3), 19);
+        assert_eq!(ce4.call(-1), -1);
+        let f: JitFunction<Ce4> = ee.get_function("shu4的f 参 i64 结").unwrap();
+        assert_eq!(f.call(1), 4);
+    })
+}
+
+#[test]
+fn public_items_of_modules() {
+    let src = "
+mo2kuai4 shu4
+han2
+    gong1kai1 fan4xing2 can1 T jie2 T xuan3 can1 T x fen1 T y jie2
+    han2
+        fan3 x fen1
+    jie2
+
+    gong1kai1 zheng3 f can1 zheng3 x jie2
+    han2
+        fan3 x fen1
+    jie2
+
+    gong1kai1 dao3chu1 zheng3 g can1 zheng3 x jie2
+    han2
+        fan3 x fen1
+    jie2
+jie2
+
+zheng3 h can1 zheng3 x jie2
+han2
+    fan3 ya1 ya1 ya1 x ru4 shu4 de1 f fen1 x ru4 shu4 de1 xuan3 ru4 shu4 de1 g fen1
+jie2
+";
+    let ir = test_generate_ir(src);
+    let fns = ir.iter().filter_map(|item| match item {
+        ir::Item::FnDefine(fn_define) => Some((fn_define.name.as_str(), fn_define.export)),
+        _ => None,
+    });
+
+    // `gong1kai1` only makes them usable outside the module, `dao3chu1` exports them
+    assert_eq!(
+        fns.collect::<Vec<_>>(),
+        [
+            ("shu4的f 参 i64 结", false),
+            ("shu4的g 参 i64 结", true),
+            ("h 参 i64 结", false),
+            ("shu4的xuan3 型 i64 参 i64 i64 结", false),
+        ]
+    );
+}
+
+#[test]
+fn jit_find_fn_in_module() {
+    let (defines, ir) = test_generate_defines(MODULES);
+    let f = crate::jit::find_fn(&defines, &ir, "shu4 de1 f", 1).unwrap();
+    assert_eq!(f.name, "shu4的f 参 i64 结");
+    let bei4 = crate::jit::find_fn(&defines, &ir, "shu4  de1 nei4 de1 bei4", 1).unwrap();
+    assert_eq!(bei4.name, "shu4的nei4的bei4 参 i64 结");
+    // not the one in the module, whose mangled name starts with `f 参` too
+    let f = crate::jit::find_fn(&defines, &ir, "f", 1).unwrap();
+    assert_eq!(f.name, "f 参 i64 结");
+}
+
+#[test]
+fn jit_entry() {
+    let (defines, ir) = test_generate_defines(OVERLOAD_TEST);
+    assert!(crate::jit::find_fn(&defines, &ir, "a", 1).is_err());
+    let callee = crate::jit::find_fn(&defines, &ir, "test", 2).unwrap();
+
+    let backend = LLVMBackend::native().unwrap();
+    let module = backend.module("jit_entry", &ir).unwrap();
+    let ee = module
+        .create_jit_execution_engine(OptimizationLevel::Default)
+        .unwrap();
+
+    let target_data = ee.get_target_data();
+    let args = ["-1".to_owned(), "x".to_owned()];
+    assert!(crate::jit::entry(callee, &args, target_data).is_err());
+    let args = ["-1".to_owned(), "2.5".to_owned()];
+    let entry = crate::jit::entry(callee, &args, target_data).unwrap();
+    let entry = backend
+        .linked_module(crate::jit::ENTRY, &[entry.into()], &ir)
+        .unwrap();
+    ee.add_module(&entry).unwrap();
+    let returned =
+        unsafe { crate::jit::call(&ee, crate::jit::ENTRY, ir::types::PrimitiveType::I64) }.unwrap();
+    assert_eq!(returned, crate::jit::Returned::Signed(114514));
+}
+
+#[test]
+fn repl_session() {
+    use crate::repl::{Eval, Session};
+
+    let backend = LLVMBackend::native().unwrap();
+    let mut session = Session::new(&backend).unwrap();
+
+    let define = "zheng3 jia can1 zheng3 x jie2 han2 fan3 x jia1 1 fen1 jie2\n";
+    assert_eq!(session.eval(define).unwrap(), None);
+    assert_eq!(session.eval("zheng3 a wei2 41 fen1\n").unwrap(), None);
+    assert_eq!(
+        session.eval("ya1 a ru4 jia\n").unwrap().as_deref(),
+        Some("42: i64")
+    );
+    // the statement is executed once, and the variable keeps its value
+    assert_eq!(session.eval("a wei2 ya1 a ru4 jia fen1\n").unwrap(), None);
+    assert_eq!(session.eval("a\n").unwrap().as_deref(), Some("42: i64"));
+    // types of expressions are inferred
+    assert_eq!(
+        session.eval("a da4 1\n").unwrap().as_deref(),
+        Some("true: bool")
+    );
+    let define = "kuan1 128 wu2fu2 zheng3 da4shu4 can1 jie2 han2 fan3 1 fen1 jie2\n";
+    assert_eq!(session.eval(define).unwrap(), None);
+    assert_eq!(
+        session.eval("ya1 ru4 da4shu4\n").unwrap().as_deref(),
+        Some("1: u128")
+    );
+    // variables can be shadowed by variables of other types
+    assert_eq!(
+        session.eval("zi4dong4 a wei2 a da4 100 fen1\n").unwrap(),
+        None
+    );
+    assert_eq!(session.eval("a\n").unwrap().as_deref(), Some("false: bool"));
+    assert!(matches!(
+        session.eval("zheng3 b wei2\n"),
+        Err(Eval::Incomplete(..))
+    ));
+    assert!(matches!(
+        session.eval("fan3 1 fen1\n"),
+        Err(Eval::Failed(..))
+    ));
+}